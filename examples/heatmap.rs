@@ -0,0 +1,166 @@
+use std::io::{self, Write, BufWriter};
+use std::env;
+
+extern crate lzma;
+
+// the background colors for the cost levels, cheap to expensive
+const ANSI: [u8; 5] = [22, 28, 136, 166, 160];
+const HTML: [&str; 5] = ["#1b5e20", "#43a047", "#fdd835", "#fb8c00", "#e53935"];
+
+#[derive(PartialEq, Eq, Clone, Copy)]
+enum Output {
+	Csv,
+	Ansi,
+	Html,
+}
+
+// maps the bits spent on a byte to a color level
+fn level(bits: f64) -> usize {
+	match bits {
+		b if b < 1.0 => 0,
+		b if b < 2.0 => 1,
+		b if b < 4.0 => 2,
+		b if b < 6.0 => 3,
+		_            => 4,
+	}
+}
+
+// decodes the stream spreading the bits of every packet evenly over the bytes
+// it produces
+fn costs(path: &str) -> (Vec<u8>, Vec<f64>) {
+	let mut reader = lzma::open(path).unwrap();
+	reader.set_cost(true);
+
+	let mut data    = Vec::new();
+	let mut costs   = Vec::new();
+	let mut last    = 0.0;
+	let mut packets = reader.packets().unwrap();
+
+	while let Some(item) = packets.next() {
+		let (_, packet) = item.unwrap();
+		let     total   = packets.get_ref().cost().unwrap();
+		let     bits    = total - last;
+		last = total;
+
+		let bytes = packets.bytes();
+
+		if bytes.is_empty() {
+			// the EOS marker goes to the last byte
+			if let Some(cost) = costs.last_mut() {
+				*cost += bits;
+			}
+
+			continue;
+		}
+
+		data.extend_from_slice(bytes);
+		costs.extend(bytes.iter().map(|_| bits / packet.length() as f64));
+	}
+
+	(data, costs)
+}
+
+fn csv<W: Write>(mut output: W, data: &[u8], costs: &[f64]) -> io::Result<()> {
+	try!(writeln!(output, "offset,byte,bits"));
+
+	for (offset, (&byte, &bits)) in data.iter().zip(costs).enumerate() {
+		try!(writeln!(output, "{},{},{:.3}", offset, byte, bits));
+	}
+
+	Ok(())
+}
+
+fn ansi<W: Write>(mut output: W, data: &[u8], costs: &[f64]) -> io::Result<()> {
+	let mut current = None;
+
+	for (&byte, &bits) in data.iter().zip(costs) {
+		if byte == b'\n' {
+			try!(output.write_all(b"\x1b[0m\n"));
+			current = None;
+
+			continue;
+		}
+
+		let level = level(bits);
+
+		if current != Some(level) {
+			try!(write!(output, "\x1b[48;5;{}m", ANSI[level]));
+			current = Some(level);
+		}
+
+		try!(output.write_all(&[byte]));
+	}
+
+	output.write_all(b"\x1b[0m")
+}
+
+fn html<W: Write>(mut output: W, data: &[u8], costs: &[f64]) -> io::Result<()> {
+	try!(writeln!(output, "<!DOCTYPE html>"));
+	try!(writeln!(output, "<html><head><meta charset=\"utf-8\"><title>heatmap</title></head>"));
+	try!(write!(output, "<body><pre>"));
+
+	let mut start = 0;
+
+	// every run of bytes with the same level is a span, with the total bits
+	// as its title
+	while start < data.len() {
+		let     heat = level(costs[start]);
+		let mut end  = start + 1;
+
+		while end < data.len() && level(costs[end]) == heat {
+			end += 1;
+		}
+
+		let bits: f64 = costs[start .. end].iter().sum();
+		try!(write!(output, "<span style=\"background:{}\" title=\"{:.1} bits for {} bytes\">",
+			HTML[heat], bits, end - start));
+
+		for c in String::from_utf8_lossy(&data[start .. end]).chars() {
+			match c {
+				'<' => try!(output.write_all(b"&lt;")),
+				'>' => try!(output.write_all(b"&gt;")),
+				'&' => try!(output.write_all(b"&amp;")),
+				'"' => try!(output.write_all(b"&quot;")),
+				c   => try!(write!(output, "{}", c)),
+			}
+		}
+
+		try!(output.write_all(b"</span>"));
+		start = end;
+	}
+
+	writeln!(output, "</pre></body></html>")
+}
+
+fn main() {
+	let mut args = env::args();
+	args.next();
+
+	let mut file   = args.next().expect("missing file");
+	let     output = match &file[..] {
+		"-csv"  => Some(Output::Csv),
+		"-ansi" => Some(Output::Ansi),
+		"-html" => Some(Output::Html),
+		_       => None,
+	};
+
+	let output = match output {
+		Some(output) => {
+			file = args.next().expect("missing file");
+			output
+		}
+
+		None =>
+			Output::Ansi
+	};
+
+	let (data, costs) = costs(&file);
+	let stdout = io::stdout();
+	let writer = BufWriter::new(stdout.lock());
+
+	match output {
+		Output::Csv  => csv(writer, &data, &costs),
+		Output::Ansi => ansi(writer, &data, &costs),
+		Output::Html => html(writer, &data, &costs),
+	}.unwrap();
+}
@@ -1,62 +1,81 @@
-use std::io::Read;
+use std::io::{self, BufReader};
+use std::fs::File;
 use std::env;
 
 extern crate lzma;
 
-fn size_for(size: u64) -> String {
-	format!("{} MB ({} bytes)", size / 1024 / 1024, size)
+#[derive(PartialEq, Eq, Clone, Copy)]
+enum Output {
+	Text,
+	Json,
+	Read,
 }
 
-fn dictionary_for(size: u32) -> String {
-	format!("{} MB (2^{} bytes)", size / 1024 / 1024, (size as f32).log2())
+// decodes the whole file, checking everything the format allows
+fn verify(file: &str) -> Result<lzma::Report, lzma::Error> {
+	lzma::verify(BufReader::new(File::open(file).unwrap()))
 }
 
 fn main() {
 	let mut args = env::args();
 	args.next();
 
-	let mut file = args.next().expect("missing file");
-	let     read = if file == "-read" {
-		file = args.next().expect("missing file");
-		true
-	}
-	else {
-		false
+	let mut file   = args.next().expect("missing file");
+	let     output = match &file[..] {
+		"-json" => Some(Output::Json),
+		"-read" => Some(Output::Read),
+		_       => None,
 	};
 
-	let mut decoder    = lzma::open(&file).unwrap();
-	let     properties = *decoder.properties();
+	let output = match output {
+		Some(output) => {
+			file = args.next().expect("missing file");
+			output
+		}
 
-	if let Some(size) = properties.uncompressed {
-		println!("Uncompressed size:\t\t{}", size_for(size));
-	}
-	else {
-		if read {
-			let mut size = 0u64;
-			let mut buf  = [0u8; 4096];
+		None =>
+			Output::Text
+	};
+
+	let info = lzma::info(&file);
+
+	match output {
+		Output::Text =>
+			print!("{}", info.unwrap()),
+
+		Output::Json => {
+			info.unwrap().write_json(io::stdout()).unwrap();
+			println!();
+		}
 
-			loop {
-				match decoder.read(&mut buf) {
-					Ok(0) =>
-						break,
+		// the headers might be what's damaged
+		Output::Read => {
+			match info {
+				Ok(info) => print!("{}", info),
+				Err(err) => println!("Headers:        {}", err),
+			}
 
-					Ok(n) =>
-						size += n as u64,
+			let report = match verify(&file) {
+				Ok(report) =>
+					report,
 
-					Err(_) =>
-						break,
+				Err(err) => {
+					println!("Integrity:      {}", err);
+					return;
 				}
+			};
+
+			println!("Decoded size:   {} B", report.uncompressed);
+			println!("Checksums:      {} verified", report.checksums);
+
+			if let Some(marker) = report.marker {
+				println!("EOS marker:     {}", if marker { "yes" } else { "no" });
 			}
 
-			println!("Uncompressed size:\t\t{}", size_for(size));
-		}
-		else {
-			println!("Uncompressed size:\t\tUnknown");
+			match report.error {
+				Some(err) => println!("Integrity:      {}", err),
+				None      => println!("Integrity:      ok"),
+			}
 		}
 	}
-
-	println!("Dictionary size:\t\t{}", dictionary_for(properties.dictionary));
-	println!("Literal context bits (lc):\t{}", properties.lc);
-	println!("Literal pos bits (lp):\t\t{}", properties.lp);
-	println!("Numebr of pos bits (pb):\t{}", properties.pb);
 }
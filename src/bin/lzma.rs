@@ -0,0 +1,894 @@
+use std::io::{self, Read, Write, BufRead, BufReader, BufWriter, IsTerminal};
+use std::fs::{self, File};
+use std::path::Path;
+use std::rc::Rc;
+use std::cell::Cell;
+use std::process;
+use std::thread;
+use std::env;
+use std::fmt;
+
+extern crate lzma;
+
+use lzma::{Properties, Format};
+use lzma::{xz, lzip, lzma2};
+
+// dictionary size, search depth and nice length of every level
+const LEVELS: [(u32, usize, usize); 10] = [
+	(1 << 18,  4,  32),
+	(1 << 20,  8,  32),
+	(1 << 21, 16,  48),
+	(1 << 22, 24,  64),
+	(1 << 22, 32,  64),
+	(1 << 23, 32,  64),
+	(1 << 23, 48,  96),
+	(1 << 24, 64, 128),
+	(1 << 25, 96, 192),
+	(1 << 26, 128, 273),
+];
+
+const USAGE: &str = "Usage: lzma [OPTION]... [FILE]...
+Compress or decompress FILEs in the .lzma, .xz or .lz format.
+
+  -z, --compress      force compression
+  -d, --decompress    force decompression
+  -t, --test          test compressed file integrity
+  -l, --list          list information about compressed files
+  -k, --keep          keep (don't delete) input files
+  -f, --force         force overwrite of output file
+  -c, --stdout        write to standard output and don't delete input files
+  -0 ... -9           compression preset; default is 6
+  -e, --extreme       try to improve compression ratio by using more CPU time
+  -T, --threads=NUM   use at most NUM threads, 0 for one per processor
+  -F, --format=FMT    file format to encode or decode; possible values are
+                      'auto' (default), 'lzma', 'xz', 'lzip' and 'raw'
+      --dict=SIZE     dictionary size, overriding the preset when compressing;
+                      raw data is decoded with the largest preset's by default
+  -S, --suffix=.SUF   use the suffix '.SUF' on compressed files
+  -v, --verbose       be verbose, --list shows every stream, block and member
+      --json          with --list, print a JSON array of the files
+  -h, --help          display this help and exit
+  -V, --version       display the version number and exit
+
+With no FILE, or when FILE is -, read standard input.";
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+enum Mode {
+	Compress,
+	Decompress,
+	Test,
+	List,
+}
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+enum Container {
+	Auto,
+	Lzma,
+	Xz,
+	Lzip,
+	Raw,
+}
+
+#[derive(Debug)]
+struct Options {
+	mode:    Mode,
+	format:  Container,
+	default: Container,
+	level:   usize,
+	extreme: bool,
+	threads: usize,
+	suffix:  Option<String>,
+	dict:    Option<u32>,
+
+	keep:    bool,
+	stdout:  bool,
+	force:   bool,
+	verbose: bool,
+	json:    bool,
+
+	files: Vec<String>,
+}
+
+// an error message about a file or the command line
+#[derive(Debug)]
+struct Failure(String);
+
+impl From<io::Error> for Failure {
+	fn from(value: io::Error) -> Failure {
+		Failure(value.to_string())
+	}
+}
+
+impl From<lzma::Error> for Failure {
+	fn from(value: lzma::Error) -> Failure {
+		Failure(value.to_string())
+	}
+}
+
+impl fmt::Display for Failure {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.write_str(&self.0)
+	}
+}
+
+// a stream keeping count of the bytes going through it, the count is shared
+// so it can be read once the stream is owned by a decoder
+struct Counted<T> {
+	inner: T,
+	count: Rc<Cell<u64>>,
+}
+
+impl<T> Counted<T> {
+	fn new(inner: T) -> (Counted<T>, Rc<Cell<u64>>) {
+		let count = Rc::new(Cell::new(0));
+
+		(Counted { inner: inner, count: count.clone() }, count)
+	}
+
+	fn add(&self, amount: usize) {
+		self.count.set(self.count.get() + amount as u64);
+	}
+}
+
+impl<T: Read> Read for Counted<T> {
+	fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+		let read = try!(self.inner.read(buf));
+		self.add(read);
+
+		Ok(read)
+	}
+}
+
+impl<T: BufRead> BufRead for Counted<T> {
+	fn fill_buf(&mut self) -> io::Result<&[u8]> {
+		self.inner.fill_buf()
+	}
+
+	fn consume(&mut self, amount: usize) {
+		self.inner.consume(amount);
+		self.add(amount);
+	}
+}
+
+impl<T: Write> Write for Counted<T> {
+	fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+		let written = try!(self.inner.write(buf));
+		self.add(written);
+
+		Ok(written)
+	}
+
+	fn flush(&mut self) -> io::Result<()> {
+		self.inner.flush()
+	}
+}
+
+// the behaviour depends on the name the program was invoked as
+fn defaults(name: &str) -> Options {
+	let (mode, format, stdout) = match name {
+		"unlzma" => (Mode::Decompress, Container::Lzma, false),
+		"lzcat"  => (Mode::Decompress, Container::Lzma, true),
+		"unxz"   => (Mode::Decompress, Container::Auto, false),
+		"xzcat"  => (Mode::Decompress, Container::Auto, true),
+		_        => (Mode::Compress, Container::Auto, false),
+	};
+
+	Options {
+		mode:    mode,
+		format:  format,
+		default: if name.contains("xz") { Container::Xz } else { Container::Lzma },
+		level:   6,
+		extreme: false,
+		threads: 1,
+		suffix:  None,
+		dict:    None,
+
+		keep:    false,
+		stdout:  stdout,
+		force:   false,
+		verbose: false,
+		json:    false,
+
+		files: Vec::new(),
+	}
+}
+
+fn parse<I: Iterator<Item = String>>(mut args: I) -> Result<Options, Failure> {
+	let     name    = args.next().unwrap_or_default();
+	let     name    = Path::new(&name).file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default();
+	let mut options = defaults(&name);
+
+	while let Some(arg) = args.next() {
+		if arg == "--" {
+			options.files.extend(args.by_ref());
+			break;
+		}
+
+		if let Some(long) = arg.strip_prefix("--") {
+			let (flag, value) = match long.find('=') {
+				Some(index) => (&long[.. index], Some(long[index + 1 ..].to_owned())),
+				None        => (long, None),
+			};
+
+			let value = match flag {
+				"threads" | "format" | "suffix" | "dict" => match value {
+					Some(value) => Some(value),
+					None        => Some(try!(args.next().ok_or_else(|| Failure(format!("option '--{}' requires an argument", flag))))),
+				},
+
+				_ => {
+					if value.is_some() {
+						return Err(Failure(format!("option '--{}' doesn't allow an argument", flag)));
+					}
+
+					None
+				}
+			};
+
+			try!(option(&mut options, flag, value));
+			continue;
+		}
+
+		if arg.len() < 2 || !arg.starts_with('-') {
+			options.files.push(arg);
+			continue;
+		}
+
+		// short options can be grouped, the ones taking a value take the rest
+		// of the group or the next argument
+		for (index, c) in arg[1 ..].char_indices() {
+			let flag = match c {
+				'z' => "compress",
+				'd' => "decompress",
+				't' => "test",
+				'l' => "list",
+				'k' => "keep",
+				'f' => "force",
+				'c' => "stdout",
+				'e' => "extreme",
+				'v' => "verbose",
+				'h' => "help",
+				'V' => "version",
+				'T' => "threads",
+				'F' => "format",
+				'S' => "suffix",
+
+				'0' ..= '9' => {
+					options.level = c as usize - '0' as usize;
+					continue;
+				}
+
+				c =>
+					return Err(Failure(format!("invalid option -- '{}'", c)))
+			};
+
+			if flag == "threads" || flag == "format" || flag == "suffix" {
+				let rest  = &arg[index + 2 ..];
+				let value = if rest.is_empty() {
+					try!(args.next().ok_or_else(|| Failure(format!("option requires an argument -- '{}'", c))))
+				}
+				else {
+					rest.to_owned()
+				};
+
+				try!(option(&mut options, flag, Some(value)));
+				break;
+			}
+
+			try!(option(&mut options, flag, None));
+		}
+	}
+
+	Ok(options)
+}
+
+fn option(options: &mut Options, flag: &str, value: Option<String>) -> Result<(), Failure> {
+	match flag {
+		"compress" =>
+			options.mode = Mode::Compress,
+
+		"decompress" | "uncompress" =>
+			options.mode = Mode::Decompress,
+
+		"test" =>
+			options.mode = Mode::Test,
+
+		"list" =>
+			options.mode = Mode::List,
+
+		"keep" =>
+			options.keep = true,
+
+		"force" =>
+			options.force = true,
+
+		"stdout" | "to-stdout" =>
+			options.stdout = true,
+
+		"extreme" =>
+			options.extreme = true,
+
+		"fast" =>
+			options.level = 0,
+
+		"best" =>
+			options.level = 9,
+
+		"verbose" =>
+			options.verbose = true,
+
+		"json" =>
+			options.json = true,
+
+		"help" => {
+			println!("{}", USAGE);
+			process::exit(0);
+		}
+
+		"version" => {
+			println!("lzma {}", env!("CARGO_PKG_VERSION"));
+			process::exit(0);
+		}
+
+		"threads" => {
+			let value = value.unwrap();
+
+			options.threads = match value.parse::<usize>() {
+				Ok(0) =>
+					thread::available_parallelism().map(|n| n.get()).unwrap_or(1),
+
+				Ok(n) =>
+					n,
+
+				Err(_) =>
+					return Err(Failure(format!("{}: invalid amount of threads", value)))
+			};
+		}
+
+		"format" => {
+			options.format = match &value.unwrap()[..] {
+				"auto" => Container::Auto,
+				"lzma" | "alone" => Container::Lzma,
+				"xz"   => Container::Xz,
+				"lzip" | "lz" => Container::Lzip,
+				"raw"  => Container::Raw,
+
+				other =>
+					return Err(Failure(format!("{}: unknown file format type", other)))
+			};
+		}
+
+		"dict" => {
+			let value = value.unwrap();
+
+			options.dict = match parse_size(&value) {
+				Some(size) if (1 << 12 ..= 3 << 29).contains(&size) =>
+					Some(size as u32),
+
+				_ =>
+					return Err(Failure(format!("{}: invalid dictionary size", value)))
+			};
+		}
+
+		"suffix" => {
+			let value = value.unwrap();
+
+			if value.is_empty() || value.contains('/') {
+				return Err(Failure(format!("{}: invalid filename suffix", value)));
+			}
+
+			options.suffix = Some(if value.starts_with('.') { value } else { format!(".{}", value) });
+		}
+
+		flag =>
+			return Err(Failure(format!("unrecognized option '--{}'", flag)))
+	}
+
+	Ok(())
+}
+
+// parses a size like xz does, with an optional KiB, MiB or GiB suffix
+fn parse_size(value: &str) -> Option<u64> {
+	let digits = value.find(|c: char| !c.is_ascii_digit()).unwrap_or(value.len());
+	let number = match value[.. digits].parse::<u64>() {
+		Ok(number) => number,
+		Err(_)     => return None,
+	};
+
+	let shift = match &value[digits ..] {
+		""                => 0,
+		"k" | "K" | "KiB" => 10,
+		"m" | "M" | "MiB" => 20,
+		"g" | "G" | "GiB" => 30,
+		_                 => return None,
+	};
+
+	number.checked_mul(1 << shift)
+}
+
+impl Options {
+	// the format to compress to
+	fn target(&self) -> Container {
+		match self.format {
+			Container::Auto => self.default,
+			format          => format,
+		}
+	}
+
+	fn properties(&self) -> Properties {
+		Properties {
+			lc: 3,
+			lp: 0,
+			pb: 2,
+
+			dictionary:   self.dict.unwrap_or(LEVELS[self.level].0),
+			uncompressed: None,
+		}
+	}
+
+	fn search(&self) -> (usize, usize) {
+		let (_, depth, nice) = LEVELS[self.level];
+
+		if self.extreme {
+			(depth * 4, 273)
+		}
+		else {
+			(depth, nice)
+		}
+	}
+
+	// the suffix of compressed files
+	fn suffix(&self) -> Result<&str, Failure> {
+		if let Some(ref suffix) = self.suffix {
+			return Ok(suffix);
+		}
+
+		match self.target() {
+			Container::Xz   => Ok(".xz"),
+			Container::Lzip => Ok(".lz"),
+			Container::Raw  => Err(Failure("with --format=raw, --suffix=.SUF is required unless writing to stdout".to_owned())),
+			_               => Ok(".lzma"),
+		}
+	}
+
+	// the name of the compressed file
+	fn compressed_name(&self, file: &str) -> Result<String, Failure> {
+		let suffix = try!(self.suffix());
+
+		for known in &[suffix, ".lzma", ".xz", ".lz", ".tlz", ".txz"] {
+			if file.ends_with(known) && file.len() > known.len() {
+				return Err(Failure(format!("{}: file already has '{}' suffix, skipping", file, known)));
+			}
+		}
+
+		Ok(format!("{}{}", file, suffix))
+	}
+
+	// the name of the decompressed file
+	fn decompressed_name(&self, file: &str) -> Result<String, Failure> {
+		let mut known = match self.format {
+			Container::Lzma => vec![(".lzma", ""), (".tlz", ".tar")],
+			Container::Xz   => vec![(".xz", ""), (".txz", ".tar")],
+			Container::Lzip => vec![(".lz", ""), (".tlz", ".tar")],
+			Container::Raw  => vec![],
+			Container::Auto => vec![(".lzma", ""), (".xz", ""), (".lz", ""), (".tlz", ".tar"), (".txz", ".tar")],
+		};
+
+		if let Some(ref suffix) = self.suffix {
+			known.insert(0, (suffix, ""));
+		}
+
+		for &(suffix, replacement) in &known {
+			if file.ends_with(suffix) && file.len() > suffix.len() {
+				return Ok(format!("{}{}", &file[.. file.len() - suffix.len()], replacement));
+			}
+		}
+
+		Err(Failure(format!("{}: filename has an unknown suffix, skipping", file)))
+	}
+}
+
+fn compress<W: Write>(options: &Options, mut input: Box<dyn BufRead>, output: W) -> Result<W, Failure> {
+	let properties    = options.properties();
+	let (depth, nice) = options.search();
+
+	Ok(match options.target() {
+		Container::Xz => {
+			let mut writer = try!(xz::Writer::new(output, properties, options.threads));
+			writer.set_search(depth, nice);
+			try!(io::copy(&mut input, &mut writer));
+			try!(writer.finish())
+		}
+
+		Container::Lzip => {
+			let mut writer = try!(lzip::write(output, properties.dictionary));
+			writer.set_search(depth, nice);
+			try!(io::copy(&mut input, &mut writer));
+			try!(writer.finish())
+		}
+
+		Container::Raw => {
+			let mut writer = try!(lzma2::write(output, properties));
+			writer.set_search(depth, nice);
+			try!(io::copy(&mut input, &mut writer));
+			try!(writer.finish())
+		}
+
+		_ => {
+			let mut writer = try!(lzma::write(output, properties));
+			writer.set_search(depth, nice);
+			try!(io::copy(&mut input, &mut writer));
+			try!(writer.finish())
+		}
+	})
+}
+
+fn decoder(options: &Options, mut input: Box<dyn BufRead>) -> Result<Box<dyn Read>, Failure> {
+	let format = match options.format {
+		Container::Auto => match lzma::detect(try!(input.fill_buf())) {
+			Some(Format::Xz) => Container::Xz,
+			Some(_)          => return Ok(try!(lzma::read_any(input))),
+			None             => return Err(Failure("file format not recognized".to_owned())),
+		},
+
+		format =>
+			format
+	};
+
+	Ok(match format {
+		Container::Xz if options.threads > 1 =>
+			Box::new(try!(xz::Parallel::new(input, options.threads))),
+
+		Container::Xz =>
+			Box::new(try!(xz::read(input))),
+
+		Container::Lzip =>
+			Box::new(try!(lzip::read(input))),
+
+		// nothing in raw data tells the dictionary size, the largest preset's
+		// decodes anything compressed without --dict
+		Container::Raw =>
+			Box::new(try!(lzma2::read(input, options.dict.unwrap_or(LEVELS[9].0)))),
+
+		_ =>
+			Box::new(try!(lzma::Reader::from(input))),
+	})
+}
+
+fn decompress<W: Write>(options: &Options, input: Box<dyn BufRead>, mut output: W) -> Result<W, Failure> {
+	let mut decoder = try!(decoder(options, input));
+	try!(io::copy(&mut decoder, &mut output));
+
+	Ok(output)
+}
+
+// compresses or decompresses depending on the mode
+fn convert<W: Write>(options: &Options, input: Box<dyn BufRead>, output: W) -> Result<W, Failure> {
+	match options.mode {
+		Mode::Compress => compress(options, input, output),
+		_              => decompress(options, input, output),
+	}
+}
+
+fn open(file: Option<&str>) -> Result<Box<dyn BufRead>, Failure> {
+	match file {
+		Some(file) => {
+			let metadata = try!(fs::metadata(file).map_err(|err| Failure(format!("{}: {}", file, err))));
+
+			if !metadata.is_file() {
+				return Err(Failure(format!("{}: not a regular file, skipping", file)));
+			}
+
+			Ok(Box::new(BufReader::new(try!(File::open(file).map_err(|err| Failure(format!("{}: {}", file, err)))))))
+		}
+
+		None =>
+			Ok(Box::new(io::stdin().lock()))
+	}
+}
+
+// writes the output to a new file, removing it if anything fails since a
+// partial output is worse than none
+fn create(options: &Options, name: &str, input: Box<dyn BufRead>, path: &str) -> Result<u64, Failure> {
+	if Path::new(path).exists() {
+		if !options.force {
+			return Err(Failure(format!("{}: file exists", path)));
+		}
+
+		try!(fs::remove_file(path).map_err(|err| Failure(format!("{}: {}", path, err))));
+	}
+
+	let file            = try!(File::create(path).map_err(|err| Failure(format!("{}: {}", path, err))));
+	let (output, count) = Counted::new(BufWriter::new(file));
+
+	let result = convert(options, input, output).and_then(|output|
+		output.inner.into_inner().map_err(|err| Failure::from(err.into_error()))).map_err(|err|
+			Failure(format!("{}: {}", name, err)));
+
+	match result {
+		Ok(_) =>
+			Ok(count.get()),
+
+		Err(err) => {
+			let _ = fs::remove_file(path);
+			Err(err)
+		}
+	}
+}
+
+// copies the permissions and modification time of the input to the output
+fn preserve(input: &str, output: &str) -> Result<(), Failure> {
+	let metadata = try!(fs::metadata(input));
+	let file     = try!(File::options().write(true).open(output));
+
+	try!(file.set_permissions(metadata.permissions()));
+
+	if let Ok(modified) = metadata.modified() {
+		try!(file.set_modified(modified));
+	}
+
+	Ok(())
+}
+
+// compresses or decompresses a file, or the standard input when there's none
+fn transform(options: &Options, file: Option<&str>) -> Result<(), Failure> {
+	let name = file.unwrap_or("(stdin)");
+
+	if file.is_none() && options.mode == Mode::Decompress && io::stdin().is_terminal() && !options.force {
+		return Err(Failure("compressed data cannot be read from a terminal".to_owned()));
+	}
+
+	let output = match file {
+		Some(file) if !options.stdout => Some(try!(match options.mode {
+			Mode::Compress => options.compressed_name(file),
+			_              => options.decompressed_name(file),
+		})),
+
+		_ =>
+			None
+	};
+
+	if output.is_none() && options.mode == Mode::Compress && io::stdout().is_terminal() && !options.force {
+		return Err(Failure("compressed data cannot be written to a terminal".to_owned()));
+	}
+
+	let (input, read) = Counted::new(try!(open(file)));
+
+	let written = match output {
+		Some(ref path) => {
+			let written = try!(create(options, name, Box::new(input), path));
+			try!(preserve(name, path).map_err(|err| Failure(format!("{}: {}", path, err))));
+
+			written
+		}
+
+		None => {
+			let stdout          = io::stdout();
+			let (output, count) = Counted::new(stdout.lock());
+			let mut output      = try!(convert(options, Box::new(input), output).map_err(|err| Failure(format!("{}: {}", name, err))));
+			try!(output.flush());
+
+			count.get()
+		}
+	};
+
+	if options.verbose {
+		report(name, options.mode, read.get(), written);
+	}
+
+	if let (Some(file), Some(_)) = (file, output) {
+		if !options.keep {
+			try!(fs::remove_file(file).map_err(|err| Failure(format!("{}: {}", file, err))));
+		}
+	}
+
+	Ok(())
+}
+
+// decodes the file without writing anything, returning the compressed and
+// decompressed sizes
+fn test(options: &Options, file: Option<&str>) -> Result<(u64, u64), Failure> {
+	let name              = file.unwrap_or("(stdin)");
+	let (mut input, read) = Counted::new(try!(open(file)));
+	let (output, size)    = Counted::new(io::sink());
+
+	// verifying checks more than decoding does and skips the output entirely,
+	// the formats it doesn't handle go through the usual decoder
+	if options.format == Container::Auto {
+		match lzma::verify(&mut input) {
+			Ok(report) => match (report.error, report.failure) {
+				(Some(err), Some(failure)) =>
+					return Err(Failure(format!("{}: {} ({})", name, err, failure))),
+
+				(Some(err), None) =>
+					return Err(Failure(format!("{}: {}", name, err))),
+
+				(None, _) =>
+					return Ok((report.compressed, report.uncompressed))
+			},
+
+			Err(lzma::Error::Unsupported) =>
+				(),
+
+			Err(err) =>
+				return Err(Failure(format!("{}: {}", name, err)))
+		}
+	}
+
+	try!(decompress(options, Box::new(input), output).map_err(|err| Failure(format!("{}: {}", name, err))));
+
+	Ok((read.get(), size.get()))
+}
+
+fn list(options: &Options) -> i32 {
+	if options.files.is_empty() || options.files.iter().any(|file| file == "-") {
+		eprintln!("lzma: --list does not support reading from standard input");
+		return 1;
+	}
+
+	let mut status = 0;
+	let mut first  = true;
+
+	if options.json {
+		print!("[");
+	}
+	else if !options.verbose {
+		println!("{:>5} {:>6} {:>12} {:>12} {:>7}  {:<11}  Filename", "Strms", "Blocks", "Compressed", "Uncompressed", "Ratio", "Check");
+	}
+
+	for file in &options.files {
+		let info = match lzma::info(file) {
+			Ok(info) =>
+				info,
+
+			Err(err) => {
+				eprintln!("lzma: {}: {}", file, err);
+				status = 1;
+
+				continue;
+			}
+		};
+
+		if options.json {
+			print!("{}{{\"file\":{},\"info\":", if first { "" } else { "," }, quote(file));
+
+			let stdout = io::stdout();
+			info.write_json(stdout.lock()).unwrap();
+
+			print!("}}");
+		}
+		else if options.verbose {
+			println!("{}{}:", if first { "" } else { "\n" }, file);
+			print!("{}", info);
+		}
+		else {
+			let (streams, blocks, check) = match info {
+				lzma::Info::Xz(ref streams) => (
+					streams.len().to_string(),
+					streams.iter().map(|stream| stream.blocks.len()).sum::<usize>().to_string(),
+					checks(streams)),
+
+				lzma::Info::Lzip(ref members) =>
+					(members.len().to_string(), "-".to_owned(), "CRC32".to_owned()),
+
+				lzma::Info::Lzma(..) =>
+					("-".to_owned(), "-".to_owned(), "-".to_owned()),
+			};
+
+			println!("{:>5} {:>6} {:>12} {:>12} {:>7}  {:<11}  {}", streams, blocks, size(info.compressed()),
+				info.uncompressed().map(size).unwrap_or_else(|| "Unknown".to_owned()),
+				info.uncompressed().map(|uncompressed| ratio(info.compressed(), uncompressed)).unwrap_or_else(|| "---".to_owned()),
+				check, file);
+		}
+
+		first = false;
+	}
+
+	if options.json {
+		println!("]");
+	}
+
+	status
+}
+
+// the checks used by the streams, in order of appearance
+fn checks(streams: &[lzma::info::Stream]) -> String {
+	let mut checks = Vec::new();
+
+	for stream in streams {
+		if !checks.contains(&stream.check) {
+			checks.push(stream.check);
+		}
+	}
+
+	checks.iter().map(|check| check.to_string()).collect::<Vec<_>>().join(",")
+}
+
+// a JSON string
+fn quote(value: &str) -> String {
+	let mut result = String::from("\"");
+
+	for c in value.chars() {
+		match c {
+			'"'  => result.push_str("\\\""),
+			'\\' => result.push_str("\\\\"),
+			c if (c as u32) < 0x20 => result.push_str(&format!("\\u{:04x}", c as u32)),
+			c    => result.push(c),
+		}
+	}
+
+	result.push('"');
+	result
+}
+
+fn report(name: &str, mode: Mode, read: u64, written: u64) {
+	let (compressed, uncompressed) = match mode {
+		Mode::Compress => (written, read),
+		_              => (read, written),
+	};
+
+	eprintln!("{}: {} / {} = {}", name, size(compressed), size(uncompressed), ratio(compressed, uncompressed));
+}
+
+fn size(bytes: u64) -> String {
+	match bytes {
+		b if b < 1 << 10 => format!("{} B", b),
+		b if b < 1 << 20 => format!("{:.1} KiB", b as f64 / 1024.0),
+		b if b < 1 << 30 => format!("{:.1} MiB", b as f64 / 1048576.0),
+		b                => format!("{:.1} GiB", b as f64 / 1073741824.0),
+	}
+}
+
+fn ratio(compressed: u64, uncompressed: u64) -> String {
+	if uncompressed == 0 {
+		"---".to_owned()
+	}
+	else {
+		format!("{:.3}", compressed as f64 / uncompressed as f64)
+	}
+}
+
+fn main() {
+	let options = match parse(env::args()) {
+		Ok(options) =>
+			options,
+
+		Err(err) => {
+			eprintln!("lzma: {}", err);
+			eprintln!("Try 'lzma --help' for more information.");
+			process::exit(1);
+		}
+	};
+
+	if options.mode == Mode::List {
+		process::exit(list(&options));
+	}
+
+	let files = if options.files.is_empty() {
+		vec![None]
+	}
+	else {
+		options.files.iter().map(|file| if file == "-" { None } else { Some(&file[..]) }).collect()
+	};
+
+	let mut status = 0;
+
+	for file in files {
+		let result = match options.mode {
+			Mode::Test => test(&options, file).map(|(compressed, uncompressed)|
+				if options.verbose {
+					report(file.unwrap_or("(stdin)"), Mode::Test, compressed, uncompressed);
+				}),
+
+			_ =>
+				transform(&options, file)
+		};
+
+		if let Err(err) = result {
+			eprintln!("lzma: {}", err);
+			status = 1;
+		}
+	}
+
+	process::exit(status);
+}
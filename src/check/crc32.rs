@@ -0,0 +1,71 @@
+const POLYNOMIAL: u32 = 0xedb88320;
+
+// the CRC of every byte value, computed at compile time
+const TABLE: [u32; 256] = table();
+
+const fn table() -> [u32; 256] {
+	let mut table = [0u32; 256];
+	let mut i     = 0;
+
+	while i < 256 {
+		let mut value = i as u32;
+		let mut bit   = 0;
+
+		while bit < 8 {
+			value = if value & 1 == 1 {
+				(value >> 1) ^ POLYNOMIAL
+			}
+			else {
+				value >> 1
+			};
+
+			bit += 1;
+		}
+
+		table[i] = value;
+		i       += 1;
+	}
+
+	table
+}
+
+/// A CRC32 hasher, as used by xz, lzip and ZIP.
+#[derive(Clone, Debug)]
+pub struct Crc32 {
+	value: u32,
+}
+
+impl Crc32 {
+	/// Creates a new hasher.
+	pub fn new() -> Self {
+		Crc32 {
+			value: 0xffffffff,
+		}
+	}
+
+	/// Updates the hash with the given data.
+	pub fn update(&mut self, data: &[u8]) {
+		for &byte in data {
+			self.value = TABLE[((self.value ^ byte as u32) & 0xff) as usize] ^ (self.value >> 8);
+		}
+	}
+
+	/// Gets the hash value.
+	pub fn value(&self) -> u32 {
+		!self.value
+	}
+}
+
+impl Default for Crc32 {
+	fn default() -> Self {
+		Crc32::new()
+	}
+}
+
+/// Computes the CRC32 of the given data.
+pub fn crc32(data: &[u8]) -> u32 {
+	let mut hasher = Crc32::new();
+	hasher.update(data);
+
+	hasher.value()
+}
@@ -0,0 +1,63 @@
+const POLYNOMIAL: u64 = 0xc96c5795d7870f42;
+
+// the CRC of every byte value, computed at compile time
+const TABLE: [u64; 256] = table();
+
+const fn table() -> [u64; 256] {
+	let mut table = [0u64; 256];
+	let mut i     = 0;
+
+	while i < 256 {
+		let mut value = i as u64;
+		let mut bit   = 0;
+
+		while bit < 8 {
+			value = if value & 1 == 1 {
+				(value >> 1) ^ POLYNOMIAL
+			}
+			else {
+				value >> 1
+			};
+
+			bit += 1;
+		}
+
+		table[i] = value;
+		i       += 1;
+	}
+
+	table
+}
+
+/// A CRC64 hasher, as used by xz.
+#[derive(Clone, Debug)]
+pub struct Crc64 {
+	value: u64,
+}
+
+impl Crc64 {
+	/// Creates a new hasher.
+	pub fn new() -> Self {
+		Crc64 {
+			value: 0xffffffffffffffff,
+		}
+	}
+
+	/// Updates the hash with the given data.
+	pub fn update(&mut self, data: &[u8]) {
+		for &byte in data {
+			self.value = TABLE[((self.value ^ byte as u64) & 0xff) as usize] ^ (self.value >> 8);
+		}
+	}
+
+	/// Gets the hash value.
+	pub fn value(&self) -> u64 {
+		!self.value
+	}
+}
+
+impl Default for Crc64 {
+	fn default() -> Self {
+		Crc64::new()
+	}
+}
@@ -0,0 +1,8 @@
+mod crc32;
+pub use self::crc32::{Crc32, crc32};
+
+mod crc64;
+pub use self::crc64::Crc64;
+
+mod sha256;
+pub use self::sha256::Sha256;
@@ -33,3 +33,6 @@ pub const LENGTH_TO_POSITION_STATES: usize = 4;
 
 ///
 pub const MATCH_MINIMUM_LENGTH: usize = 2;
+
+///
+pub const MATCH_MAXIMUM_LENGTH: usize = MATCH_MINIMUM_LENGTH + 271;
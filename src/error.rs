@@ -14,6 +14,24 @@ pub enum Error {
 	/// Invalid model values.
 	InvalidProperties,
 
+	/// Invalid container header.
+	InvalidHeader,
+
+	/// The stream uses features that are not supported.
+	Unsupported,
+
+	/// The integrity check failed.
+	Checksum,
+
+	/// Decoding needs more memory than allowed.
+	MemoryLimit {
+		/// The memory needed, in bytes.
+		needed: u64,
+
+		/// The memory limit, in bytes.
+		limit: u64,
+	},
+
 	/// The EOS marker is missing.
 	MissingMarker,
 
@@ -25,6 +43,189 @@ pub enum Error {
 
 	/// The stream has finished unexpectedly with a marker.
 	FinishedWithMarker,
+
+	/// The operation isn't allowed at this point, like changing settings after
+	/// writing has started.
+	InvalidState,
+}
+
+/// Where and why decoding failed, see `Reader::failure`.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub struct Failure {
+	/// Where decoding failed.
+	pub position: Position,
+
+	/// Why decoding failed.
+	pub cause: Cause,
+}
+
+/// Where decoding failed.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub struct Position {
+	/// The amount of compressed bytes read when the failure was detected.
+	pub compressed: u64,
+
+	/// The amount of bytes decoded before the failing packet.
+	pub uncompressed: u64,
+
+	/// The packet being decoded.
+	pub packet: PacketKind,
+}
+
+/// The kind of a LZMA packet.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum PacketKind {
+	/// The kind hasn't been decoded yet.
+	Unknown,
+
+	/// A literal byte.
+	Literal,
+
+	/// A match with a new distance.
+	Match,
+
+	/// A single byte at the last distance.
+	ShortRep,
+
+	/// A match at one of the last four distances, `rep0` to `rep3`.
+	Rep(u8),
+
+	/// The EOS marker.
+	EndMarker,
+}
+
+/// Why decoding failed.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum Cause {
+	/// The first byte of the range coded data isn't zero.
+	FirstByte,
+
+	/// The range decoder code reached its range, which the encoder never
+	/// produces.
+	CodeEqualsRange,
+
+	/// A match distance is beyond the dictionary size.
+	DistanceBeyondDictionary,
+
+	/// A match distance is beyond the decoded data.
+	DistanceBeyondHistory,
+
+	/// The data goes on after the uncompressed size.
+	HasMoreData,
+
+	/// The EOS marker comes before the uncompressed size.
+	NeedMoreData,
+
+	/// The EOS marker comes before the end of the range coded data.
+	UnfinishedRange,
+
+	/// The compressed data ends in the middle of a packet.
+	Truncated,
+}
+
+impl Cause {
+	// the error decoders return for the cause, a truncated stream fails with
+	// the error of the stream instead
+	pub(crate) fn error(&self) -> Error {
+		match self {
+			&Cause::HasMoreData =>
+				Error::HasMoreData,
+
+			&Cause::NeedMoreData | &Cause::UnfinishedRange =>
+				Error::NeedMoreData,
+
+			_ =>
+				Error::Corrupted
+		}
+	}
+}
+
+impl Error {
+	// a copy of the error, for readers that keep returning the one that
+	// stopped them
+	pub(crate) fn duplicate(&self) -> Error {
+		match self {
+			&Error::IO(ref err) => match err.get_ref().and_then(|inner| inner.downcast_ref::<Error>()) {
+				Some(inner) =>
+					Error::IO(io::Error::new(err.kind(), inner.duplicate())),
+
+				None =>
+					Error::IO(io::Error::new(err.kind(), err.to_string()))
+			},
+
+			&Error::Corrupted          => Error::Corrupted,
+			&Error::InvalidProperties  => Error::InvalidProperties,
+			&Error::InvalidHeader      => Error::InvalidHeader,
+			&Error::Unsupported        => Error::Unsupported,
+			&Error::Checksum           => Error::Checksum,
+			&Error::MissingMarker      => Error::MissingMarker,
+			&Error::HasMoreData        => Error::HasMoreData,
+			&Error::NeedMoreData       => Error::NeedMoreData,
+			&Error::FinishedWithMarker => Error::FinishedWithMarker,
+			&Error::InvalidState       => Error::InvalidState,
+
+			&Error::MemoryLimit { needed, limit } =>
+				Error::MemoryLimit { needed: needed, limit: limit },
+		}
+	}
+
+	// gets back the error the readers wrapped
+	pub(crate) fn from_io(err: io::Error) -> Error {
+		if err.get_ref().is_some_and(|inner| inner.is::<Error>()) {
+			*err.into_inner().unwrap().downcast::<Error>().unwrap()
+		}
+		else {
+			Error::IO(err)
+		}
+	}
+
+	// wraps the error for the `Read` and `Write` impls, `from_io` unwraps it
+	pub(crate) fn into_io(self) -> io::Error {
+		match self {
+			Error::IO(err) =>
+				err,
+
+			err =>
+				io::Error::other(err)
+		}
+	}
+}
+
+// a reader that stops at its first failure, see `read`
+pub(crate) trait Sticky {
+	// the error that stopped the reader
+	fn failed(&mut self) -> &mut Option<Error>;
+
+	// decodes into the buffer, which isn't empty
+	fn decode(&mut self, buf: &mut [u8]) -> Result<usize, Error>;
+}
+
+// reads from a reader that stops at its first failure, the decoder state is
+// lost at that point so the error is kept and every later read returns it
+// again, only an interrupted read can be tried again
+pub(crate) fn read<S: Sticky>(reader: &mut S, buf: &mut [u8]) -> io::Result<usize> {
+	if buf.is_empty() {
+		return Ok(0);
+	}
+
+	if let Some(ref err) = *reader.failed() {
+		return Err(err.duplicate().into_io());
+	}
+
+	match reader.decode(buf) {
+		Ok(read) =>
+			Ok(read),
+
+		Err(Error::IO(err)) if err.kind() == io::ErrorKind::Interrupted =>
+			Err(err),
+
+		Err(err) => {
+			let copy = err.duplicate();
+			*reader.failed() = Some(err);
+
+			Err(copy.into_io())
+		}
+	}
 }
 
 impl From<io::Error> for Error {
@@ -39,6 +240,67 @@ impl fmt::Display for Error {
 	}
 }
 
+impl fmt::Display for Failure {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{} at compressed byte {}, uncompressed byte {}, in {}",
+			self.cause, self.position.compressed, self.position.uncompressed, self.position.packet)
+	}
+}
+
+impl fmt::Display for PacketKind {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			&PacketKind::Unknown =>
+				f.write_str("a packet of unknown kind"),
+
+			&PacketKind::Literal =>
+				f.write_str("a literal"),
+
+			&PacketKind::Match =>
+				f.write_str("a match"),
+
+			&PacketKind::ShortRep =>
+				f.write_str("a short repetition"),
+
+			&PacketKind::Rep(index) =>
+				write!(f, "a repetition at rep{}", index),
+
+			&PacketKind::EndMarker =>
+				f.write_str("the EOS marker"),
+		}
+	}
+}
+
+impl fmt::Display for Cause {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.write_str(match self {
+			&Cause::FirstByte =>
+				"the first byte of the range coded data isn't zero",
+
+			&Cause::CodeEqualsRange =>
+				"the range decoder code reached its range",
+
+			&Cause::DistanceBeyondDictionary =>
+				"a distance is beyond the dictionary size",
+
+			&Cause::DistanceBeyondHistory =>
+				"a distance is beyond the decoded data",
+
+			&Cause::HasMoreData =>
+				"the data goes on after the uncompressed size",
+
+			&Cause::NeedMoreData =>
+				"the EOS marker comes before the uncompressed size",
+
+			&Cause::UnfinishedRange =>
+				"the EOS marker comes before the end of the range coded data",
+
+			&Cause::Truncated =>
+				"the compressed data is truncated",
+		})
+	}
+}
+
 impl error::Error for Error {
 	fn description(&self) -> &str {
 		match self {
@@ -51,6 +313,18 @@ impl error::Error for Error {
 			&Error::InvalidProperties =>
 				"Invalid model values.",
 
+			&Error::InvalidHeader =>
+				"Invalid container header.",
+
+			&Error::Unsupported =>
+				"The stream uses features that are not supported.",
+
+			&Error::Checksum =>
+				"The integrity check failed.",
+
+			&Error::MemoryLimit { .. } =>
+				"Decoding needs more memory than allowed.",
+
 			&Error::MissingMarker =>
 				"The EOS marker is missing.",
 
@@ -62,6 +336,9 @@ impl error::Error for Error {
 
 			&Error::FinishedWithMarker =>
 				"The stream has finished unexpectedly with a marker.",
+
+			&Error::InvalidState =>
+				"The operation isn't allowed at this point.",
 		}
 	}
 }
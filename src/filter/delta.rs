@@ -0,0 +1,55 @@
+use super::Filter;
+
+/// The delta filter, storing the difference between bytes at the given
+/// distance.
+#[derive(Clone, Debug)]
+pub struct Delta {
+	encoder:  bool,
+	distance: usize,
+
+	history:  [u8; 256],
+	position: u8,
+}
+
+impl Delta {
+	/// Creates a new delta encoder.
+	pub fn encoder(distance: usize) -> Self {
+		Delta::new(true, distance)
+	}
+
+	/// Creates a new delta decoder.
+	pub fn decoder(distance: usize) -> Self {
+		Delta::new(false, distance)
+	}
+
+	fn new(encoder: bool, distance: usize) -> Self {
+		Delta {
+			encoder:  encoder,
+			distance: distance,
+
+			history:  [0; 256],
+			position: 0,
+		}
+	}
+}
+
+impl Filter for Delta {
+	fn code(&mut self, buffer: &mut [u8]) -> usize {
+		for byte in buffer.iter_mut() {
+			let previous = self.history[(self.distance + self.position as usize) & 0xff];
+
+			if self.encoder {
+				self.history[self.position as usize] = *byte;
+				*byte = byte.wrapping_sub(previous);
+			}
+			else {
+				*byte = byte.wrapping_add(previous);
+				self.history[self.position as usize] = *byte;
+			}
+
+			self.position = self.position.wrapping_sub(1);
+		}
+
+		buffer.len()
+	}
+}
@@ -0,0 +1,20 @@
+mod x86;
+pub use self::x86::X86;
+
+mod delta;
+pub use self::delta::Delta;
+
+mod reader;
+pub use self::reader::Reader;
+
+mod writer;
+pub use self::writer::Writer;
+
+/// A branch converter.
+pub trait Filter {
+	/// Converts the buffer in place, returning the amount of bytes converted.
+	///
+	/// The bytes that are left must be given again followed by more data, or
+	/// kept as they are at the end of the stream.
+	fn code(&mut self, buffer: &mut [u8]) -> usize;
+}
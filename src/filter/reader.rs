@@ -0,0 +1,102 @@
+use std::io::{self, Read};
+use std::cmp;
+
+use super::Filter;
+
+const BUFFER_SIZE: usize = 4096;
+
+/// A stream reader applying a filter.
+#[derive(Debug)]
+pub struct Reader<R: Read, F: Filter> {
+	stream: R,
+	filter: F,
+
+	// data in the buffer is converted up to `converted` and consumed up to
+	// `offset`, what's after `converted` is waiting for more data
+	buffer:    Vec<u8>,
+	offset:    usize,
+	converted: usize,
+
+	finished: bool,
+}
+
+impl<R: Read, F: Filter> Reader<R, F> {
+	/// Creates a filtered reader from the given stream.
+	pub fn new(stream: R, filter: F) -> Reader<R, F> {
+		Reader {
+			stream: stream,
+			filter: filter,
+
+			buffer:    Vec::with_capacity(BUFFER_SIZE),
+			offset:    0,
+			converted: 0,
+
+			finished: false,
+		}
+	}
+
+	/// Returns the inner stream.
+	pub fn get_ref(&self) -> &R {
+		&self.stream
+	}
+
+	/// Unwraps this `Reader`, returning the underlying reader.
+	///
+	/// Note that any leftover data in the internal buffer is lost.
+	pub fn into_inner(self) -> R {
+		self.stream
+	}
+
+	fn fill(&mut self) -> io::Result<()> {
+		self.buffer.drain(.. self.offset);
+		self.converted -= self.offset;
+		self.offset     = 0;
+
+		while !self.finished && self.buffer.len() < BUFFER_SIZE {
+			let length = self.buffer.len();
+			self.buffer.resize(BUFFER_SIZE, 0);
+
+			match self.stream.read(&mut self.buffer[length ..]) {
+				Ok(0) => {
+					self.buffer.truncate(length);
+					self.finished = true;
+				}
+
+				Ok(n) =>
+					self.buffer.truncate(length + n),
+
+				Err(ref err) if err.kind() == io::ErrorKind::Interrupted =>
+					self.buffer.truncate(length),
+
+				Err(err) => {
+					self.buffer.truncate(length);
+					return Err(err);
+				}
+			}
+		}
+
+		self.converted += self.filter.code(&mut self.buffer[self.converted ..]);
+
+		// the leftovers are never going to be complete
+		if self.finished {
+			self.converted = self.buffer.len();
+		}
+
+		Ok(())
+	}
+}
+
+impl<R: Read, F: Filter> Read for Reader<R, F> {
+	fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+		if self.offset == self.converted {
+			try!(self.fill());
+		}
+
+		let length = cmp::min(buf.len(), self.converted - self.offset);
+
+		buf[.. length].copy_from_slice(&self.buffer[self.offset .. self.offset + length]);
+		self.offset += length;
+
+		Ok(length)
+	}
+}
@@ -0,0 +1,53 @@
+use std::io::{self, Write};
+
+use super::Filter;
+
+/// A stream writer applying a filter.
+#[derive(Debug)]
+pub struct Writer<W: Write, F: Filter> {
+	stream: W,
+	filter: F,
+
+	// bytes waiting for more data before they can be converted
+	buffer: Vec<u8>,
+}
+
+impl<W: Write, F: Filter> Writer<W, F> {
+	/// Creates a filtered writer to the given stream.
+	pub fn new(stream: W, filter: F) -> Writer<W, F> {
+		Writer {
+			stream: stream,
+			filter: filter,
+
+			buffer: Vec::new(),
+		}
+	}
+
+	/// Returns the inner stream.
+	pub fn get_ref(&self) -> &W {
+		&self.stream
+	}
+
+	/// Writes the leftovers as they are, returning the underlying writer.
+	pub fn finish(mut self) -> io::Result<W> {
+		try!(self.stream.write_all(&self.buffer));
+
+		Ok(self.stream)
+	}
+}
+
+impl<W: Write, F: Filter> Write for Writer<W, F> {
+	fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+		self.buffer.extend_from_slice(buf);
+
+		let converted = self.filter.code(&mut self.buffer);
+		try!(self.stream.write_all(&self.buffer[.. converted]));
+		self.buffer.drain(.. converted);
+
+		Ok(buf.len())
+	}
+
+	fn flush(&mut self) -> io::Result<()> {
+		self.stream.flush()
+	}
+}
@@ -0,0 +1,131 @@
+use super::Filter;
+
+const ALLOWED: [bool; 8] = [true, true, true, false, true, false, false, false];
+const BITS:    [u32; 8]  = [0, 1, 2, 2, 3, 3, 3, 3];
+
+fn is_msb(byte: u8) -> bool {
+	byte == 0 || byte == 0xff
+}
+
+/// The x86 BCJ filter, converting the relative addresses of `call` and `jmp`
+/// instructions.
+#[derive(Clone, Debug)]
+pub struct X86 {
+	encoder: bool,
+
+	position: u32,
+	previous: u32,
+	mask:     u32,
+}
+
+impl X86 {
+	/// Creates a new x86 encoder.
+	pub fn encoder() -> Self {
+		X86::new(true)
+	}
+
+	/// Creates a new x86 decoder.
+	pub fn decoder() -> Self {
+		X86::new(false)
+	}
+
+	fn new(encoder: bool) -> Self {
+		X86 {
+			encoder: encoder,
+
+			position: 0,
+			previous: 0u32.wrapping_sub(5),
+			mask:     0,
+		}
+	}
+}
+
+impl Filter for X86 {
+	fn code(&mut self, buffer: &mut [u8]) -> usize {
+		if buffer.len() < 5 {
+			return 0;
+		}
+
+		if self.position.wrapping_sub(self.previous) > 5 {
+			self.previous = self.position.wrapping_sub(5);
+		}
+
+		let limit = buffer.len() - 5;
+		let mut i = 0;
+
+		while i <= limit {
+			let byte = buffer[i];
+
+			if byte != 0xe8 && byte != 0xe9 {
+				i += 1;
+				continue;
+			}
+
+			let current = self.position.wrapping_add(i as u32);
+			let offset  = current.wrapping_sub(self.previous);
+			self.previous = current;
+
+			if offset > 5 {
+				self.mask = 0;
+			}
+			else {
+				for _ in 0 .. offset {
+					self.mask &= 0x77;
+					self.mask <<= 1;
+				}
+			}
+
+			let byte = buffer[i + 4];
+
+			if is_msb(byte) && ALLOWED[((self.mask >> 1) & 0x7) as usize] && (self.mask >> 1) < 0x10 {
+				let mut source = (byte as u32) << 24
+					| (buffer[i + 3] as u32) << 16
+					| (buffer[i + 2] as u32) << 8
+					| (buffer[i + 1] as u32);
+
+				let mut destination;
+
+				loop {
+					destination = if self.encoder {
+						source.wrapping_add(current.wrapping_add(5))
+					}
+					else {
+						source.wrapping_sub(current.wrapping_add(5))
+					};
+
+					if self.mask == 0 {
+						break;
+					}
+
+					let index = BITS[(self.mask >> 1) as usize];
+
+					if !is_msb((destination >> (24 - index * 8)) as u8) {
+						break;
+					}
+
+					source = destination ^ ((1 << (32 - index * 8)) - 1);
+				}
+
+				buffer[i + 4] = !(((destination >> 24) & 1).wrapping_sub(1)) as u8;
+				buffer[i + 3] = (destination >> 16) as u8;
+				buffer[i + 2] = (destination >> 8) as u8;
+				buffer[i + 1] = destination as u8;
+
+				i        += 5;
+				self.mask = 0;
+			}
+			else {
+				i         += 1;
+				self.mask |= 1;
+
+				if is_msb(byte) {
+					self.mask |= 0x10;
+				}
+			}
+		}
+
+		self.position = self.position.wrapping_add(i as u32);
+
+		i
+	}
+}
@@ -0,0 +1,154 @@
+use std::io::{self, Read, BufRead, BufReader, Cursor};
+use std::fs::File;
+use std::path::Path;
+use byteorder::{LittleEndian, ByteOrder};
+
+use {Error, reader, xz, lzip, pbzx, swf};
+
+/// The magic bytes of a 7z archive.
+pub const SEVEN_ZIP_MAGIC: [u8; 6] = [b'7', b'z', 0xbc, 0xaf, 0x27, 0x1c];
+
+/// Amount of bytes needed to tell any format apart.
+pub const DETECT_SIZE: usize = 14;
+
+/// A known container format.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum Format {
+	/// A `.lzma` stream.
+	Lzma,
+
+	/// A `.xz` stream.
+	Xz,
+
+	/// A `.lz` stream.
+	Lzip,
+
+	/// A `.7z` archive.
+	SevenZip,
+
+	/// An Apple pbzx payload.
+	Pbzx,
+
+	/// A LZMA compressed SWF file.
+	Swf,
+}
+
+/// Detects the format from the first bytes of a file.
+///
+/// Formats with a magic number are recognized from it, `.lzma` streams are
+/// recognized by the plausibility of their header and need `DETECT_SIZE`
+/// bytes.
+pub fn detect(header: &[u8]) -> Option<Format> {
+	if header.starts_with(&xz::HEADER_MAGIC) {
+		Some(Format::Xz)
+	}
+	else if header.starts_with(&lzip::MAGIC) {
+		Some(Format::Lzip)
+	}
+	else if header.starts_with(&SEVEN_ZIP_MAGIC) {
+		Some(Format::SevenZip)
+	}
+	else if header.starts_with(b"pbzx") {
+		Some(Format::Pbzx)
+	}
+	else if header.starts_with(b"ZWS") {
+		Some(Format::Swf)
+	}
+	else if is_lzma(header) {
+		Some(Format::Lzma)
+	}
+	else {
+		None
+	}
+}
+
+// the header fields must be valid and sensible, and the range coder always
+// starts with a zero byte
+fn is_lzma(header: &[u8]) -> bool {
+	if header.len() < DETECT_SIZE {
+		return false;
+	}
+
+	if header[0] >= 9 * 5 * 5 {
+		return false;
+	}
+
+	// encoders only use powers of two, or the sum of two consecutive ones
+	let dictionary = LittleEndian::read_u32(&header[1 .. 5]);
+	let plausible  = dictionary == 0xffffffff || (dictionary != 0 && {
+		let high = 31 - dictionary.leading_zeros();
+		(dictionary & !(3 << high.saturating_sub(1))) == 0
+	});
+
+	if !plausible {
+		return false;
+	}
+
+	// no one is compressing 256 TiB
+	let uncompressed = LittleEndian::read_u64(&header[5 .. 13]);
+
+	if uncompressed != 0xffffffffffffffff && uncompressed >= 1 << 48 {
+		return false;
+	}
+
+	header[13] == 0x00
+}
+
+// a stream with the detected header put back in front
+pub(crate) type Peeked<R> = io::Chain<Cursor<Vec<u8>>, R>;
+
+// reads the bytes needed to detect the format, as a single buffer might hold
+// less than that, and puts them back in front of the stream
+pub(crate) fn peek<R: BufRead>(mut stream: R) -> Result<(Option<Format>, Peeked<R>), Error> {
+	let mut header = Vec::with_capacity(DETECT_SIZE);
+	try!(stream.by_ref().take(DETECT_SIZE as u64).read_to_end(&mut header));
+
+	Ok((detect(&header), Cursor::new(header).chain(stream)))
+}
+
+/// Open a file as any supported format.
+pub fn open_any<T: AsRef<Path>>(path: T) -> Result<Box<dyn Read>, Error> {
+	open_any_with_memlimit(path, u64::MAX)
+}
+
+/// Open a file as any supported format, with a memory limit.
+pub fn open_any_with_memlimit<T: AsRef<Path>>(path: T, memlimit: u64) -> Result<Box<dyn Read>, Error> {
+	read_any_with_memlimit(BufReader::new(try!(File::open(path))), memlimit)
+}
+
+/// Create a decoder for any supported format from another stream.
+///
+/// The format is detected from the first bytes, which are handed over to the
+/// decoder.
+pub fn read_any<T: BufRead + 'static>(stream: T) -> Result<Box<dyn Read>, Error> {
+	read_any_with_memlimit(stream, u64::MAX)
+}
+
+/// Create a decoder for any supported format from another stream, with a
+/// memory limit.
+pub fn read_any_with_memlimit<T: BufRead + 'static>(stream: T, memlimit: u64) -> Result<Box<dyn Read>, Error> {
+	let (format, stream) = try!(peek(stream));
+
+	Ok(match format {
+		Some(Format::Lzma) =>
+			Box::new(try!(reader::Reader::from_with_memlimit(stream, memlimit))),
+
+		Some(Format::Xz) =>
+			Box::new(try!(xz::Reader::with_memlimit(stream, memlimit))),
+
+		Some(Format::Lzip) =>
+			Box::new(try!(lzip::Reader::with_memlimit(stream, memlimit))),
+
+		Some(Format::Pbzx) =>
+			Box::new(try!(pbzx::Reader::with_memlimit(stream, memlimit))),
+
+		Some(Format::Swf) =>
+			Box::new(try!(swf::Reader::with_memlimit(stream, memlimit))),
+
+		Some(Format::SevenZip) =>
+			return Err(Error::Unsupported),
+
+		None =>
+			return Err(Error::InvalidHeader)
+	})
+}
@@ -0,0 +1,492 @@
+use std::io::{Read, Write, Seek, SeekFrom, BufReader};
+use std::fs::File;
+use std::path::Path;
+use std::cmp;
+use std::fmt;
+use byteorder::{LittleEndian, ReadBytesExt};
+
+use {Error, Properties, Format, properties, format, xz, lzip, lzma2};
+use xz::{Check, Filter};
+
+/// Information about a `.lzma` stream, read from its header.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub struct Lzma {
+	/// The model properties, with the uncompressed size if stored.
+	pub properties: Properties,
+
+	/// The size of the stream, header included.
+	pub size: u64,
+}
+
+/// Information about a xz stream, read from its index.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct Stream {
+	/// The offset of the stream header.
+	pub offset: u64,
+
+	/// The size of the stream, from the header to the footer.
+	pub size: u64,
+
+	/// The size of the padding after the stream.
+	pub padding: u64,
+
+	/// The integrity check of the blocks.
+	pub check: Check,
+
+	/// The blocks.
+	pub blocks: Vec<Block>,
+}
+
+impl Stream {
+	/// Returns the size of the uncompressed data.
+	pub fn uncompressed(&self) -> u64 {
+		self.blocks.iter().map(|block| block.uncompressed).sum()
+	}
+}
+
+/// Information about a xz block, read from its header and the index.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct Block {
+	/// The offset of the block header.
+	pub offset: u64,
+
+	/// The size of the block header.
+	pub header: u64,
+
+	/// The size of the compressed data, without padding and check.
+	pub compressed: u64,
+
+	/// The size of the uncompressed data.
+	pub uncompressed: u64,
+
+	/// The filter chain, in encoding order.
+	pub filters: Vec<Filter>,
+
+	/// The memory needed to decode the block, if the chain is supported.
+	pub memory: Option<u64>,
+}
+
+/// Information about a lzip member, read from its header and trailer.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub struct Member {
+	/// The offset of the member header.
+	pub offset: u64,
+
+	/// The size of the member, header and trailer included.
+	pub size: u64,
+
+	/// The version of the member.
+	pub version: u8,
+
+	/// The dictionary size.
+	pub dictionary: u32,
+
+	/// The CRC32 of the uncompressed data.
+	pub crc: u32,
+
+	/// The size of the uncompressed data.
+	pub uncompressed: u64,
+}
+
+impl Member {
+	/// Returns the memory needed to decode the member.
+	pub fn memory(&self) -> u64 {
+		lzip::properties(self.dictionary).decoder_memory_usage()
+	}
+}
+
+/// Information about a compressed file, see `info`.
+///
+/// Only headers, indexes and trailers are read, nothing is decompressed.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub enum Info {
+	/// A `.lzma` stream.
+	Lzma(Lzma),
+
+	/// A `.xz` file, with its streams.
+	Xz(Vec<Stream>),
+
+	/// A `.lz` file, with its members.
+	Lzip(Vec<Member>),
+}
+
+impl Info {
+	/// Reads the information from a stream, from the current position to the
+	/// end.
+	pub fn read<R: Read + Seek>(mut stream: R) -> Result<Info, Error> {
+		let start = try!(stream.stream_position());
+		let end   = try!(stream.seek(SeekFrom::End(0)));
+		try!(stream.seek(SeekFrom::Start(start)));
+
+		let mut header = Vec::new();
+		try!(stream.by_ref().take(format::DETECT_SIZE as u64).read_to_end(&mut header));
+		try!(stream.seek(SeekFrom::Start(start)));
+
+		match format::detect(&header) {
+			Some(Format::Lzma) =>
+				Ok(Info::Lzma(Lzma {
+					properties: try!(properties::read(stream)),
+					size:       end - start,
+				})),
+
+			Some(Format::Xz) =>
+				Ok(Info::Xz(try!(streams(&mut stream)))),
+
+			Some(Format::Lzip) =>
+				Ok(Info::Lzip(try!(members(&mut stream, start, end)))),
+
+			Some(_) =>
+				Err(Error::Unsupported),
+
+			None =>
+				Err(Error::InvalidHeader)
+		}
+	}
+
+	/// Returns the format.
+	pub fn format(&self) -> Format {
+		match self {
+			&Info::Lzma(..) => Format::Lzma,
+			&Info::Xz(..)   => Format::Xz,
+			&Info::Lzip(..) => Format::Lzip,
+		}
+	}
+
+	/// Returns the size of the compressed data, padding included.
+	pub fn compressed(&self) -> u64 {
+		match self {
+			&Info::Lzma(ref lzma) =>
+				lzma.size,
+
+			&Info::Xz(ref streams) =>
+				streams.iter().map(|stream| stream.size + stream.padding).sum(),
+
+			&Info::Lzip(ref members) =>
+				members.iter().map(|member| member.size).sum(),
+		}
+	}
+
+	/// Returns the size of the uncompressed data, `None` for a `.lzma` stream
+	/// that doesn't store it.
+	pub fn uncompressed(&self) -> Option<u64> {
+		match self {
+			&Info::Lzma(ref lzma) =>
+				lzma.properties.uncompressed,
+
+			&Info::Xz(ref streams) =>
+				Some(streams.iter().map(|stream| stream.uncompressed()).sum()),
+
+			&Info::Lzip(ref members) =>
+				Some(members.iter().map(|member| member.uncompressed).sum()),
+		}
+	}
+
+	/// Returns the compressed size divided by the uncompressed size.
+	pub fn ratio(&self) -> Option<f64> {
+		match self.uncompressed() {
+			Some(0) | None =>
+				None,
+
+			Some(size) =>
+				Some(self.compressed() as f64 / size as f64)
+		}
+	}
+
+	/// Returns the memory needed to decode the file, `None` if it contains
+	/// nothing that can be decoded.
+	pub fn memory(&self) -> Option<u64> {
+		match self {
+			&Info::Lzma(ref lzma) =>
+				Some(lzma.properties.decoder_memory_usage()),
+
+			&Info::Xz(ref streams) =>
+				streams.iter().flat_map(|stream| stream.blocks.iter()).filter_map(|block| block.memory).max(),
+
+			&Info::Lzip(ref members) =>
+				members.iter().map(|member| member.memory()).max(),
+		}
+	}
+
+	/// Writes the information as a JSON object.
+	///
+	/// The keys are part of the API, new ones might be added but existing
+	/// ones won't change.
+	pub fn write_json<W: Write>(&self, mut stream: W) -> Result<(), Error> {
+		try!(write!(stream, "{{\"format\":\"{}\",\"compressed\":{},\"uncompressed\":{},\"ratio\":{},\"memory\":{}",
+			format_name(self.format()), self.compressed(), optional(self.uncompressed()),
+			self.ratio().map(|ratio| format!("{:.4}", ratio)).unwrap_or_else(|| "null".to_owned()), optional(self.memory())));
+
+		match self {
+			&Info::Lzma(ref lzma) => {
+				let p = &lzma.properties;
+				try!(write!(stream, ",\"lc\":{},\"lp\":{},\"pb\":{},\"dictionary\":{}", p.lc, p.lp, p.pb, p.dictionary));
+			}
+
+			&Info::Xz(ref streams) => {
+				try!(write!(stream, ",\"streams\":["));
+
+				for (i, s) in streams.iter().enumerate() {
+					try!(write!(stream, "{}{{\"offset\":{},\"size\":{},\"padding\":{},\"uncompressed\":{},\"check\":\"{}\",\"blocks\":[",
+						if i == 0 { "" } else { "," }, s.offset, s.size, s.padding, s.uncompressed(), s.check));
+
+					for (j, b) in s.blocks.iter().enumerate() {
+						try!(write!(stream, "{}{{\"offset\":{},\"header\":{},\"compressed\":{},\"uncompressed\":{},\"memory\":{},\"filters\":[",
+							if j == 0 { "" } else { "," }, b.offset, b.header, b.compressed, b.uncompressed, optional(b.memory)));
+
+						for (k, filter) in b.filters.iter().enumerate() {
+							try!(write!(stream, "{}{}", if k == 0 { "" } else { "," }, filter_json(filter)));
+						}
+
+						try!(write!(stream, "]}}"));
+					}
+
+					try!(write!(stream, "]}}"));
+				}
+
+				try!(write!(stream, "]"));
+			}
+
+			&Info::Lzip(ref members) => {
+				try!(write!(stream, ",\"members\":["));
+
+				for (i, m) in members.iter().enumerate() {
+					try!(write!(stream, "{}{{\"offset\":{},\"size\":{},\"version\":{},\"dictionary\":{},\"crc\":{},\"uncompressed\":{}}}",
+						if i == 0 { "" } else { "," }, m.offset, m.size, m.version, m.dictionary, m.crc, m.uncompressed));
+				}
+
+				try!(write!(stream, "]"));
+			}
+		}
+
+		try!(write!(stream, "}}"));
+
+		Ok(())
+	}
+}
+
+impl fmt::Display for Info {
+	/// Formats the information as human readable text.
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		try!(writeln!(f, "Format:         {}", format_name(self.format())));
+		try!(writeln!(f, "Compressed:     {}", size(self.compressed())));
+		try!(writeln!(f, "Uncompressed:   {}", self.uncompressed().map(size).unwrap_or_else(|| "unknown".to_owned())));
+		try!(writeln!(f, "Ratio:          {}", self.ratio().map(|ratio| format!("{:.3}", ratio)).unwrap_or_else(|| "---".to_owned())));
+		try!(writeln!(f, "Memory needed:  {}", self.memory().map(size).unwrap_or_else(|| "---".to_owned())));
+
+		match self {
+			&Info::Lzma(ref lzma) => {
+				let p = &lzma.properties;
+
+				try!(writeln!(f, "Dictionary:     {}", size(p.dictionary as u64)));
+				try!(writeln!(f, "Literal bits:   lc={} lp={}", p.lc, p.lp));
+				try!(writeln!(f, "Position bits:  pb={}", p.pb));
+			}
+
+			&Info::Xz(ref streams) => {
+				try!(writeln!(f, "Streams:        {}", streams.len()));
+				try!(writeln!(f, "Blocks:         {}", streams.iter().map(|s| s.blocks.len()).sum::<usize>()));
+
+				for (i, s) in streams.iter().enumerate() {
+					try!(writeln!(f));
+					try!(writeln!(f, "Stream {}: offset {}, size {}, blocks {}, check {}, padding {}",
+						i + 1, s.offset, size(s.size), s.blocks.len(), s.check, s.padding));
+
+					for (j, b) in s.blocks.iter().enumerate() {
+						let filters = b.filters.iter().map(filter_name).collect::<Vec<_>>().join(",");
+
+						try!(writeln!(f, "  Block {}: offset {}, header {}, {} -> {}, memory {}, filters {}",
+							j + 1, b.offset, b.header, size(b.compressed), size(b.uncompressed),
+							b.memory.map(size).unwrap_or_else(|| "---".to_owned()), filters));
+					}
+				}
+			}
+
+			&Info::Lzip(ref members) => {
+				try!(writeln!(f, "Members:        {}", members.len()));
+
+				for (i, m) in members.iter().enumerate() {
+					try!(writeln!(f, "  Member {}: offset {}, {} -> {}, version {}, dictionary {}, crc {:08x}",
+						i + 1, m.offset, size(m.size), size(m.uncompressed), m.version, size(m.dictionary as u64), m.crc));
+				}
+			}
+		}
+
+		Ok(())
+	}
+}
+
+// reads the streams from their indexes, and the block headers for the filters
+fn streams<R: Read + Seek>(stream: &mut R) -> Result<Vec<Stream>, Error> {
+	let mut streams = Vec::new();
+
+	for located in try!(xz::locate(stream)) {
+		let check      = located.flags.check;
+		let mut offset = located.offset + 12;
+		let mut blocks = Vec::new();
+
+		for record in &located.index.records {
+			try!(stream.seek(SeekFrom::Start(offset)));
+
+			let first  = try!(stream.read_u8());
+			let header = try!(xz::Block::read(stream.by_ref(), first));
+
+			let compressed = match record.unpadded.checked_sub(header.size + check.size() as u64) {
+				Some(size) if size > 0 =>
+					size,
+
+				_ =>
+					return Err(Error::Corrupted)
+			};
+
+			// the sizes in the header, if any, must match the index
+			if header.compressed.unwrap_or(compressed) != compressed ||
+			   header.uncompressed.unwrap_or(record.uncompressed) != record.uncompressed
+			{
+				return Err(Error::Corrupted);
+			}
+
+			let memory = match header.filters.last() {
+				Some(&Filter::Lzma2(dictionary)) => {
+					let dictionary = cmp::min(dictionary as u64, cmp::max(record.uncompressed, 1)) as u32;
+					Some(lzma2::decoder_memory_usage(dictionary))
+				}
+
+				_ =>
+					None
+			};
+
+			blocks.push(Block {
+				offset:       offset,
+				header:       header.size,
+				compressed:   compressed,
+				uncompressed: record.uncompressed,
+				filters:      header.filters,
+				memory:       memory,
+			});
+
+			offset += (record.unpadded + 3) & !3;
+		}
+
+		streams.push(Stream {
+			offset:  located.offset,
+			size:    located.size,
+			padding: located.padding,
+			check:   check,
+			blocks:  blocks,
+		});
+	}
+
+	Ok(streams)
+}
+
+// reads the members backwards from their trailers
+fn members<R: Read + Seek>(stream: &mut R, start: u64, mut end: u64) -> Result<Vec<Member>, Error> {
+	let mut members = Vec::new();
+
+	while end > start {
+		if end - start < lzip::HEADER_SIZE + lzip::TRAILER_SIZE {
+			return Err(Error::Corrupted);
+		}
+
+		try!(stream.seek(SeekFrom::Start(end - lzip::TRAILER_SIZE)));
+
+		let crc          = try!(stream.read_u32::<LittleEndian>());
+		let uncompressed = try!(stream.read_u64::<LittleEndian>());
+		let size         = try!(stream.read_u64::<LittleEndian>());
+
+		let offset = match end.checked_sub(size) {
+			Some(offset) if offset >= start && size >= lzip::HEADER_SIZE + lzip::TRAILER_SIZE =>
+				offset,
+
+			_ =>
+				return Err(Error::Corrupted)
+		};
+
+		let mut header = [0u8; 6];
+		try!(stream.seek(SeekFrom::Start(offset)));
+		try!(stream.read_exact(&mut header));
+
+		if header[.. 4] != lzip::MAGIC {
+			return Err(Error::Corrupted);
+		}
+
+		members.push(Member {
+			offset:       offset,
+			size:         size,
+			version:      header[4],
+			dictionary:   try!(lzip::dictionary(header[5])),
+			crc:          crc,
+			uncompressed: uncompressed,
+		});
+
+		end = offset;
+	}
+
+	members.reverse();
+
+	Ok(members)
+}
+
+fn format_name(format: Format) -> &'static str {
+	match format {
+		Format::Lzma     => "lzma",
+		Format::Xz       => "xz",
+		Format::Lzip     => "lzip",
+		Format::SevenZip => "7z",
+		Format::Pbzx     => "pbzx",
+		Format::Swf      => "swf",
+	}
+}
+
+fn filter_name(filter: &Filter) -> String {
+	match filter {
+		&Filter::Lzma2(dictionary) =>
+			format!("lzma2:dict={}", size(dictionary as u64)),
+
+		&Filter::X86 =>
+			"x86".to_owned(),
+
+		&Filter::Delta(distance) =>
+			format!("delta:dist={}", distance),
+
+		&Filter::Unknown(id, _) =>
+			format!("{:#x}", id),
+	}
+}
+
+fn filter_json(filter: &Filter) -> String {
+	match filter {
+		&Filter::Lzma2(dictionary) =>
+			format!("{{\"id\":\"lzma2\",\"dictionary\":{}}}", dictionary),
+
+		&Filter::X86 =>
+			"{\"id\":\"x86\"}".to_owned(),
+
+		&Filter::Delta(distance) =>
+			format!("{{\"id\":\"delta\",\"distance\":{}}}", distance),
+
+		&Filter::Unknown(id, ref properties) => {
+			let hex = properties.iter().map(|b| format!("{:02x}", b)).collect::<String>();
+			format!("{{\"id\":\"{:#x}\",\"properties\":\"{}\"}}", id, hex)
+		}
+	}
+}
+
+fn optional(value: Option<u64>) -> String {
+	value.map(|value| value.to_string()).unwrap_or_else(|| "null".to_owned())
+}
+
+// the exact size, with a rounded one in the largest fitting unit
+fn size(bytes: u64) -> String {
+	match bytes {
+		b if b < 1 << 10 => format!("{} B", b),
+		b if b < 1 << 20 => format!("{:.1} KiB ({} B)", b as f64 / 1024.0, b),
+		b if b < 1 << 30 => format!("{:.1} MiB ({} B)", b as f64 / 1048576.0, b),
+		b                => format!("{:.1} GiB ({} B)", b as f64 / 1073741824.0, b),
+	}
+}
+
+/// Reads the information of a compressed file.
+pub fn info<T: AsRef<Path>>(path: T) -> Result<Info, Error> {
+	Info::read(BufReader::new(try!(File::open(path))))
+}
@@ -5,9 +5,10 @@
 extern crate byteorder;
 
 mod consts;
+mod pool;
 
 mod error;
-pub use error::Error;
+pub use error::{Error, Failure, Position, PacketKind, Cause};
 
 /// Model property related functions.
 pub mod properties;
@@ -15,4 +16,69 @@ pub use properties::Properties;
 
 #[doc(hidden)]
 pub mod reader;
-pub use reader::{Reader, open, read};
+pub use reader::{Reader, Seekable, Snapshot, Packet, Packets, open, read};
+
+#[doc(hidden)]
+pub mod writer;
+pub use writer::{Writer, create, write};
+
+#[doc(hidden)]
+pub mod format;
+pub use format::{Format, detect, open_any, open_any_with_memlimit, read_any, read_any_with_memlimit};
+
+#[doc(hidden)]
+pub mod scan;
+pub use scan::{Candidate, scan, scan_with_memlimit};
+
+#[doc(hidden)]
+pub mod patch;
+pub use patch::{diff, diff_with_memlimit, patch, patch_with_memlimit};
+
+#[doc(hidden)]
+pub mod stats;
+pub use stats::{Stats, Category, stats};
+
+#[doc(hidden)]
+pub mod info;
+pub use info::{Info, info};
+
+#[doc(hidden)]
+pub mod verify;
+pub use verify::{Report, verify};
+
+#[doc(hidden)]
+pub mod recover;
+pub use recover::{Recovery, Damage, recover, recover_with_memlimit};
+
+/// Branch converter filters.
+pub mod filter;
+
+/// Integrity checks.
+pub mod check;
+
+/// LZMA2 handling.
+pub mod lzma2;
+
+/// ZIP method 14 entry handling.
+pub mod zip;
+
+/// LZMA compressed SWF handling.
+pub mod swf;
+
+/// MicroLZMA handling.
+pub mod microlzma;
+
+/// lzma86 handling.
+pub mod lzma86;
+
+/// NSIS installer data handling.
+pub mod nsis;
+
+/// xz handling.
+pub mod xz;
+
+/// lzip handling.
+pub mod lzip;
+
+/// Apple pbzx payload handling.
+pub mod pbzx;
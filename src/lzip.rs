@@ -0,0 +1,374 @@
+use std::io::{self, Read, Write, BufReader, BufWriter};
+use std::fs::File;
+use std::path::Path;
+use std::cmp;
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+
+use {Error, Properties, reader, writer};
+use error::{self, Sticky};
+use check::Crc32;
+use reader::Counter;
+
+/// The magic bytes at the start of a member.
+pub const MAGIC: [u8; 4] = [b'L', b'Z', b'I', b'P'];
+
+/// Size of the member header.
+pub const HEADER_SIZE: u64 = 6;
+
+/// Size of the member trailer.
+pub const TRAILER_SIZE: u64 = 20;
+
+/// Decodes the dictionary size from the coded byte of the header.
+pub fn dictionary(byte: u8) -> Result<u32, Error> {
+	let bits = byte & 0x1f;
+
+	if !(12 ..= 29).contains(&bits) {
+		return Err(Error::InvalidHeader);
+	}
+
+	let base = 1u32 << bits;
+
+	Ok(base - (base / 16) * (byte >> 5) as u32)
+}
+
+/// Encodes the dictionary size into the coded byte of the header, rounding up.
+pub fn dictionary_byte(size: u32) -> u8 {
+	let bits = (32 - size.saturating_sub(1).leading_zeros()).clamp(12, 29);
+	let base = 1u32 << bits;
+
+	// the fraction can only be taken off sizes above the minimum
+	let fraction = if bits > 12 {
+		cmp::min(base.saturating_sub(size) / (base / 16), 7)
+	}
+	else {
+		0
+	};
+
+	bits as u8 | (fraction as u8) << 5
+}
+
+/// A lzip stream reader, members are read one after the other.
+#[derive(Debug)]
+pub struct Reader<R: Read> {
+	stream:  Option<Counter<R>>,
+	inner:   Option<reader::Reader<Counter<R>>>,
+	version: u8,
+	start:   u64,
+	crc:     Crc32,
+	members: u64,
+
+	memlimit: u64,
+	finished: bool,
+	failed:   Option<Error>,
+}
+
+impl<R: Read> Reader<R> {
+	/// Creates a lzip reader from the given stream, reading the first member
+	/// header.
+	pub fn new(stream: R) -> Result<Reader<R>, Error> {
+		Reader::with_memlimit(stream, u64::MAX)
+	}
+
+	/// Creates a lzip reader like `new`, every member fails before allocating
+	/// anything if decoding it needs more memory than the limit.
+	pub fn with_memlimit(stream: R, memlimit: u64) -> Result<Reader<R>, Error> {
+		let mut stream = Counter::new(stream, 0);
+		let     header = try!(read_header(&mut stream));
+
+		let (version, dictionary) = match header {
+			Some(header) =>
+				header,
+
+			None =>
+				return Err(Error::InvalidHeader)
+		};
+
+		Ok(Reader {
+			stream:  None,
+			inner:   Some(try!(reader::Reader::with_memlimit(stream, properties(dictionary), memlimit))),
+			version: version,
+			start:   0,
+			crc:     Crc32::new(),
+			members: 1,
+
+			memlimit: memlimit,
+			finished: false,
+			failed:   None,
+		})
+	}
+
+	/// Returns the version of the current member.
+	pub fn version(&self) -> u8 {
+		self.version
+	}
+
+	/// Returns the model properties of the current member, `None` once
+	/// finished.
+	pub fn properties(&self) -> Option<&Properties> {
+		self.inner.as_ref().map(|inner| inner.properties())
+	}
+
+	/// Returns the amount of members encountered so far.
+	pub fn members(&self) -> u64 {
+		self.members
+	}
+
+	/// Returns the size of the members read so far.
+	pub fn compressed(&self) -> u64 {
+		self.start
+	}
+
+	/// Checks if the end of the last member has been reached.
+	pub fn is_finished(&self) -> bool {
+		self.finished
+	}
+
+	/// Unwraps this `Reader`, returning the underlying reader.
+	///
+	/// Note that once finished any trailing data that was peeked is lost.
+	pub fn into_inner(self) -> R {
+		match self.stream {
+			Some(stream) =>
+				stream.into_inner(),
+
+			None =>
+				self.inner.unwrap().into_inner().into_inner()
+		}
+	}
+
+	// verifies the trailer and moves to the next member if any, the stream
+	// stays with the reader whatever happens
+	fn end(&mut self) -> Result<(), Error> {
+		let     decoder = self.inner.take().unwrap();
+		let     decoded = decoder.decoded();
+		let mut stream  = decoder.into_inner();
+
+		let result = self.trailer(&mut stream, decoded).and_then(|_| {
+			// anything that isn't another member is trailing data
+			let header = try!(read_header(&mut stream));
+
+			if let Some((_, dictionary)) = header {
+				let needed = properties(dictionary).decoder_memory_usage();
+
+				if needed > self.memlimit {
+					return Err(Error::MemoryLimit { needed: needed, limit: self.memlimit });
+				}
+			}
+
+			Ok(header)
+		});
+
+		match result {
+			Ok(Some((version, dictionary))) => {
+				self.version  = version;
+				self.members += 1;
+				self.inner    = Some(try!(reader::Reader::with_memlimit(stream, properties(dictionary), self.memlimit)));
+			}
+
+			Ok(None) => {
+				self.stream   = Some(stream);
+				self.finished = true;
+			}
+
+			Err(err) => {
+				self.stream = Some(stream);
+				return Err(err);
+			}
+		}
+
+		Ok(())
+	}
+
+	// verifies the trailer of the member
+	fn trailer(&mut self, stream: &mut Counter<R>, decoded: u64) -> Result<(), Error> {
+		let crc    = try!(stream.read_u32::<LittleEndian>());
+		let size   = try!(stream.read_u64::<LittleEndian>());
+		let member = try!(stream.read_u64::<LittleEndian>());
+
+		if crc != self.crc.value() {
+			return Err(Error::Checksum);
+		}
+
+		if size != decoded || member != stream.count() - self.start {
+			return Err(Error::Corrupted);
+		}
+
+		self.start = stream.count();
+		self.crc   = Crc32::new();
+
+		Ok(())
+	}
+}
+
+impl<R: Read> Sticky for Reader<R> {
+	fn failed(&mut self) -> &mut Option<Error> {
+		&mut self.failed
+	}
+
+	fn decode(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+		loop {
+			if self.finished {
+				return Ok(0);
+			}
+
+			let read = try!(self.inner.as_mut().unwrap().read(buf));
+
+			if read != 0 {
+				self.crc.update(&buf[.. read]);
+				return Ok(read);
+			}
+
+			try!(self.end());
+		}
+	}
+}
+
+impl<R: Read> Read for Reader<R> {
+	fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+		error::read(self, buf)
+	}
+}
+
+/// A lzip stream writer, the data is written as a single member.
+#[derive(Debug)]
+pub struct Writer<W: Write> {
+	inner: writer::Writer<Written<W>>,
+	crc:   Crc32,
+	size:  u64,
+}
+
+// a writer keeping count of the bytes written
+#[derive(Debug)]
+struct Written<W: Write> {
+	stream: W,
+	count:  u64,
+}
+
+impl<W: Write> Write for Written<W> {
+	fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+		let written = try!(self.stream.write(buf));
+		self.count += written as u64;
+
+		Ok(written)
+	}
+
+	fn flush(&mut self) -> io::Result<()> {
+		self.stream.flush()
+	}
+}
+
+impl<W: Write> Writer<W> {
+	/// Creates a lzip writer with the given dictionary size, writing the member
+	/// header.
+	///
+	/// The dictionary size is rounded up to one the header can hold.
+	pub fn new(stream: W, dictionary: u32) -> Result<Writer<W>, Error> {
+		let     coded  = dictionary_byte(dictionary);
+		let mut stream = Written { stream: stream, count: 0 };
+
+		try!(stream.write_all(&MAGIC));
+		try!(stream.write_u8(1));
+		try!(stream.write_u8(coded));
+
+		let mut inner = try!(writer::Writer::new(stream, properties(try!(self::dictionary(coded)))));
+		inner.set_marker(true);
+
+		Ok(Writer {
+			inner: inner,
+			crc:   Crc32::new(),
+			size:  0,
+		})
+	}
+
+	/// Returns the model properties.
+	pub fn properties(&self) -> &Properties {
+		self.inner.properties()
+	}
+
+	/// Sets how hard the match finder searches, see `lzma::Writer::set_search`.
+	pub fn set_search(&mut self, depth: usize, nice: usize) {
+		self.inner.set_search(depth, nice);
+	}
+
+	/// Encodes the remaining data and writes the member trailer, returning the
+	/// underlying writer.
+	pub fn finish(self) -> Result<W, Error> {
+		let mut stream = try!(self.inner.finish());
+		let     member = stream.count + TRAILER_SIZE;
+
+		try!(stream.write_u32::<LittleEndian>(self.crc.value()));
+		try!(stream.write_u64::<LittleEndian>(self.size));
+		try!(stream.write_u64::<LittleEndian>(member));
+		try!(stream.flush());
+
+		Ok(stream.stream)
+	}
+}
+
+impl<W: Write> Write for Writer<W> {
+	fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+		let written = try!(self.inner.write(buf));
+		self.crc.update(&buf[.. written]);
+		self.size += written as u64;
+
+		Ok(written)
+	}
+
+	fn flush(&mut self) -> io::Result<()> {
+		self.inner.flush()
+	}
+}
+
+pub(crate) fn properties(dictionary: u32) -> Properties {
+	Properties {
+		lc: 3,
+		lp: 0,
+		pb: 2,
+
+		dictionary:   dictionary,
+		uncompressed: None,
+	}
+}
+
+// reads a member header, `None` if there's no member
+pub(crate) fn read_header<T: Read>(stream: &mut T) -> Result<Option<(u8, u32)>, Error> {
+	let mut header = [0u8; HEADER_SIZE as usize];
+	let mut length = 0;
+
+	while length < header.len() {
+		match try!(stream.read(&mut header[length ..])) {
+			0 => break,
+			n => length += n,
+		}
+	}
+
+	if length < header.len() || header[.. 4] != MAGIC {
+		return Ok(None);
+	}
+
+	if header[4] != 1 {
+		return Err(Error::Unsupported);
+	}
+
+	Ok(Some((header[4], try!(dictionary(header[5])))))
+}
+
+/// Open a file as a lzip stream.
+pub fn open<T: AsRef<Path>>(path: T) -> Result<Reader<BufReader<File>>, Error> {
+	read(BufReader::new(try!(File::open(path))))
+}
+
+/// Create a lzip stream from another stream.
+pub fn read<T: Read>(stream: T) -> Result<Reader<T>, Error> {
+	Reader::new(stream)
+}
+
+/// Create a file as a lzip stream.
+pub fn create<T: AsRef<Path>>(path: T, dictionary: u32) -> Result<Writer<BufWriter<File>>, Error> {
+	write(BufWriter::new(try!(File::create(path))), dictionary)
+}
+
+/// Create a lzip stream to another stream.
+pub fn write<T: Write>(stream: T, dictionary: u32) -> Result<Writer<T>, Error> {
+	Writer::new(stream, dictionary)
+}
@@ -0,0 +1,48 @@
+mod reader;
+pub use self::reader::Reader;
+
+mod writer;
+pub use self::writer::{Writer, CHUNK_UNCOMPRESSED_MAXIMUM, CHUNK_COMPRESSED_MAXIMUM};
+
+mod parallel;
+pub use self::parallel::{Parallel, SEGMENT_MAXIMUM};
+
+use std::io::{Read, Write};
+
+use {Error, Properties};
+
+/// Decodes the dictionary size from the LZMA2 properties byte.
+pub fn dictionary(byte: u8) -> Result<u32, Error> {
+	match byte {
+		40 =>
+			Ok(0xffffffff),
+
+		n if n > 40 =>
+			Err(Error::InvalidProperties),
+
+		n =>
+			Ok((2 | (n as u32 & 1)) << (n / 2 + 11))
+	}
+}
+
+/// Returns the amount of memory in bytes decoding a LZMA2 stream with the
+/// given dictionary size needs at most.
+pub fn decoder_memory_usage(dictionary: u32) -> u64 {
+	// chunks can switch to any model with `lc + lp` up to 4
+	Properties { lc: 4, lp: 0, pb: 0, dictionary: dictionary, uncompressed: None }.decoder_memory_usage()
+}
+
+/// Encodes the dictionary size into the LZMA2 properties byte, rounding up.
+pub fn dictionary_byte(size: u32) -> u8 {
+	(0 .. 40).find(|&n| dictionary(n).unwrap() >= size).unwrap_or(40)
+}
+
+/// Create a LZMA2 stream from another stream.
+pub fn read<T: Read>(stream: T, dictionary: u32) -> Result<Reader<T>, Error> {
+	Reader::new(stream, dictionary)
+}
+
+/// Create a LZMA2 stream to another stream.
+pub fn write<T: Write>(stream: T, properties: Properties) -> Result<Writer<T>, Error> {
+	Writer::new(stream, properties)
+}
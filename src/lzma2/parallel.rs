@@ -0,0 +1,342 @@
+use std::io::{self, Read, Cursor};
+use std::collections::VecDeque;
+use std::sync::mpsc;
+use std::mem;
+use std::cmp;
+use byteorder::{BigEndian, ReadBytesExt};
+
+use Error;
+use error::{self, Sticky};
+use pool::Pool;
+use super::{Reader, decoder_memory_usage};
+
+// a segment decoded by a worker
+type Decoded = Result<Vec<u8>, Error>;
+
+#[derive(Debug)]
+enum Pending {
+	// a segment sent to the workers
+	Segment(mpsc::Receiver<Decoded>),
+
+	// the start of a segment too big to be held in memory and its decoded
+	// size, the stream is right after it
+	Inline(Vec<u8>, u64),
+}
+
+#[derive(Debug)]
+struct Inline {
+	decoder:  Reader<VecDeque<u8>>,
+	unpacked: u64,
+}
+
+/// The maximum decoded size of a segment decoded by a worker.
+pub const SEGMENT_MAXIMUM: u64 = 1 << 23;
+
+/// A LZMA2 stream reader decoding on several threads.
+///
+/// Every chunk resetting the dictionary starts a segment which doesn't depend
+/// on what comes before it, the segments are decoded in parallel and the
+/// output is returned in order. Segments decoding to more than
+/// `SEGMENT_MAXIMUM` bytes, like a stream without such chunks, are decoded
+/// chunk by chunk on the calling thread.
+///
+/// At most `threads` segments are held in memory at any time, both compressed
+/// and decoded.
+#[derive(Debug)]
+pub struct Parallel<R: Read> {
+	stream:   R,
+	segment:  Vec<u8>,
+	unpacked: u64,
+	inline:   Option<Inline>,
+
+	pool:    Pool,
+	pending: VecDeque<Pending>,
+	output:  Cursor<Vec<u8>>,
+
+	dictionary: u32,
+	memlimit:   u64,
+	decoded:    u64,
+	scanned:    bool,
+	waiting:    bool,
+	failed:     Option<Error>,
+}
+
+impl<R: Read> Parallel<R> {
+	/// Creates a LZMA2 reader with the given dictionary size using the given
+	/// amount of threads.
+	pub fn new(stream: R, dictionary: u32, threads: usize) -> Result<Parallel<R>, Error> {
+		Parallel::with_memlimit(stream, dictionary, threads, u64::MAX)
+	}
+
+	/// Creates a LZMA2 reader like `new`, failing before allocating anything
+	/// if decoding a segment needs more memory than the limit.
+	pub fn with_memlimit(stream: R, dictionary: u32, threads: usize, memlimit: u64) -> Result<Parallel<R>, Error> {
+		let needed = decoder_memory_usage(dictionary);
+
+		if needed > memlimit {
+			return Err(Error::MemoryLimit { needed: needed, limit: memlimit });
+		}
+
+		Ok(Parallel {
+			stream:   stream,
+			segment:  Vec::new(),
+			unpacked: 0,
+			inline:   None,
+
+			pool:    Pool::new(threads),
+			pending: VecDeque::new(),
+			output:  Cursor::new(Vec::new()),
+
+			dictionary: dictionary,
+			memlimit:   memlimit,
+			decoded:    0,
+			scanned:    false,
+			waiting:    false,
+			failed:     None,
+		})
+	}
+
+	/// Returns the amount of threads.
+	pub fn threads(&self) -> usize {
+		self.pool.threads()
+	}
+
+	/// Returns the amount of decoded bytes.
+	pub fn decoded(&self) -> u64 {
+		self.decoded
+	}
+
+	/// Checks if the end of the stream has been reached.
+	pub fn is_finished(&self) -> bool {
+		self.scanned && self.inline.is_none() && self.pending.is_empty() &&
+			self.output.position() == self.output.get_ref().len() as u64
+	}
+
+	/// Returns the inner stream.
+	pub fn get_ref(&self) -> &R {
+		&self.stream
+	}
+
+	/// Unwraps this `Parallel`, returning the underlying reader.
+	///
+	/// The stream is read up to the end marker and no further.
+	pub fn into_inner(self) -> R {
+		self.stream
+	}
+
+	// reads the next chunk, returning it with its decoded size and whether it
+	// resets the dictionary, or nothing at the end marker
+	fn chunk(&mut self) -> Result<Option<(Vec<u8>, u64, bool)>, Error> {
+		let mut header = [0u8; 6];
+		header[0] = try!(self.stream.read_u8());
+
+		let control = header[0];
+
+		if control == 0x00 {
+			return Ok(None);
+		}
+
+		if (0x03 .. 0x80).contains(&control) {
+			return Err(Error::Corrupted);
+		}
+
+		let (length, size, unpacked, reset) = if control < 0x80 {
+			try!(self.stream.read_exact(&mut header[1 .. 3]));
+
+			let size = try!((&header[1 .. 3]).read_u16::<BigEndian>()) as u64 + 1;
+			(3, size, size, control == 0x01)
+		}
+		else {
+			let length = if control >= 0xc0 { 6 } else { 5 };
+			try!(self.stream.read_exact(&mut header[1 .. length]));
+
+			let unpacked = ((control as u64 & 0x1f) << 16) + try!((&header[1 .. 3]).read_u16::<BigEndian>()) as u64 + 1;
+			(length, try!((&header[3 .. 5]).read_u16::<BigEndian>()) as u64 + 1, unpacked, control >= 0xe0)
+		};
+
+		let mut chunk = header[.. length].to_vec();
+		try!(self.stream.by_ref().take(size).read_to_end(&mut chunk));
+
+		if ((chunk.len() - length) as u64) != size {
+			return Err(Error::NeedMoreData);
+		}
+
+		Ok(Some((chunk, unpacked, reset)))
+	}
+
+	// reads the next chunk into the current segment, sending the segment to
+	// the workers when the chunk starts a new one, a segment getting too big
+	// is left to be decoded in place and stops the reading ahead
+	fn next(&mut self) -> Result<(), Error> {
+		let (chunk, unpacked, reset) = match try!(self.chunk()) {
+			Some(chunk) =>
+				chunk,
+
+			None => {
+				self.scanned = true;
+				return self.dispatch();
+			}
+		};
+
+		if reset {
+			try!(self.dispatch());
+		}
+
+		self.segment.extend_from_slice(&chunk);
+		self.unpacked += unpacked;
+
+		if self.unpacked > SEGMENT_MAXIMUM {
+			self.pending.push_back(Pending::Inline(mem::take(&mut self.segment), self.unpacked));
+			self.unpacked = 0;
+			self.waiting  = true;
+		}
+
+		Ok(())
+	}
+
+	// sends the current segment to the workers
+	fn dispatch(&mut self) -> Result<(), Error> {
+		if self.segment.is_empty() {
+			return Ok(());
+		}
+
+		let mut segment    = mem::take(&mut self.segment);
+		let     dictionary = self.dictionary;
+		let     memlimit   = self.memlimit;
+
+		segment.push(0x00);
+
+		self.pending.push_back(Pending::Segment(self.pool.spawn(move |_| decode(segment, dictionary, memlimit))));
+		self.unpacked = 0;
+
+		Ok(())
+	}
+
+	// feeds the segment decoded in place with the next chunk, or checks it's
+	// complete when the chunk starts a new segment
+	fn resume(&mut self) -> Result<(), Error> {
+		let next = try!(self.chunk());
+
+		if let Some((ref chunk, unpacked, false)) = next {
+			let inline = self.inline.as_mut().unwrap();
+
+			inline.decoder.get_mut().extend(chunk.iter());
+			inline.unpacked += unpacked;
+
+			return Ok(());
+		}
+
+		let mut decoder = self.inline.take().unwrap().decoder;
+		decoder.get_mut().push_back(0x00);
+
+		if try!(decoder.read(&mut [0u8; 1])) != 0 || !decoder.is_finished() {
+			return Err(Error::Corrupted);
+		}
+
+		self.waiting = false;
+
+		match next {
+			Some((chunk, unpacked, _)) => {
+				self.segment  = chunk;
+				self.unpacked = unpacked;
+			}
+
+			None =>
+				self.scanned = true
+		}
+
+		Ok(())
+	}
+
+	// keeps the workers busy, returns false when everything was read
+	fn advance(&mut self) -> Result<bool, Error> {
+		while !self.scanned && !self.waiting && self.pending.len() < self.pool.threads() {
+			try!(self.next());
+		}
+
+		match self.pending.pop_front() {
+			Some(Pending::Segment(receiver)) => match receiver.recv() {
+				Ok(result) =>
+					self.output = Cursor::new(try!(result)),
+
+				Err(_) =>
+					return Err(Error::IO(io::Error::other("decoding thread died")))
+			},
+
+			Some(Pending::Inline(segment, unpacked)) => {
+				let mut decoder = try!(Reader::with_memlimit(VecDeque::new(), self.dictionary, self.memlimit));
+				decoder.get_mut().extend(segment);
+
+				self.inline = Some(Inline {
+					decoder:  decoder,
+					unpacked: unpacked,
+				});
+			}
+
+			None =>
+				return Ok(false)
+		}
+
+		Ok(true)
+	}
+}
+
+impl<R: Read> Sticky for Parallel<R> {
+	fn failed(&mut self) -> &mut Option<Error> {
+		&mut self.failed
+	}
+
+	fn decode(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+		loop {
+			let read = try!(self.output.read(buf));
+
+			if read != 0 {
+				self.decoded += read as u64;
+				return Ok(read);
+			}
+
+			if self.inline.is_some() {
+				let inline = self.inline.as_mut().unwrap();
+
+				if inline.unpacked != 0 {
+					let length = cmp::min(buf.len() as u64, inline.unpacked) as usize;
+					let read   = try!(inline.decoder.read(&mut buf[.. length]));
+
+					if read == 0 {
+						return Err(Error::Corrupted);
+					}
+
+					inline.unpacked -= read as u64;
+					self.decoded    += read as u64;
+
+					return Ok(read);
+				}
+
+				try!(self.resume());
+				continue;
+			}
+
+			if !try!(self.advance()) {
+				return Ok(0);
+			}
+		}
+	}
+}
+
+impl<R: Read> Read for Parallel<R> {
+	fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+		error::read(self, buf)
+	}
+}
+
+fn decode(segment: Vec<u8>, dictionary: u32, memlimit: u64) -> Decoded {
+	let mut reader = try!(Reader::with_memlimit(Cursor::new(segment), dictionary, memlimit));
+	let mut output = Vec::new();
+	try!(reader.read_to_end(&mut output));
+
+	if !reader.is_finished() {
+		return Err(Error::Corrupted);
+	}
+
+	Ok(output)
+}
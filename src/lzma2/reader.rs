@@ -0,0 +1,265 @@
+use std::io::{self, Read, Take};
+use std::cmp;
+use byteorder::{BigEndian, ReadBytesExt};
+
+use {Error, Properties, properties, reader};
+use super::decoder_memory_usage;
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+enum Chunk {
+	Header,
+	Compressed,
+	Uncompressed(u64),
+	Finished,
+}
+
+/// A LZMA2 stream reader.
+#[derive(Debug)]
+pub struct Reader<R: Read> {
+	decoder: reader::Reader<Take<R>>,
+	chunk:   Chunk,
+	skipped: u64,
+
+	need_dictionary: bool,
+	need_properties: bool,
+}
+
+impl<R: Read> Reader<R> {
+	/// Creates a LZMA2 reader with the given dictionary size.
+	pub fn new(stream: R, dictionary: u32) -> Result<Reader<R>, Error> {
+		Reader::with_memlimit(stream, dictionary, u64::MAX)
+	}
+
+	/// Creates a LZMA2 reader like `new`, preloading the dictionary with a
+	/// preset, so the first chunk doesn't need to reset it.
+	///
+	/// The stream must have been encoded with the same preset dictionary.
+	pub fn with_preset(stream: R, dictionary: u32, preset: &[u8]) -> Result<Reader<R>, Error> {
+		let properties = Properties {
+			lc: 0,
+			lp: 0,
+			pb: 0,
+
+			dictionary:   dictionary,
+			uncompressed: None,
+		};
+
+		Ok(Reader {
+			decoder: try!(reader::Reader::with_preset(stream.take(0), properties, preset)),
+			chunk:   Chunk::Header,
+			skipped: 0,
+
+			need_dictionary: preset.is_empty(),
+			need_properties: true,
+		})
+	}
+
+	/// Creates a LZMA2 reader like `new`, failing before allocating anything
+	/// if decoding needs more memory than the limit.
+	pub fn with_memlimit(stream: R, dictionary: u32, memlimit: u64) -> Result<Reader<R>, Error> {
+		let properties = Properties {
+			lc: 0,
+			lp: 0,
+			pb: 0,
+
+			dictionary:   dictionary,
+			uncompressed: None,
+		};
+
+		let needed = decoder_memory_usage(dictionary);
+
+		if needed > memlimit {
+			return Err(Error::MemoryLimit { needed: needed, limit: memlimit });
+		}
+
+		Ok(Reader {
+			decoder: try!(reader::Reader::new(stream.take(0), properties)),
+			chunk:   Chunk::Header,
+			skipped: 0,
+
+			need_dictionary: true,
+			need_properties: true,
+		})
+	}
+
+	/// Returns the amount of decoded bytes.
+	pub fn decoded(&self) -> u64 {
+		self.decoder.decoded()
+	}
+
+	/// Checks if the end of the stream has been reached.
+	pub fn is_finished(&self) -> bool {
+		self.chunk == Chunk::Finished
+	}
+
+	/// Returns the inner stream.
+	pub fn get_ref(&self) -> &R {
+		self.decoder.get_ref().get_ref()
+	}
+
+	// the inner stream, to append chunks to a stream decoded as it comes
+	pub(crate) fn get_mut(&mut self) -> &mut R {
+		unsafe { self.decoder.inner() }.get_mut()
+	}
+
+	/// Unwraps this `Reader`, returning the underlying reader.
+	pub fn into_inner(self) -> R {
+		self.decoder.into_inner().into_inner()
+	}
+
+	fn header(&mut self) -> Result<(), Error> {
+		let stream = unsafe { self.decoder.inner() };
+		stream.set_limit(6);
+
+		let control = try!(stream.read_u8());
+
+		if control == 0x00 {
+			self.chunk = Chunk::Finished;
+			return Ok(());
+		}
+
+		if (0x03 .. 0x80).contains(&control) {
+			return Err(Error::Corrupted);
+		}
+
+		// an uncompressed chunk, optionally resetting the dictionary
+		if control < 0x80 {
+			let size = try!(stream.read_u16::<BigEndian>()) as u64 + 1;
+
+			// the next compressed chunk has to set the properties again
+			if control == 0x01 {
+				unsafe { self.decoder.reset_dictionary(); }
+				self.need_dictionary = false;
+				self.need_properties = true;
+			}
+			else if self.need_dictionary {
+				return Err(Error::Corrupted);
+			}
+
+			unsafe { self.decoder.inner().set_limit(size); }
+			self.chunk = Chunk::Uncompressed(size);
+			self.skip(3);
+
+			return Ok(());
+		}
+
+		let unpacked = ((control as u64 & 0x1f) << 16) + try!(stream.read_u16::<BigEndian>()) as u64 + 1;
+		let packed   = try!(stream.read_u16::<BigEndian>()) as u64 + 1;
+		let reset    = (control >> 5) & 0x03;
+
+		let properties = if reset >= 2 {
+			let properties = try!(properties::decode(try!(stream.read_u8()), self.decoder.properties().dictionary));
+
+			if properties.lc + properties.lp > 4 {
+				return Err(Error::InvalidProperties);
+			}
+
+			Some(properties)
+		}
+		else {
+			None
+		};
+
+		if reset == 3 {
+			unsafe { self.decoder.reset_dictionary(); }
+			self.need_dictionary = false;
+		}
+		else if self.need_dictionary {
+			return Err(Error::Corrupted);
+		}
+
+		if properties.is_some() {
+			unsafe { self.decoder.reset(properties); }
+			self.need_properties = false;
+		}
+		else if self.need_properties {
+			return Err(Error::Corrupted);
+		}
+
+		unsafe {
+			if reset >= 1 {
+				self.decoder.reset_state();
+			}
+			else {
+				self.decoder.reset_range();
+			}
+
+			let decoded = self.decoder.decoded();
+			self.decoder.set_uncompressed(Some(decoded + unpacked));
+			self.decoder.inner().set_limit(packed);
+		}
+
+		self.chunk = Chunk::Compressed;
+		self.skip(if reset >= 2 { 6 } else { 5 });
+
+		Ok(())
+	}
+
+	// accounts for bytes that aren't range coded data in error positions
+	fn skip(&mut self, length: u64) {
+		self.skipped += length;
+		self.decoder.set_base(self.skipped);
+	}
+}
+
+impl<R: Read> Read for Reader<R> {
+	fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+		if buf.is_empty() {
+			return Ok(0);
+		}
+
+		loop {
+			match self.chunk {
+				Chunk::Finished =>
+					return Ok(0),
+
+				Chunk::Header => match self.header() {
+					Err(Error::IO(err)) =>
+						return Err(err),
+
+					Err(err) =>
+						return Err(io::Error::other(err)),
+
+					Ok(()) =>
+						()
+				},
+
+				Chunk::Compressed => {
+					let read = try!(self.decoder.read(buf));
+
+					if read != 0 {
+						return Ok(read);
+					}
+
+					// the chunk has been decoded, no compressed data can be left over
+					if unsafe { self.decoder.inner() }.limit() != 0 || !self.decoder.is_range_finished() {
+						return Err(io::Error::other(Error::Corrupted));
+					}
+
+					self.chunk = Chunk::Header;
+				}
+
+				Chunk::Uncompressed(0) =>
+					self.chunk = Chunk::Header,
+
+				Chunk::Uncompressed(remaining) => {
+					let length = cmp::min(buf.len() as u64, remaining) as usize;
+					let read   = try!(unsafe { self.decoder.inner() }.read(&mut buf[.. length]));
+
+					if read == 0 {
+						return Err(io::Error::new(io::ErrorKind::UnexpectedEof, Error::NeedMoreData));
+					}
+
+					if let Err(err) = unsafe { self.decoder.push(io::sink(), &buf[.. read]) } {
+						return Err(io::Error::other(err));
+					}
+
+					self.chunk = Chunk::Uncompressed(remaining - read as u64);
+					self.skip(read as u64);
+
+					return Ok(read);
+				}
+			}
+		}
+	}
+}
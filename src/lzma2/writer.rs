@@ -0,0 +1,162 @@
+use std::io::{self, Write};
+use std::cmp;
+use std::mem;
+use byteorder::{BigEndian, WriteBytesExt};
+
+use {Error, Properties, properties, writer};
+
+/// The maximum amount of uncompressed data in a chunk.
+pub const CHUNK_UNCOMPRESSED_MAXIMUM: u64 = 1 << 21;
+
+/// The maximum amount of compressed data in a chunk.
+pub const CHUNK_COMPRESSED_MAXIMUM: u64 = 1 << 16;
+
+/// A LZMA2 stream writer.
+///
+/// The first chunk resets the dictionary and sets the model properties, the
+/// following ones keep the state of the previous. Chunks that don't compress
+/// are stored uncompressed instead, and the next compressed chunk resets the
+/// state.
+#[derive(Debug)]
+pub struct Writer<W: Write> {
+	stream:  W,
+	encoder: writer::Writer<Vec<u8>>,
+	pending: Vec<u8>,
+
+	received: u64,
+	start:    u64,
+	reset:    u8,
+}
+
+impl<W: Write> Writer<W> {
+	/// Creates a LZMA2 writer with the given model properties, the size is
+	/// ignored.
+	pub fn new(stream: W, properties: Properties) -> Result<Writer<W>, Error> {
+		if properties.lc + properties.lp > 4 {
+			return Err(Error::InvalidProperties);
+		}
+
+		let mut encoder = try!(writer::Writer::new(Vec::new(), Properties { uncompressed: None, .. properties }));
+		encoder.set_marker(false);
+		encoder.set_limit(Some(CHUNK_COMPRESSED_MAXIMUM));
+
+		Ok(Writer {
+			stream:  stream,
+			encoder: encoder,
+			pending: Vec::new(),
+
+			received: 0,
+			start:    0,
+			reset:    0xe0,
+		})
+	}
+
+	/// Returns the model properties.
+	pub fn properties(&self) -> &Properties {
+		self.encoder.properties()
+	}
+
+	/// Sets how hard the match finder searches, see `lzma::Writer::set_search`.
+	pub fn set_search(&mut self, depth: usize, nice: usize) {
+		self.encoder.set_search(depth, nice);
+	}
+
+	/// Returns the inner stream.
+	pub fn get_ref(&self) -> &W {
+		&self.stream
+	}
+
+	// writes the data encoded since the last chunk as a new chunk
+	fn chunk(&mut self) -> Result<(), Error> {
+		let unpacked = self.encoder.encoded() - self.start;
+
+		if unpacked == 0 {
+			return Ok(());
+		}
+
+		let packed = unsafe {
+			try!(self.encoder.restart());
+			mem::take(self.encoder.inner())
+		};
+
+		let header = if self.reset >= 0xc0 { 6 } else { 5 };
+
+		if unpacked <= CHUNK_COMPRESSED_MAXIMUM && unpacked + 3 <= packed.len() as u64 + header {
+			try!(self.stream.write_u8(if self.reset == 0xe0 { 0x01 } else { 0x02 }));
+			try!(self.stream.write_u16::<BigEndian>((unpacked - 1) as u16));
+			try!(self.stream.write_all(&self.pending[.. unpacked as usize]));
+
+			// the decoder didn't follow the encoder through the chunk
+			unsafe { self.encoder.reset_state(); }
+			self.reset = if self.reset >= 0xc0 { 0xc0 } else { 0xa0 };
+		}
+		else {
+			try!(self.stream.write_u8(self.reset | ((unpacked - 1) >> 16) as u8));
+			try!(self.stream.write_u16::<BigEndian>((unpacked - 1) as u16));
+			try!(self.stream.write_u16::<BigEndian>((packed.len() - 1) as u16));
+
+			if self.reset >= 0xc0 {
+				try!(self.stream.write_u8(try!(properties::encode(self.encoder.properties()))));
+			}
+
+			try!(self.stream.write_all(&packed));
+			self.reset = 0x80;
+		}
+
+		self.pending.drain(.. unpacked as usize);
+		self.start += unpacked;
+
+		Ok(())
+	}
+
+	/// Encodes the remaining data and writes the end of the stream, returning
+	/// the underlying writer.
+	pub fn finish(mut self) -> Result<W, Error> {
+		while !try!(self.encoder.drain()) {
+			try!(self.chunk());
+		}
+
+		try!(self.chunk());
+		try!(self.stream.write_u8(0x00));
+		try!(self.stream.flush());
+
+		Ok(self.stream)
+	}
+}
+
+impl<W: Write> Write for Writer<W> {
+	fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+		let mut written = 0;
+
+		while written < buf.len() {
+			let room = CHUNK_UNCOMPRESSED_MAXIMUM - (self.received - self.start);
+
+			if room == 0 || self.encoder.is_exhausted() {
+				match self.chunk() {
+					Err(Error::IO(err)) =>
+						return Err(err),
+
+					Err(err) =>
+						return Err(io::Error::other(err)),
+
+					Ok(()) =>
+						continue
+				}
+			}
+
+			// the encoder takes everything while it isn't exhausted
+			let length = cmp::min(room, (buf.len() - written) as u64) as usize;
+			let length = try!(self.encoder.write(&buf[written .. written + length]));
+
+			self.pending.extend_from_slice(&buf[written .. written + length]);
+			self.received += length as u64;
+			written       += length;
+		}
+
+		Ok(written)
+	}
+
+	fn flush(&mut self) -> io::Result<()> {
+		self.stream.flush()
+	}
+}
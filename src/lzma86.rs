@@ -0,0 +1,159 @@
+use std::io::{self, Read, Write};
+use byteorder::{ReadBytesExt, WriteBytesExt};
+
+use {Error, Properties, properties, reader, writer, filter};
+use filter::X86;
+
+/// The data is not filtered.
+pub const FILTER_NONE: u8 = 0;
+
+/// The data went through the x86 BCJ filter.
+pub const FILTER_X86: u8 = 1;
+
+#[derive(Debug)]
+enum Decoder<R: Read> {
+	None(reader::Reader<R>),
+	X86(filter::Reader<reader::Reader<R>, X86>),
+}
+
+#[derive(Debug)]
+enum Encoder<W: Write> {
+	None(writer::Writer<W>),
+	X86(filter::Writer<writer::Writer<W>, X86>),
+}
+
+/// A lzma86 stream reader.
+#[derive(Debug)]
+pub struct Reader<R: Read> {
+	inner: Decoder<R>,
+}
+
+impl<R: Read> Reader<R> {
+	/// Creates a lzma86 reader from the given stream, reading the filter and
+	/// the model properties.
+	pub fn new(stream: R) -> Result<Reader<R>, Error> {
+		Reader::with_memlimit(stream, u64::MAX)
+	}
+
+	/// Creates a lzma86 reader like `new`, failing before allocating anything
+	/// if decoding needs more memory than the limit.
+	pub fn with_memlimit(mut stream: R, memlimit: u64) -> Result<Reader<R>, Error> {
+		let filter     = try!(stream.read_u8());
+		let properties = try!(properties::read(stream.by_ref()));
+		let decoder    = try!(reader::Reader::with_memlimit(stream, properties, memlimit));
+
+		Ok(Reader {
+			inner: match filter {
+				FILTER_NONE =>
+					Decoder::None(decoder),
+
+				FILTER_X86 =>
+					Decoder::X86(filter::Reader::new(decoder, X86::decoder())),
+
+				_ =>
+					return Err(Error::InvalidHeader)
+			}
+		})
+	}
+
+	/// Returns the filter.
+	pub fn filter(&self) -> u8 {
+		match self.inner {
+			Decoder::None(_) => FILTER_NONE,
+			Decoder::X86(_)  => FILTER_X86,
+		}
+	}
+
+	/// Returns the model properties.
+	pub fn properties(&self) -> &Properties {
+		match self.inner {
+			Decoder::None(ref decoder) => decoder.properties(),
+			Decoder::X86(ref decoder)  => decoder.get_ref().properties(),
+		}
+	}
+}
+
+impl<R: Read> Read for Reader<R> {
+	fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+		match self.inner {
+			Decoder::None(ref mut decoder) => decoder.read(buf),
+			Decoder::X86(ref mut decoder)  => decoder.read(buf),
+		}
+	}
+}
+
+/// A lzma86 stream writer.
+#[derive(Debug)]
+pub struct Writer<W: Write> {
+	inner: Encoder<W>,
+}
+
+impl<W: Write> Writer<W> {
+	/// Creates a lzma86 writer with the given filter and model properties.
+	///
+	/// Note that the 7-Zip decoder requires the uncompressed size to be known.
+	pub fn new(mut stream: W, filter: u8, properties: Properties) -> Result<Writer<W>, Error> {
+		if filter != FILTER_NONE && filter != FILTER_X86 {
+			return Err(Error::InvalidHeader);
+		}
+
+		try!(stream.write_u8(filter));
+
+		let encoder = try!(writer::Writer::to(stream, properties));
+
+		Ok(Writer {
+			inner: if filter == FILTER_X86 {
+				Encoder::X86(filter::Writer::new(encoder, X86::encoder()))
+			}
+			else {
+				Encoder::None(encoder)
+			}
+		})
+	}
+
+	/// Returns the model properties.
+	pub fn properties(&self) -> &Properties {
+		match self.inner {
+			Encoder::None(ref encoder) => encoder.properties(),
+			Encoder::X86(ref encoder)  => encoder.get_ref().properties(),
+		}
+	}
+
+	/// Encodes the remaining data and finishes the stream, returning the
+	/// underlying writer.
+	pub fn finish(self) -> Result<W, Error> {
+		match self.inner {
+			Encoder::None(encoder) =>
+				encoder.finish(),
+
+			Encoder::X86(encoder) =>
+				try!(encoder.finish()).finish(),
+		}
+	}
+}
+
+impl<W: Write> Write for Writer<W> {
+	fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+		match self.inner {
+			Encoder::None(ref mut encoder) => encoder.write(buf),
+			Encoder::X86(ref mut encoder)  => encoder.write(buf),
+		}
+	}
+
+	fn flush(&mut self) -> io::Result<()> {
+		match self.inner {
+			Encoder::None(ref mut encoder) => encoder.flush(),
+			Encoder::X86(ref mut encoder)  => encoder.flush(),
+		}
+	}
+}
+
+/// Create a lzma86 stream from another stream.
+pub fn read<T: Read>(stream: T) -> Result<Reader<T>, Error> {
+	Reader::new(stream)
+}
+
+/// Create a lzma86 stream to another stream.
+pub fn write<T: Write>(stream: T, filter: u8, properties: Properties) -> Result<Writer<T>, Error> {
+	Writer::new(stream, filter, properties)
+}
@@ -0,0 +1,189 @@
+use std::io::{self, Read, Write};
+use std::cmp;
+
+use {Error, Properties, properties, reader, writer};
+
+/// A MicroLZMA stream reader.
+#[derive(Debug)]
+pub struct Reader<R: Read> {
+	inner: reader::Reader<Input<R>>,
+}
+
+impl<R: Read> Reader<R> {
+	/// Creates a MicroLZMA reader from the given stream.
+	///
+	/// Both the compressed and uncompressed sizes must be exact, and the
+	/// dictionary size has to come from elsewhere too.
+	pub fn new(stream: R, compressed: u64, uncompressed: u64, dictionary: u32) -> Result<Reader<R>, Error> {
+		Reader::with_memlimit(stream, compressed, uncompressed, dictionary, u64::MAX)
+	}
+
+	/// Creates a MicroLZMA reader like `new`, failing before allocating
+	/// anything if decoding needs more memory than the limit.
+	pub fn with_memlimit(mut stream: R, compressed: u64, uncompressed: u64, dictionary: u32, memlimit: u64) -> Result<Reader<R>, Error> {
+		let mut byte = [0u8; 1];
+		try!(stream.read_exact(&mut byte));
+
+		let mut properties = try!(properties::decode(!byte[0], dictionary));
+		properties.uncompressed = Some(uncompressed);
+
+		let input = Input {
+			stream:   stream,
+			limit:    compressed,
+			consumed: 1,
+			seeded:   false,
+		};
+
+		Ok(Reader {
+			inner: try!(reader::Reader::with_memlimit(input, properties, memlimit)),
+		})
+	}
+
+	/// Returns the model properties.
+	pub fn properties(&self) -> &Properties {
+		self.inner.properties()
+	}
+
+	/// Returns the amount of compressed bytes consumed so far.
+	pub fn consumed(&self) -> u64 {
+		self.inner.get_ref().consumed
+	}
+
+	/// Unwraps this `Reader`, returning the underlying reader.
+	pub fn into_inner(self) -> R {
+		self.inner.into_inner().stream
+	}
+}
+
+impl<R: Read> Read for Reader<R> {
+	fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+		self.inner.read(buf)
+	}
+}
+
+/// A MicroLZMA stream writer.
+#[derive(Debug)]
+pub struct Writer<W: Write> {
+	inner: writer::Writer<Output<W>>,
+}
+
+impl<W: Write> Writer<W> {
+	/// Creates a MicroLZMA writer with the given model properties.
+	///
+	/// The EOS marker is never written so the amount of uncompressed data must
+	/// be stored elsewhere, when the compressed size is limited only part of
+	/// the input might be encoded, see `encoded`.
+	pub fn new(stream: W, properties: Properties, limit: Option<u64>) -> Result<Writer<W>, Error> {
+		let output = Output {
+			stream: stream,
+			first:  Some(!try!(properties::encode(&properties))),
+		};
+
+		let mut inner = try!(writer::Writer::new(output, properties));
+		inner.set_marker(false);
+		inner.set_limit(limit);
+
+		Ok(Writer {
+			inner: inner,
+		})
+	}
+
+	/// Returns the model properties.
+	pub fn properties(&self) -> &Properties {
+		self.inner.properties()
+	}
+
+	/// Checks if the compressed size limit has been reached.
+	pub fn is_exhausted(&self) -> bool {
+		self.inner.is_exhausted()
+	}
+
+	/// Returns the amount of uncompressed bytes encoded so far.
+	pub fn encoded(&self) -> u64 {
+		self.inner.encoded()
+	}
+
+	/// Finishes the stream, returning the underlying writer.
+	pub fn finish(self) -> Result<W, Error> {
+		Ok(try!(self.inner.finish()).stream)
+	}
+}
+
+impl<W: Write> Write for Writer<W> {
+	fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+		self.inner.write(buf)
+	}
+
+	fn flush(&mut self) -> io::Result<()> {
+		self.inner.flush()
+	}
+}
+
+/// The compressed input, the range decoder gets back the zero it expects in
+/// place of the properties byte, and reading stops at the compressed size.
+#[derive(Debug)]
+struct Input<R: Read> {
+	stream:   R,
+	limit:    u64,
+	consumed: u64,
+	seeded:   bool,
+}
+
+impl<R: Read> Read for Input<R> {
+	fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+		if buf.is_empty() {
+			return Ok(0);
+		}
+
+		if !self.seeded {
+			buf[0]      = 0;
+			self.seeded = true;
+
+			return Ok(1);
+		}
+
+		let length = cmp::min(buf.len() as u64, self.limit.saturating_sub(self.consumed)) as usize;
+		let read   = try!(self.stream.read(&mut buf[.. length]));
+
+		self.consumed += read as u64;
+
+		Ok(read)
+	}
+}
+
+/// The compressed output, the zero written first by the range encoder is
+/// replaced with the inverted properties byte.
+#[derive(Debug)]
+struct Output<W: Write> {
+	stream: W,
+	first:  Option<u8>,
+}
+
+impl<W: Write> Write for Output<W> {
+	fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+		if buf.is_empty() {
+			return Ok(0);
+		}
+
+		if let Some(byte) = self.first.take() {
+			try!(self.stream.write_all(&[byte]));
+			return Ok(1);
+		}
+
+		self.stream.write(buf)
+	}
+
+	fn flush(&mut self) -> io::Result<()> {
+		self.stream.flush()
+	}
+}
+
+/// Create a MicroLZMA stream from another stream.
+pub fn read<T: Read>(stream: T, compressed: u64, uncompressed: u64, dictionary: u32) -> Result<Reader<T>, Error> {
+	Reader::new(stream, compressed, uncompressed, dictionary)
+}
+
+/// Create a MicroLZMA stream to another stream.
+pub fn write<T: Write>(stream: T, properties: Properties, limit: Option<u64>) -> Result<Writer<T>, Error> {
+	Writer::new(stream, properties, limit)
+}
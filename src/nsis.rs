@@ -0,0 +1,311 @@
+use std::io::{self, Read, Take};
+use std::cmp;
+use byteorder::{LittleEndian, ReadBytesExt};
+
+use {Error, properties, reader};
+use consts::MINIMUM_DICTIONARY_SIZE;
+
+/// The flag marking a compressed block in non-solid data.
+const COMPRESSED_FLAG: u32 = 0x80000000;
+
+// the largest dictionary taken for solid data
+const MAXIMUM_DICTIONARY_SIZE: u32 = 1 << 30;
+
+/// An embedded file.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub struct Entry {
+	/// The index of the file.
+	pub index: usize,
+
+	/// The offset of the block, in the uncompressed stream for solid data.
+	pub offset: u64,
+
+	/// The size of the block, not including the length.
+	pub size: u64,
+
+	/// Whether the block is compressed on its own.
+	pub compressed: bool,
+}
+
+#[derive(Debug)]
+enum State<R: Read> {
+	Solid(reader::Reader<R>),
+	Stored(R),
+	Compressed(reader::Reader<Take<R>>),
+}
+
+/// A NSIS data reader, the contents of the current file can be read after
+/// moving to it with `next_entry`.
+#[derive(Debug)]
+pub struct Reader<R: Read> {
+	state: Option<State<R>>,
+
+	index:     usize,
+	offset:    u64,
+	remaining: u64,
+
+	memlimit: u64,
+	failed:   Option<Error>,
+}
+
+impl<R: Read> Reader<R> {
+	/// Creates a reader for solid data, a single LZMA stream with the files
+	/// prefixed by their length.
+	///
+	/// The stream is expected to end with the EOS marker.
+	pub fn solid(stream: R) -> Result<Reader<R>, Error> {
+		Reader::solid_with_memlimit(stream, u64::MAX)
+	}
+
+	/// Creates a reader for solid data like `solid`, failing before
+	/// allocating anything if decoding needs more memory than the limit.
+	pub fn solid_with_memlimit(mut stream: R, memlimit: u64) -> Result<Reader<R>, Error> {
+		let properties = try!(properties::read_model(stream.by_ref()));
+
+		Ok(Reader {
+			state: Some(State::Solid(try!(reader::Reader::with_memlimit(stream, properties, memlimit)))),
+
+			index:     0,
+			offset:    0,
+			remaining: 0,
+
+			memlimit: memlimit,
+			failed:   None,
+		})
+	}
+
+	/// Creates a reader for non-solid data, where every file is prefixed by
+	/// its length and can be compressed on its own.
+	pub fn non_solid(stream: R) -> Reader<R> {
+		Reader::non_solid_with_memlimit(stream, u64::MAX)
+	}
+
+	/// Creates a reader for non-solid data like `non_solid`, every compressed
+	/// file fails before allocating anything if decoding it needs more memory
+	/// than the limit.
+	pub fn non_solid_with_memlimit(stream: R, memlimit: u64) -> Reader<R> {
+		Reader {
+			state: Some(State::Stored(stream)),
+
+			index:     0,
+			offset:    0,
+			remaining: 0,
+
+			memlimit: memlimit,
+			failed:   None,
+		}
+	}
+
+	/// Checks if the data is solid.
+	pub fn is_solid(&self) -> bool {
+		matches!(self.state, Some(State::Solid(_)))
+	}
+
+	// skips what's left of the current file, going back to the raw stream in
+	// non-solid data
+	fn skip(&mut self) -> Result<(), Error> {
+		try!(io::copy(self, &mut io::sink()));
+
+		self.state = match self.state.take() {
+			Some(State::Compressed(decoder)) => {
+				let mut stream = decoder.into_inner();
+				try!(io::copy(&mut stream, &mut io::sink()));
+
+				Some(State::Stored(stream.into_inner()))
+			}
+
+			state =>
+				state
+		};
+
+		Ok(())
+	}
+
+	/// Moves to the next file, returns `None` when there are no more.
+	///
+	/// The stream is left in the middle of a file when moving fails, so the
+	/// error is kept and every later call returns it again.
+	pub fn next_entry(&mut self) -> Result<Option<Entry>, Error> {
+		if let Some(ref err) = self.failed {
+			return Err(err.duplicate());
+		}
+
+		let result = self.advance();
+
+		if let Err(ref err) = result {
+			self.failed = Some(err.duplicate());
+		}
+
+		result
+	}
+
+	// moves to the next file
+	fn advance(&mut self) -> Result<Option<Entry>, Error> {
+		if self.state.is_none() {
+			return Ok(None);
+		}
+
+		try!(self.skip());
+
+		let entry = match self.state.take() {
+			Some(State::Solid(mut decoder)) => {
+				let length = match try!(read_length(decoder.by_ref())) {
+					Some(length) =>
+						length,
+
+					None =>
+						return Ok(None)
+				};
+
+				self.state     = Some(State::Solid(decoder));
+				self.remaining = length as u64;
+
+				Entry {
+					index:      self.index,
+					offset:     self.offset,
+					size:       length as u64,
+					compressed: false,
+				}
+			}
+
+			Some(State::Stored(mut stream)) => {
+				let length = match try!(read_length(stream.by_ref())) {
+					Some(length) =>
+						length,
+
+					None =>
+						return Ok(None)
+				};
+
+				let size = (length & !COMPRESSED_FLAG) as u64;
+
+				if length & COMPRESSED_FLAG != 0 {
+					let mut stream     = stream.take(size);
+					let     properties = try!(properties::read_model(stream.by_ref()));
+
+					self.state     = Some(State::Compressed(try!(reader::Reader::with_memlimit(stream, properties, self.memlimit))));
+					self.remaining = 0;
+				}
+				else {
+					self.state     = Some(State::Stored(stream));
+					self.remaining = size;
+				}
+
+				Entry {
+					index:      self.index,
+					offset:     self.offset,
+					size:       size,
+					compressed: length & COMPRESSED_FLAG != 0,
+				}
+			}
+
+			_ =>
+				return Err(Error::Corrupted)
+		};
+
+		self.index  += 1;
+		self.offset += 4 + entry.size;
+
+		Ok(Some(entry))
+	}
+}
+
+impl<R: Read> Read for Reader<R> {
+	fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+		match self.state {
+			Some(State::Compressed(ref mut decoder)) =>
+				decoder.read(buf),
+
+			Some(State::Solid(ref mut decoder)) => {
+				let length = cmp::min(buf.len() as u64, self.remaining) as usize;
+				let read   = try!(decoder.read(&mut buf[.. length]));
+
+				if read == 0 && length != 0 {
+					return Err(io::Error::new(io::ErrorKind::UnexpectedEof, Error::NeedMoreData));
+				}
+
+				self.remaining -= read as u64;
+				Ok(read)
+			}
+
+			Some(State::Stored(ref mut stream)) => {
+				let length = cmp::min(buf.len() as u64, self.remaining) as usize;
+				let read   = try!(stream.read(&mut buf[.. length]));
+
+				if read == 0 && length != 0 {
+					return Err(io::Error::new(io::ErrorKind::UnexpectedEof, Error::NeedMoreData));
+				}
+
+				self.remaining -= read as u64;
+				Ok(read)
+			}
+
+			None => match self.failed {
+				Some(ref err) =>
+					Err(io::Error::other(err.duplicate())),
+
+				None =>
+					Ok(0)
+			}
+		}
+	}
+}
+
+// reads the length of a block, or nothing if the stream is over
+fn read_length<T: Read>(mut stream: T) -> Result<Option<u32>, Error> {
+	let mut buffer = [0u8; 4];
+
+	loop {
+		match stream.read(&mut buffer[.. 1]) {
+			Ok(0) =>
+				return Ok(None),
+
+			Ok(_) =>
+				break,
+
+			Err(ref err) if err.kind() == io::ErrorKind::Interrupted =>
+				continue,
+
+			Err(err) =>
+				return Err(Error::IO(err))
+		}
+	}
+
+	try!(stream.read_exact(&mut buffer[1 ..]));
+
+	Ok(Some(try!((&buffer[..]).read_u32::<LittleEndian>())))
+}
+
+/// Checks if the data starts with LZMA properties for solid compression,
+/// rather than a block length.
+///
+/// Any dictionary from 4 KiB up to 1 GiB is accepted, the length of a
+/// compressed first block in non-solid data looks like a larger one.
+pub fn is_solid(header: &[u8]) -> bool {
+	if header.len() < 5 {
+		return false;
+	}
+
+	let dictionary = (&header[1 .. 5]).read_u32::<LittleEndian>().unwrap();
+
+	properties::decode(header[0], dictionary).is_ok() &&
+		(MINIMUM_DICTIONARY_SIZE ..= MAXIMUM_DICTIONARY_SIZE).contains(&dictionary)
+}
+
+/// List the files in NSIS data.
+pub fn list<T: Read>(stream: T, solid: bool) -> Result<Vec<Entry>, Error> {
+	let mut reader = if solid {
+		try!(Reader::solid(stream))
+	}
+	else {
+		Reader::non_solid(stream)
+	};
+
+	let mut entries = Vec::new();
+
+	while let Some(entry) = try!(reader.next_entry()) {
+		entries.push(entry);
+	}
+
+	Ok(entries)
+}
@@ -0,0 +1,150 @@
+use std::io::{self, Read, Write};
+use std::cmp;
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+
+use {Error, Properties, properties, reader, writer};
+use check::{Crc32, crc32};
+use consts::MINIMUM_DICTIONARY_SIZE;
+
+/// The magic bytes at the start of a patch.
+pub const MAGIC: [u8; 4] = [b'L', b'Z', b'P', b'T'];
+
+/// Creates a patch turning `old` into `new`.
+///
+/// The new file is encoded with the old one as preset dictionary, so the
+/// dictionary covers both and the unchanged parts become matches. Past the
+/// largest dictionary only the end of the old file is within reach.
+pub fn diff(old: &[u8], new: &[u8]) -> Result<Vec<u8>, Error> {
+	diff_with_memlimit(old, new, u64::MAX)
+}
+
+/// Creates a patch like `diff`, failing before allocating anything if the
+/// match finder and the buffer holding both files need more memory than the
+/// limit.
+pub fn diff_with_memlimit(old: &[u8], new: &[u8], memlimit: u64) -> Result<Vec<u8>, Error> {
+	let size = old.len() as u64 + new.len() as u64;
+
+	let properties = Properties {
+		lc: 3,
+		lp: 0,
+		pb: 2,
+
+		dictionary:   cmp::max(cmp::min(size, u32::MAX as u64) as u32, MINIMUM_DICTIONARY_SIZE),
+		uncompressed: Some(new.len() as u64),
+	};
+
+	let needed = size + writer::Window::memory_usage(properties.dictionary);
+
+	if needed > memlimit {
+		return Err(Error::MemoryLimit { needed: needed, limit: memlimit });
+	}
+
+	let mut patch = Vec::new();
+	try!(patch.write_all(&MAGIC));
+	try!(patch.write_u64::<LittleEndian>(old.len() as u64));
+	try!(patch.write_u32::<LittleEndian>(crc32(old)));
+	try!(patch.write_u64::<LittleEndian>(new.len() as u64));
+	try!(patch.write_u32::<LittleEndian>(crc32(new)));
+	try!(properties::write_model(&mut patch, &properties));
+
+	let mut writer = try!(writer::Writer::with_preset(patch, properties, old));
+	try!(writer.write_all(new));
+
+	writer.finish()
+}
+
+/// Applies a patch created by `diff` to the old file, writing the new one to
+/// the given stream and returning it.
+///
+/// Both files are streamed, only the window of the decoder is kept in memory.
+pub fn patch<O: Read, P: Read, W: Write>(old: O, patch: P, new: W) -> Result<W, Error> {
+	patch_with_memlimit(old, patch, new, u64::MAX)
+}
+
+/// Applies a patch like `patch`, failing before allocating anything if
+/// decoding needs more memory than the limit.
+pub fn patch_with_memlimit<O: Read, P: Read, W: Write>(old: O, mut patch: P, new: W, memlimit: u64) -> Result<W, Error> {
+	let mut magic = [0u8; 4];
+	try!(patch.read_exact(&mut magic));
+
+	if magic != MAGIC {
+		return Err(Error::InvalidHeader);
+	}
+
+	let old_size = try!(patch.read_u64::<LittleEndian>());
+	let old_crc  = try!(patch.read_u32::<LittleEndian>());
+	let new_size = try!(patch.read_u64::<LittleEndian>());
+	let new_crc  = try!(patch.read_u32::<LittleEndian>());
+
+	let mut properties = try!(properties::read_model(patch.by_ref()));
+	properties.uncompressed = Some(new_size);
+
+	// the old file takes room in the window like decoded data would
+	let needed = Properties { uncompressed: Some(old_size.saturating_add(new_size)), .. properties }.decoder_memory_usage();
+
+	if needed > memlimit {
+		return Err(Error::MemoryLimit { needed: needed, limit: memlimit });
+	}
+
+	let mut old    = Digest::new(old);
+	let mut reader = match reader::Reader::with_preset_from(patch, properties, &mut old, old_size) {
+		Err(Error::IO(ref err)) if err.kind() == io::ErrorKind::UnexpectedEof =>
+			return Err(Error::Checksum),
+
+		result =>
+			try!(result)
+	};
+
+	// the patch was made for another file
+	if old.crc.value() != old_crc || try!(old.inner.read(&mut [0])) != 0 {
+		return Err(Error::Checksum);
+	}
+
+	let mut new     = Digest::new(new);
+	let     written = try!(io::copy(&mut reader, &mut new).map_err(Error::from_io));
+
+	if written != new_size || new.crc.value() != new_crc {
+		return Err(Error::Checksum);
+	}
+
+	try!(new.inner.flush());
+
+	Ok(new.inner)
+}
+
+// a stream computing the CRC of what goes through it
+struct Digest<T> {
+	inner: T,
+	crc:   Crc32,
+}
+
+impl<T> Digest<T> {
+	fn new(inner: T) -> Digest<T> {
+		Digest {
+			inner: inner,
+			crc:   Crc32::new(),
+		}
+	}
+}
+
+impl<R: Read> Read for Digest<R> {
+	fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+		let read = try!(self.inner.read(buf));
+		self.crc.update(&buf[.. read]);
+
+		Ok(read)
+	}
+}
+
+impl<W: Write> Write for Digest<W> {
+	fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+		let written = try!(self.inner.write(buf));
+		self.crc.update(&buf[.. written]);
+
+		Ok(written)
+	}
+
+	fn flush(&mut self) -> io::Result<()> {
+		self.inner.flush()
+	}
+}
@@ -0,0 +1,199 @@
+use std::io::{self, Read, Take, Chain, Cursor, BufReader};
+use std::fs::File;
+use std::path::Path;
+use std::cmp;
+use byteorder::{BigEndian, ReadBytesExt};
+
+use {Error, xz};
+use error::{self, Sticky};
+
+/// Flag set when another chunk follows.
+pub const MORE_FLAG: u64 = 1 << 24;
+
+// the bytes peeked to tell the chunk kind are put back in front of it
+type Input<R> = Chain<Cursor<Vec<u8>>, Take<R>>;
+
+#[derive(Debug)]
+enum Chunk<R: Read> {
+	Header(R),
+	Raw(Input<R>),
+	Xz(Box<xz::Reader<Input<R>>>),
+}
+
+/// A pbzx payload reader, chaining the chunks into a single stream.
+#[derive(Debug)]
+pub struct Reader<R: Read> {
+	chunk:  Option<Chunk<R>>,
+	flags:  u64,
+	chunks: u64,
+
+	memlimit: u64,
+	failed:   Option<Error>,
+}
+
+impl<R: Read> Reader<R> {
+	/// Creates a pbzx reader from the given stream, reading the magic and
+	/// the flags.
+	pub fn new(stream: R) -> Result<Reader<R>, Error> {
+		Reader::with_memlimit(stream, u64::MAX)
+	}
+
+	/// Creates a pbzx reader like `new`, with a memory limit for every xz
+	/// chunk.
+	pub fn with_memlimit(mut stream: R, memlimit: u64) -> Result<Reader<R>, Error> {
+		let mut magic = [0u8; 4];
+		try!(stream.read_exact(&mut magic));
+
+		if &magic != b"pbzx" {
+			return Err(Error::InvalidHeader);
+		}
+
+		let flags = try!(stream.read_u64::<BigEndian>());
+
+		Ok(Reader {
+			chunk:  Some(Chunk::Header(stream)),
+			flags:  flags,
+			chunks: 0,
+
+			memlimit: memlimit,
+			failed:   None,
+		})
+	}
+
+	/// Returns the amount of chunks encountered so far.
+	pub fn chunks(&self) -> u64 {
+		self.chunks
+	}
+
+	/// Unwraps this `Reader`, returning the underlying reader.
+	///
+	/// Note that unwrapping in the middle of a chunk leaves the rest of it in
+	/// the stream.
+	pub fn into_inner(self) -> R {
+		match self.chunk.unwrap() {
+			Chunk::Header(stream) => stream,
+			Chunk::Raw(stream)    => stream.into_inner().1.into_inner(),
+			Chunk::Xz(stream)     => stream.into_inner().into_inner().1.into_inner(),
+		}
+	}
+
+	// reads the next chunk header, returns false when there are no more chunks
+	fn next(&mut self, mut stream: R) -> Result<bool, Error> {
+		if self.flags & MORE_FLAG == 0 {
+			self.chunk = Some(Chunk::Header(stream));
+			return Ok(false);
+		}
+
+		// the stream stays with the reader whatever happens
+		let (flags, length, peeked) = match Reader::header(&mut stream) {
+			Ok(header) =>
+				header,
+
+			Err(err) => {
+				self.chunk = Some(Chunk::Header(stream));
+				return Err(err);
+			}
+		};
+
+		let is_xz = peeked.starts_with(&xz::HEADER_MAGIC);
+		let rest  = length - peeked.len() as u64;
+		let input = Cursor::new(peeked).chain(stream.take(rest));
+
+		self.flags   = flags;
+		self.chunks += 1;
+		self.chunk   = Some(if is_xz {
+			Chunk::Xz(Box::new(try!(xz::Reader::with_memlimit(input, self.memlimit))))
+		}
+		else {
+			Chunk::Raw(input)
+		});
+
+		Ok(true)
+	}
+
+	// reads the flags and length of a chunk and peeks at its start, the xz
+	// stream header is checked here so creating its reader can't fail
+	fn header(stream: &mut R) -> Result<(u64, u64, Vec<u8>), Error> {
+		let flags  = try!(stream.read_u64::<BigEndian>());
+		let length = try!(stream.read_u64::<BigEndian>());
+
+		let mut peeked = Vec::new();
+		try!(stream.by_ref().take(cmp::min(length, 12)).read_to_end(&mut peeked));
+
+		if (peeked.len() as u64) < cmp::min(length, 12) {
+			return Err(Error::IO(io::ErrorKind::UnexpectedEof.into()));
+		}
+
+		if peeked.starts_with(&xz::HEADER_MAGIC) {
+			try!(xz::Flags::read_header(&peeked[..]));
+		}
+
+		Ok((flags, length, peeked))
+	}
+
+	// unwraps the chunk, returning the stream and whether the chunk has been
+	// consumed entirely
+	fn end(chunk: Chunk<R>) -> (R, bool) {
+		let stream = match chunk {
+			Chunk::Header(stream) =>
+				return (stream, true),
+
+			Chunk::Raw(stream) =>
+				stream.into_inner().1,
+
+			Chunk::Xz(stream) =>
+				stream.into_inner().into_inner().1,
+		};
+
+		let consumed = stream.limit() == 0;
+
+		(stream.into_inner(), consumed)
+	}
+}
+
+impl<R: Read> Sticky for Reader<R> {
+	fn failed(&mut self) -> &mut Option<Error> {
+		&mut self.failed
+	}
+
+	fn decode(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+		loop {
+			let read = try!(match *self.chunk.as_mut().unwrap() {
+				Chunk::Header(_)          => Ok(0),
+				Chunk::Raw(ref mut input) => input.read(buf),
+				Chunk::Xz(ref mut input)  => input.read(buf),
+			});
+
+			if read != 0 {
+				return Ok(read);
+			}
+
+			let (stream, consumed) = Reader::end(self.chunk.take().unwrap());
+
+			if !consumed {
+				self.chunk = Some(Chunk::Header(stream));
+				return Err(Error::Corrupted);
+			}
+
+			if !try!(self.next(stream)) {
+				return Ok(0);
+			}
+		}
+	}
+}
+
+impl<R: Read> Read for Reader<R> {
+	fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+		error::read(self, buf)
+	}
+}
+
+/// Open a file as a pbzx payload.
+pub fn open<T: AsRef<Path>>(path: T) -> Result<Reader<BufReader<File>>, Error> {
+	read(BufReader::new(try!(File::open(path))))
+}
+
+/// Create a pbzx payload reader from another stream.
+pub fn read<T: Read>(stream: T) -> Result<Reader<T>, Error> {
+	Reader::new(stream)
+}
@@ -0,0 +1,75 @@
+use std::sync::{Arc, Mutex, mpsc};
+use std::thread;
+use std::fmt;
+
+type Job = Box<dyn FnOnce() + Send>;
+
+/// A fixed pool of worker threads.
+///
+/// The workers stop once the pool is dropped and their current job is done.
+pub struct Pool {
+	jobs:    mpsc::Sender<Job>,
+	threads: usize,
+}
+
+impl Pool {
+	/// Spawns the given amount of workers, at least one.
+	pub fn new(threads: usize) -> Pool {
+		let (sender, receiver) = mpsc::channel::<Job>();
+		let receiver = Arc::new(Mutex::new(receiver));
+		let threads  = if threads == 0 { 1 } else { threads };
+
+		for _ in 0 .. threads {
+			let receiver = receiver.clone();
+
+			thread::spawn(move || loop {
+				let job = match receiver.lock().unwrap().recv() {
+					Ok(job) => job,
+					Err(_)  => break,
+				};
+
+				job();
+			});
+		}
+
+		Pool {
+			jobs:    sender,
+			threads: threads,
+		}
+	}
+
+	/// Returns the amount of workers.
+	pub fn threads(&self) -> usize {
+		self.threads
+	}
+
+	/// Runs the job on the first free worker.
+	pub fn execute<F: FnOnce() + Send + 'static>(&self, job: F) {
+		// the workers only stop with the pool
+		let _ = self.jobs.send(Box::new(job));
+	}
+
+	/// Runs the job on the first free worker, returning a channel with what
+	/// the job sends through it as it goes followed by its result.
+	///
+	/// The channel holds one message, a job sending more waits for them to be
+	/// received.
+	pub fn spawn<T: Send + 'static, F: FnOnce(&mpsc::SyncSender<T>) -> T + Send + 'static>(&self, job: F) -> mpsc::Receiver<T> {
+		let (sender, receiver) = mpsc::sync_channel(1);
+
+		self.execute(move || {
+			let result = job(&sender);
+
+			// the receiver might be gone by the time the job is done
+			let _ = sender.send(result);
+		});
+
+		receiver
+	}
+}
+
+impl fmt::Debug for Pool {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("Pool").field("threads", &self.threads).finish()
+	}
+}
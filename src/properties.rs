@@ -1,9 +1,11 @@
-use std::io::Read;
+use std::io::{Read, Write};
 use std::u64;
-use byteorder::{LittleEndian, ReadBytesExt};
+use std::cmp;
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
 
 use {Error};
-use consts::MINIMUM_DICTIONARY_SIZE;
+use consts::{MINIMUM_DICTIONARY_SIZE, FULL_DISTANCES, END_POSITION_MODEL_INDEX, STATES};
+use consts::{POSITION_BITS_MAX, LENGTH_TO_POSITION_STATES, ALIGN_BITS};
 
 /// LZMA model properties.
 #[derive(PartialEq, Eq, Clone, Copy, Debug)]
@@ -24,10 +26,64 @@ pub struct Properties {
 	pub uncompressed: Option<u64>,
 }
 
+impl Properties {
+	/// Returns the size of the window needed to decode, which is never bigger
+	/// than the data.
+	pub fn window(&self) -> u32 {
+		match self.uncompressed {
+			Some(size) if size < self.dictionary as u64 =>
+				cmp::max(size, 1) as u32,
+
+			_ =>
+				self.dictionary
+		}
+	}
+
+	/// Returns the amount of memory in bytes a `Reader` needs for these
+	/// properties at most.
+	pub fn decoder_memory_usage(&self) -> u64 {
+		// every probability is an u16
+		let probabilities =
+			(0x300 << (self.lc + self.lp)) +
+			(1 + FULL_DISTANCES - END_POSITION_MODEL_INDEX) +
+			2 * (2 + (2 << (POSITION_BITS_MAX + 3)) + (1 << 8)) +
+			(LENGTH_TO_POSITION_STATES << 6) +
+			(1 << ALIGN_BITS) +
+			((2 * STATES) << POSITION_BITS_MAX) +
+			4 * STATES;
+
+		self.window() as u64 + probabilities as u64 * 2
+	}
+}
+
+/// The size of the properties header written by `write`.
+pub const HEADER_SIZE: u64 = 13;
+
 /// Read the model properties from a stream.
 pub fn read<T: Read>(mut stream: T) -> Result<Properties, Error> {
+	let mut properties = try!(read_model(stream.by_ref()));
+
+	properties.uncompressed = match try!(stream.read_u64::<LittleEndian>()) {
+		u64::MAX =>
+			None,
+
+		n =>
+			Some(n)
+	};
+
+	Ok(properties)
+}
+
+/// Read the model properties from a stream, without the uncompressed size.
+pub fn read_model<T: Read>(mut stream: T) -> Result<Properties, Error> {
 	let d = try!(stream.read_u8());
 
+	decode(d, try!(stream.read_u32::<LittleEndian>()))
+}
+
+/// Decode the model properties from the packed `lc`, `lp` and `pb` byte and
+/// the dictionary size.
+pub fn decode(d: u8, dictionary: u32) -> Result<Properties, Error> {
 	if d >= (9 * 5 * 5) {
 		return Err(Error::InvalidProperties);
 	}
@@ -37,7 +93,7 @@ pub fn read<T: Read>(mut stream: T) -> Result<Properties, Error> {
 	let pb = d / 5;
 	let lp = d % 5;
 
-	let dictionary = match try!(stream.read_u32::<LittleEndian>()) {
+	let dictionary = match dictionary {
 		n if n < MINIMUM_DICTIONARY_SIZE =>
 			MINIMUM_DICTIONARY_SIZE,
 
@@ -45,20 +101,37 @@ pub fn read<T: Read>(mut stream: T) -> Result<Properties, Error> {
 			n
 	};
 
-	let uncompressed = match try!(stream.read_u64::<LittleEndian>()) {
-		u64::MAX =>
-			None,
-
-		n =>
-			Some(n)
-	};
-
 	Ok(Properties {
 		lc: lc,
 		lp: lp,
 		pb: pb,
 
 		dictionary:   dictionary,
-		uncompressed: uncompressed,
+		uncompressed: None,
 	})
 }
+
+/// Encode `lc`, `lp` and `pb` in a packed byte.
+pub fn encode(properties: &Properties) -> Result<u8, Error> {
+	if properties.lc > 8 || properties.lp > 4 || properties.pb > 4 {
+		return Err(Error::InvalidProperties);
+	}
+
+	Ok((properties.pb * 5 + properties.lp) * 9 + properties.lc)
+}
+
+/// Write the model properties to a stream.
+pub fn write<T: Write>(mut stream: T, properties: &Properties) -> Result<(), Error> {
+	try!(write_model(stream.by_ref(), properties));
+	try!(stream.write_u64::<LittleEndian>(properties.uncompressed.unwrap_or(u64::MAX)));
+
+	Ok(())
+}
+
+/// Write the model properties to a stream, without the uncompressed size.
+pub fn write_model<T: Write>(mut stream: T, properties: &Properties) -> Result<(), Error> {
+	try!(stream.write_u8(try!(encode(properties))));
+	try!(stream.write_u32::<LittleEndian>(properties.dictionary));
+
+	Ok(())
+}
@@ -1,4 +1,4 @@
-use std::io::Read;
+use std::io::{Read, Write};
 
 use {Error};
 use super::{Range, Probabilities};
@@ -31,6 +31,16 @@ impl BitTree {
 		self.bits
 	}
 
+	/// Writes the probabilities to the given stream.
+	pub fn write<W: Write>(&self, stream: W) -> Result<(), Error> {
+		self.probabilities.write(stream)
+	}
+
+	/// Reads the probabilities from the given stream.
+	pub fn read<R: Read>(&mut self, stream: R) -> Result<(), Error> {
+		self.probabilities.read(stream)
+	}
+
 	/// Decodes bits.
 	pub fn decode<T: Read>(&mut self, mut stream: T, range: &mut Range) -> Result<usize, Error> {
 		let mut m = 1usize;
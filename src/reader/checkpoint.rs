@@ -0,0 +1,181 @@
+use std::io::{Read, Write};
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+
+use {Error, Properties};
+use consts::{LENGTH_TO_POSITION_STATES, ALIGN_BITS, END_POSITION_MODEL_INDEX};
+use consts::{FULL_DISTANCES, STATES, POSITION_BITS_MAX};
+use super::{Range, Window, Length, Probabilities, BitTree};
+
+/// The decoder state at a given point of the stream.
+///
+/// It holds a copy of the window, so it takes as much memory as the
+/// dictionary.
+#[derive(Clone, Debug)]
+pub struct Checkpoint {
+	pub(crate) compressed: u64,
+	pub(crate) decoded:    u64,
+
+	pub(crate) range:  Range,
+	pub(crate) window: Window,
+
+	pub(crate) literal:  Probabilities,
+	pub(crate) position: Probabilities,
+
+	pub(crate) length: Length,
+	pub(crate) repeat: Length,
+
+	pub(crate) slot:  Vec<BitTree>,
+	pub(crate) align: BitTree,
+
+	pub(crate) state: u32,
+	pub(crate) rep:   [u32; 4],
+
+	pub(crate) is_match:     Probabilities,
+	pub(crate) is_rep:       Probabilities,
+	pub(crate) is_rep_g0:    Probabilities,
+	pub(crate) is_rep_g1:    Probabilities,
+	pub(crate) is_rep_g2:    Probabilities,
+	pub(crate) is_rep0_long: Probabilities,
+}
+
+impl Checkpoint {
+	/// Returns the offset in the compressed stream.
+	pub fn compressed(&self) -> u64 {
+		self.compressed
+	}
+
+	/// Returns the amount of decoded bytes.
+	pub fn decoded(&self) -> u64 {
+		self.decoded
+	}
+
+	/// Writes the checkpoint to the given stream.
+	pub fn write<W: Write>(&self, mut stream: W) -> Result<(), Error> {
+		try!(stream.write_u64::<LittleEndian>(self.compressed));
+		try!(stream.write_u64::<LittleEndian>(self.decoded));
+
+		try!(self.range.write(stream.by_ref()));
+		try!(self.window.write(stream.by_ref()));
+
+		try!(self.literal.write(stream.by_ref()));
+		try!(self.position.write(stream.by_ref()));
+
+		try!(self.length.write(stream.by_ref()));
+		try!(self.repeat.write(stream.by_ref()));
+
+		for bt in &self.slot {
+			try!(bt.write(stream.by_ref()));
+		}
+
+		try!(self.align.write(stream.by_ref()));
+
+		try!(stream.write_u8(self.state as u8));
+
+		for &rep in &self.rep {
+			try!(stream.write_u32::<LittleEndian>(rep));
+		}
+
+		try!(self.is_match.write(stream.by_ref()));
+		try!(self.is_rep.write(stream.by_ref()));
+		try!(self.is_rep_g0.write(stream.by_ref()));
+		try!(self.is_rep_g1.write(stream.by_ref()));
+		try!(self.is_rep_g2.write(stream.by_ref()));
+		try!(self.is_rep0_long.write(stream.by_ref()));
+
+		Ok(())
+	}
+
+	/// Reads a checkpoint of a stream with the given model properties.
+	pub fn read<R: Read>(mut stream: R, properties: &Properties) -> Result<Checkpoint, Error> {
+		let compressed = try!(stream.read_u64::<LittleEndian>());
+		let decoded    = try!(stream.read_u64::<LittleEndian>());
+
+		let range  = try!(Range::read(stream.by_ref()));
+		let window = try!(Window::read(stream.by_ref(), properties.dictionary));
+
+		let mut literal  = Probabilities::new(0x300 << (properties.lc + properties.lp));
+		let mut position = Probabilities::new(1 + FULL_DISTANCES - END_POSITION_MODEL_INDEX);
+		try!(literal.read(stream.by_ref()));
+		try!(position.read(stream.by_ref()));
+
+		let mut length = Length::new();
+		let mut repeat = Length::new();
+		try!(length.read(stream.by_ref()));
+		try!(repeat.read(stream.by_ref()));
+
+		let mut slot = vec![BitTree::new(6); LENGTH_TO_POSITION_STATES];
+
+		for bt in &mut slot {
+			try!(bt.read(stream.by_ref()));
+		}
+
+		let mut align = BitTree::new(ALIGN_BITS);
+		try!(align.read(stream.by_ref()));
+
+		let state = try!(stream.read_u8()) as u32;
+
+		if state as usize >= STATES {
+			return Err(Error::Corrupted);
+		}
+
+		let mut rep = [0u32; 4];
+
+		for rep in &mut rep {
+			*rep = try!(stream.read_u32::<LittleEndian>());
+		}
+
+		// the distances must point into the restored history, only a fresh
+		// decoder has none and never uses them until a match, and the EOS
+		// marker leaves its distance behind with the range coder finished
+		for (index, &rep) in rep.iter().enumerate() {
+			let valid = (rep < properties.dictionary && (window.check(rep + 1) ||
+				(window.is_empty() && rep == 0 && state < 7))) ||
+				(index == 0 && rep == 0xffffffff && range.is_finished());
+
+			if !valid {
+				return Err(Error::Corrupted);
+			}
+		}
+
+		let mut is_match     = Probabilities::new(STATES << POSITION_BITS_MAX);
+		let mut is_rep       = Probabilities::new(STATES);
+		let mut is_rep_g0    = Probabilities::new(STATES);
+		let mut is_rep_g1    = Probabilities::new(STATES);
+		let mut is_rep_g2    = Probabilities::new(STATES);
+		let mut is_rep0_long = Probabilities::new(STATES << POSITION_BITS_MAX);
+
+		try!(is_match.read(stream.by_ref()));
+		try!(is_rep.read(stream.by_ref()));
+		try!(is_rep_g0.read(stream.by_ref()));
+		try!(is_rep_g1.read(stream.by_ref()));
+		try!(is_rep_g2.read(stream.by_ref()));
+		try!(is_rep0_long.read(stream.by_ref()));
+
+		Ok(Checkpoint {
+			compressed: compressed,
+			decoded:    decoded,
+
+			range:  range,
+			window: window,
+
+			literal:  literal,
+			position: position,
+
+			length: length,
+			repeat: repeat,
+
+			slot:  slot,
+			align: align,
+
+			state: state,
+			rep:   rep,
+
+			is_match:     is_match,
+			is_rep:       is_rep,
+			is_rep_g0:    is_rep_g0,
+			is_rep_g1:    is_rep_g1,
+			is_rep_g2:    is_rep_g2,
+			is_rep0_long: is_rep0_long,
+		})
+	}
+}
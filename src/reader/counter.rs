@@ -0,0 +1,52 @@
+use std::io::{self, Read, Seek, SeekFrom};
+
+/// A reader keeping count of the bytes read.
+#[derive(Debug)]
+pub struct Counter<R: Read> {
+	stream: R,
+	count:  u64,
+}
+
+impl<R: Read> Counter<R> {
+	/// Creates a counter starting from the given offset.
+	pub fn new(stream: R, offset: u64) -> Counter<R> {
+		Counter {
+			stream: stream,
+			count:  offset,
+		}
+	}
+
+	/// Gets the amount of bytes read.
+	pub fn count(&self) -> u64 {
+		self.count
+	}
+
+	/// Returns the inner stream.
+	pub fn get_ref(&self) -> &R {
+		&self.stream
+	}
+
+	/// Unwraps this `Counter`, returning the underlying reader.
+	pub fn into_inner(self) -> R {
+		self.stream
+	}
+}
+
+impl<R: Read> Read for Counter<R> {
+	fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+		let read = try!(self.stream.read(buf));
+		self.count += read as u64;
+
+		Ok(read)
+	}
+}
+
+impl<R: Read + Seek> Seek for Counter<R> {
+	/// Seeks the inner stream, the count becomes the new position so the
+	/// counter must have been created with the position as offset.
+	fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+		self.count = try!(self.stream.seek(pos));
+
+		Ok(self.count)
+	}
+}
@@ -1,7 +1,8 @@
-use std::io::Read;
+use std::io::{Read, Write};
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
 
 use {Error};
-use consts::{PROBABILITY_INITIAL_VALUE, POSITION_BITS_MAX};
+use consts::{PROBABILITY_INITIAL_VALUE, POSITION_BITS_MAX, MODEL_TOTAL_BITS};
 use super::{BitTree, Range};
 
 /// A length decoder.
@@ -43,6 +44,36 @@ impl Length {
 		self.hig.reset();
 	}
 
+	/// Writes the probabilities to the given stream.
+	pub fn write<W: Write>(&self, mut stream: W) -> Result<(), Error> {
+		for &v in &self.choice {
+			try!(stream.write_u16::<LittleEndian>(v));
+		}
+
+		for bt in self.low.iter().chain(self.mid.iter()) {
+			try!(bt.write(stream.by_ref()));
+		}
+
+		self.hig.write(stream)
+	}
+
+	/// Reads the probabilities from the given stream.
+	pub fn read<R: Read>(&mut self, mut stream: R) -> Result<(), Error> {
+		for v in &mut self.choice {
+			*v = try!(stream.read_u16::<LittleEndian>());
+
+			if *v == 0 || *v >= 1 << MODEL_TOTAL_BITS {
+				return Err(Error::Corrupted);
+			}
+		}
+
+		for bt in self.low.iter_mut().chain(self.mid.iter_mut()) {
+			try!(bt.read(stream.by_ref()));
+		}
+
+		self.hig.read(stream)
+	}
+
 	/// Decode a length.
 	pub fn decode<T: Read>(&mut self, mut stream: T, range: &mut Range, state: usize) -> Result<usize, Error> {
 		if !try!(range.probabilistic(stream.by_ref(), &mut self.choice[0])) {
@@ -19,9 +19,24 @@ pub use self::state::State;
 mod cache;
 pub use self::cache::Cache;
 
+mod counter;
+pub use self::counter::Counter;
+
 mod reader;
 pub use self::reader::Reader;
 
+mod packet;
+pub use self::packet::{Packet, Packets};
+
+mod checkpoint;
+pub use self::checkpoint::Checkpoint;
+
+mod snapshot;
+pub use self::snapshot::Snapshot;
+
+mod seekable;
+pub use self::seekable::Seekable;
+
 use std::io::{Read, BufReader};
 use std::fs::File;
 use std::path::Path;
@@ -0,0 +1,151 @@
+use std::io::{self, Read};
+
+use {Error, PacketKind};
+use super::Reader;
+
+/// A decoded LZMA packet.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum Packet {
+	/// A literal byte.
+	Literal(u8),
+
+	/// A match with a new distance, 1 being the last decoded byte.
+	Match {
+		/// The distance of the match.
+		distance: u32,
+
+		/// The length of the match.
+		len: usize,
+	},
+
+	/// A single byte at the last distance.
+	ShortRep,
+
+	/// A match at one of the last four distances.
+	Rep {
+		/// The index of the distance, from 0 for the last one to 3.
+		index: u8,
+
+		/// The length of the match.
+		len: usize,
+	},
+
+	/// The EOS marker.
+	EndMarker,
+}
+
+impl Packet {
+	/// Returns the kind of the packet.
+	pub fn kind(&self) -> PacketKind {
+		match *self {
+			Packet::Literal(..)       => PacketKind::Literal,
+			Packet::Match { .. }      => PacketKind::Match,
+			Packet::ShortRep          => PacketKind::ShortRep,
+			Packet::Rep { index, .. } => PacketKind::Rep(index),
+			Packet::EndMarker         => PacketKind::EndMarker,
+		}
+	}
+
+	/// Returns the amount of bytes the packet produces.
+	pub fn length(&self) -> usize {
+		match *self {
+			Packet::Literal(..)       => 1,
+			Packet::Match { len, .. } => len,
+			Packet::ShortRep          => 1,
+			Packet::Rep { len, .. }   => len,
+			Packet::EndMarker         => 0,
+		}
+	}
+}
+
+/// An iterator over the packets of a stream with their uncompressed offset,
+/// see `Reader::packets`.
+///
+/// It stops after the first error. Packets can't be skipped without decoding
+/// them, every packet depends on the ones before, so `skip` and `nth` cost as
+/// much as decoding, only the bytes of the skipped packets aren't kept.
+#[derive(Debug)]
+pub struct Packets<'a, R: Read + 'a> {
+	reader: &'a mut Reader<R>,
+	bytes:  Vec<u8>,
+	done:   bool,
+}
+
+impl<'a, R: Read> Packets<'a, R> {
+	pub(crate) fn new(reader: &'a mut Reader<R>) -> Packets<'a, R> {
+		Packets {
+			reader: reader,
+			bytes:  Vec::new(),
+			done:   false,
+		}
+	}
+
+	/// Returns the reader.
+	pub fn get_ref(&self) -> &Reader<R> {
+		self.reader
+	}
+
+	/// Returns the bytes produced by the last packet.
+	pub fn bytes(&self) -> &[u8] {
+		&self.bytes
+	}
+}
+
+impl<'a, R: Read> Iterator for Packets<'a, R> {
+	type Item = Result<(u64, Packet), Error>;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		if self.done {
+			return None;
+		}
+
+		let offset = self.reader.decoded();
+		self.bytes.clear();
+
+		match self.reader.decode_packet(&mut self.bytes) {
+			Ok(Some(packet)) => {
+				self.done = packet == Packet::EndMarker;
+				Some(Ok((offset, packet)))
+			}
+
+			Ok(None) => {
+				self.done = true;
+				None
+			}
+
+			Err(err) => {
+				self.done = true;
+				Some(Err(err))
+			}
+		}
+	}
+
+	// the skipped packets are decoded all the same, their bytes are thrown
+	// away and a failure among them is returned instead of being lost
+	fn nth(&mut self, n: usize) -> Option<Self::Item> {
+		self.bytes.clear();
+
+		for _ in 0 .. n {
+			if self.done {
+				return None;
+			}
+
+			match self.reader.decode_packet(io::sink()) {
+				Ok(Some(packet)) =>
+					self.done = packet == Packet::EndMarker,
+
+				Ok(None) => {
+					self.done = true;
+					return None;
+				}
+
+				Err(err) => {
+					self.done = true;
+					return Some(Err(err));
+				}
+			}
+		}
+
+		self.next()
+	}
+}
@@ -1,8 +1,9 @@
 use std::ops::{Deref, DerefMut};
-use std::io::Read;
+use std::io::{Read, Write};
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
 
 use Error;
-use consts::PROBABILITY_INITIAL_VALUE;
+use consts::{PROBABILITY_INITIAL_VALUE, MODEL_TOTAL_BITS};
 use super::Range;
 
 /// Table of probabilities.
@@ -29,6 +30,28 @@ impl Probabilities {
 			*v = PROBABILITY_INITIAL_VALUE;
 		}
 	}
+
+	/// Writes the table to the given stream.
+	pub fn write<W: Write>(&self, mut stream: W) -> Result<(), Error> {
+		for &v in &self.buffer {
+			try!(stream.write_u16::<LittleEndian>(v));
+		}
+
+		Ok(())
+	}
+
+	/// Reads the table from the given stream, the size must match.
+	pub fn read<R: Read>(&mut self, mut stream: R) -> Result<(), Error> {
+		for v in &mut self.buffer {
+			*v = try!(stream.read_u16::<LittleEndian>());
+
+			if *v == 0 || *v >= 1 << MODEL_TOTAL_BITS {
+				return Err(Error::Corrupted);
+			}
+		}
+
+		Ok(())
+	}
 }
 
 impl Deref for Probabilities {
@@ -1,16 +1,19 @@
-use std::io::Read;
-use byteorder::ReadBytesExt;
+use std::io::{Read, Write};
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
 
-use Error;
+use {Error, Cause};
 use consts::{MODEL_TOTAL_BITS, TOP_VALUE, MOVE_BITS};
 
 /// A range decoder.
-#[derive(PartialEq, Eq, Clone, Debug)]
+#[derive(PartialEq, Clone, Debug)]
 pub struct Range {
 	range: u32,
 	code:  u32,
+	count: u64,
+	cost:  Option<f64>,
 
-	seeded: bool,
+	seeded:  bool,
+	failure: Option<Cause>,
 }
 
 impl Range {
@@ -19,8 +22,11 @@ impl Range {
 		Range {
 			range: 0xffffffff,
 			code:  0,
+			count: 0,
+			cost:  None,
 
-			seeded: false,
+			seeded:  false,
+			failure: None,
 		}
 	}
 
@@ -29,16 +35,46 @@ impl Range {
 		Range {
 			range: range,
 			code:  code,
+			count: 0,
+			cost:  None,
 
-			seeded: true,
+			seeded:  true,
+			failure: None,
 		}
 	}
 
+	/// Gets the amount of bytes read from the stream.
+	pub fn count(&self) -> u64 {
+		self.count
+	}
+
+	/// Sets whether the cost in bits of the decoded bits is computed, which
+	/// slows down decoding.
+	pub fn set_cost(&mut self, value: bool) {
+		self.cost = if value { Some(0.0) } else { None };
+	}
+
+	/// Returns the cost in bits of the bits decoded since enabling it.
+	pub fn cost(&self) -> Option<f64> {
+		self.cost
+	}
+
 	/// Checks if the decoder is finished.
 	pub fn is_finished(&self) -> bool {
 		self.code == 0
 	}
 
+	/// Takes the cause of the last failure, for the reader to locate it.
+	pub fn failure(&mut self) -> Option<Cause> {
+		self.failure.take()
+	}
+
+	// keeps the cause of the failure
+	fn fail(&mut self, cause: Cause) -> Error {
+		self.failure = Some(cause);
+		cause.error()
+	}
+
 	/// Checks if the range is seeded.
 	pub fn is_seeded(&self) -> bool {
 		self.seeded
@@ -52,11 +88,16 @@ impl Range {
 			self.code = (self.code << 8) | try!(stream.read_u8()) as u32;
 		}
 
-		if control != 0 || self.code == self.range {
-			return Err(Error::Corrupted)
+		if control != 0 {
+			return Err(self.fail(Cause::FirstByte));
 		}
 
-		self.seeded = true;
+		if self.code == self.range {
+			return Err(self.fail(Cause::CodeEqualsRange));
+		}
+
+		self.seeded  = true;
+		self.count  += 5;
 
 		Ok(())
 	}
@@ -70,10 +111,43 @@ impl Range {
 		self.seeded = false;
 	}
 
+	/// Writes the range to the given stream.
+	pub fn write<W: Write>(&self, mut stream: W) -> Result<(), Error> {
+		try!(stream.write_u32::<LittleEndian>(self.range));
+		try!(stream.write_u32::<LittleEndian>(self.code));
+		try!(stream.write_u64::<LittleEndian>(self.count));
+		try!(stream.write_u8(self.seeded as u8));
+
+		Ok(())
+	}
+
+	/// Reads a range from the given stream.
+	pub fn read<R: Read>(mut stream: R) -> Result<Range, Error> {
+		let range  = try!(stream.read_u32::<LittleEndian>());
+		let code   = try!(stream.read_u32::<LittleEndian>());
+		let count  = try!(stream.read_u64::<LittleEndian>());
+		let seeded = try!(stream.read_u8());
+
+		if seeded > 1 {
+			return Err(Error::Corrupted);
+		}
+
+		Ok(Range {
+			range: range,
+			code:  code,
+			count: count,
+			cost:  None,
+
+			seeded:  seeded == 1,
+			failure: None,
+		})
+	}
+
 	fn normalize<T: Read>(&mut self, mut stream: T) -> Result<(), Error> {
 		if self.range < TOP_VALUE {
 			self.range <<= 8;
 			self.code    = (self.code << 8) | try!(stream.read_u8()) as u32;
+			self.count  += 1;
 		}
 
 		Ok(())
@@ -91,7 +165,7 @@ impl Range {
 			self.code = self.code.wrapping_add(self.range & t);
 
 			if self.code == self.range {
-				return Err(Error::Corrupted);
+				return Err(self.fail(Cause::CodeEqualsRange));
 			}
 
 			try!(self.normalize(stream.by_ref()));
@@ -100,6 +174,11 @@ impl Range {
 			result  += t.wrapping_add(1) as usize;
 		}
 
+		// direct bits have even odds
+		if let Some(ref mut cost) = self.cost {
+			*cost += bits as f64;
+		}
+
 		Ok(result)
 	}
 
@@ -124,6 +203,12 @@ impl Range {
 
 		try!(self.normalize(stream));
 
+		// the cost comes from the probability the bit was decoded with
+		if let Some(ref mut cost) = self.cost {
+			let odds = if bit { (1 << MODEL_TOTAL_BITS) - *prob } else { *prob };
+			*cost -= (odds as f64 / (1 << MODEL_TOTAL_BITS) as f64).log2();
+		}
+
 		*prob = v;
 
 		Ok(bit)
@@ -1,15 +1,21 @@
 use std::io::{self, Write, Read, Cursor};
+use std::cmp;
 
-use {Error, Properties, properties};
+use {Error, Failure, Position, PacketKind, Cause, Properties, properties};
 use consts::{LENGTH_TO_POSITION_STATES, ALIGN_BITS, END_POSITION_MODEL_INDEX};
 use consts::{FULL_DISTANCES, STATES, POSITION_BITS_MAX, MATCH_MINIMUM_LENGTH};
-use super::{Range, Window, Length, Probabilities, BitTree, State, Cache};
+use super::{Range, Window, Length, Probabilities, BitTree, State, Cache, Checkpoint, Snapshot, Packet, Packets};
 
 /// A LZMA stream reader.
 #[derive(Debug)]
 pub struct Reader<R: Read> {
 	stream:  R,
+	base:    u64,
 	decoded: u64,
+	kind:    PacketKind,
+	marker:  bool,
+	failure: Option<Failure>,
+	cause:   Option<Cause>,
 
 	properties: Properties,
 
@@ -45,13 +51,18 @@ impl<R: Read> Reader<R> {
 	/// Creates a LZMA reader with the given model properties and the given
 	/// stream.
 	pub fn new(stream: R, properties: Properties) -> Result<Reader<R>, Error> {
-		let window = Window::new(properties.dictionary);
+		let window = Window::new(properties.window());
 
 		let literal = Probabilities::new(0x300 << (properties.lc + properties.lp));
 
 		Ok(Reader {
 			stream:  stream,
+			base:    0,
 			decoded: 0,
+			kind:    PacketKind::Unknown,
+			marker:  false,
+			failure: None,
+			cause:   None,
 
 			properties: properties,
 
@@ -82,12 +93,92 @@ impl<R: Read> Reader<R> {
 		})
 	}
 
+	/// Creates a LZMA reader like `new`, failing before allocating anything
+	/// if decoding needs more memory than the limit.
+	pub fn with_memlimit(stream: R, properties: Properties, memlimit: u64) -> Result<Reader<R>, Error> {
+		let needed = properties.decoder_memory_usage();
+
+		if needed > memlimit {
+			return Err(Error::MemoryLimit { needed: needed, limit: memlimit });
+		}
+
+		Reader::new(stream, properties)
+	}
+
+	/// Creates a LZMA reader like `new`, preloading the window with a preset
+	/// dictionary.
+	///
+	/// The stream must have been encoded with the same preset dictionary.
+	pub fn with_preset(stream: R, properties: Properties, dictionary: &[u8]) -> Result<Reader<R>, Error> {
+		Reader::with_preset_from(stream, properties, dictionary, dictionary.len() as u64)
+	}
+
+	/// Creates a LZMA reader like `with_preset`, reading the `size` bytes of
+	/// the preset dictionary from another stream.
+	pub fn with_preset_from<T: Read>(stream: R, properties: Properties, mut dictionary: T, size: u64) -> Result<Reader<R>, Error> {
+		let mut reader = try!(Reader::new(stream, properties));
+
+		// the preset takes room in the window like decoded data would
+		reader.window = Window::new(Properties {
+			uncompressed: properties.uncompressed.map(|uncompressed| uncompressed.saturating_add(size)),
+			.. properties
+		}.window());
+
+		let mut buffer = [0u8; 4096];
+		let mut left   = size;
+
+		while left > 0 {
+			let length = cmp::min(left, buffer.len() as u64) as usize;
+			try!(dictionary.read_exact(&mut buffer[.. length]));
+
+			for &byte in &buffer[.. length] {
+				try!(reader.window.push(io::sink(), byte));
+			}
+
+			left -= length as u64;
+		}
+
+		Ok(reader)
+	}
+
+	/// Creates a LZMA reader resuming from a snapshot taken with `snapshot`.
+	///
+	/// The stream must be positioned right after the bytes read before the
+	/// snapshot, see `Snapshot::compressed`.
+	pub fn restore(stream: R, snapshot: &Snapshot) -> Result<Reader<R>, Error> {
+		Reader::restore_with_memlimit(stream, snapshot, u64::MAX)
+	}
+
+	/// Creates a LZMA reader like `restore`, with a memory limit.
+	pub fn restore_with_memlimit(stream: R, snapshot: &Snapshot, memlimit: u64) -> Result<Reader<R>, Error> {
+		let mut reader = try!(Reader::with_memlimit(stream, snapshot.properties, memlimit));
+
+		unsafe {
+			reader.resume(&snapshot.checkpoint);
+		}
+
+		reader.base = snapshot.base;
+
+		if !snapshot.leftover.is_empty() {
+			reader.buffer = Some(snapshot.leftover.clone());
+		}
+
+		Ok(reader)
+	}
+
 	/// Creates a LZMA stream from the given stream, reading the model
 	/// properties.
-	pub fn from(mut stream: R) -> Result<Reader<R>, Error> {
-		let properties = try!(properties::read(stream.by_ref()));
+	pub fn from(stream: R) -> Result<Reader<R>, Error> {
+		Reader::from_with_memlimit(stream, u64::MAX)
+	}
 
-		Reader::new(stream, properties)
+	/// Creates a LZMA stream like `from`, with a memory limit.
+	pub fn from_with_memlimit(mut stream: R, memlimit: u64) -> Result<Reader<R>, Error> {
+		let     properties = try!(properties::read(stream.by_ref()));
+		let mut reader     = try!(Reader::with_memlimit(stream, properties, memlimit));
+		reader.base = properties::HEADER_SIZE;
+
+		Ok(reader)
 	}
 
 	/// Returns the model properties.
@@ -112,6 +203,11 @@ impl<R: Read> Reader<R> {
 		self.stream
 	}
 
+	/// Returns the inner stream.
+	pub fn get_ref(&self) -> &R {
+		&self.stream
+	}
+
 	/// Returns the inner stream mutably.
 	///
 	/// Note that reading bytes from the raw stream might corrupt the decoding.
@@ -119,6 +215,12 @@ impl<R: Read> Reader<R> {
 		&mut self.stream
 	}
 
+	/// Sets the amount of bytes in the stream that aren't range coded data,
+	/// which is added to the compressed offset of errors.
+	pub fn set_base(&mut self, value: u64) {
+		self.base = value;
+	}
+
 	/// Sets the uncompressed size.
 	///
 	/// Note that changing the uncompressed size might corrupt the decoding.
@@ -126,6 +228,12 @@ impl<R: Read> Reader<R> {
 		self.properties.uncompressed = value;
 	}
 
+	/// Sets whether the EOS marker must follow the data when the uncompressed
+	/// size is known, otherwise decoding stops at the size.
+	pub fn set_marker(&mut self, value: bool) {
+		self.marker = value;
+	}
+
 	/// Resets the decoder.
 	///
 	/// Note that resetting might corrupt the decoding.
@@ -166,6 +274,177 @@ impl<R: Read> Reader<R> {
 		}
 	}
 
+	/// Resets the probabilities, the state and the range decoder, keeping the
+	/// dictionary.
+	///
+	/// Note that resetting might corrupt the decoding.
+	pub unsafe fn reset_state(&mut self) {
+		self.reset_range();
+
+		self.literal.reset();
+		self.position.reset();
+
+		self.length.reset();
+		self.repeat.reset();
+
+		for bt in &mut self.slot {
+			bt.reset();
+		}
+
+		self.align.reset();
+
+		self.state = 0;
+		self.rep   = [0; 4];
+
+		self.is_match.reset();
+		self.is_rep.reset();
+		self.is_rep_g0.reset();
+		self.is_rep_g1.reset();
+		self.is_rep_g2.reset();
+		self.is_rep0_long.reset();
+	}
+
+	/// Resets the range decoder, requiring a new seed.
+	///
+	/// Note that resetting might corrupt the decoding.
+	pub unsafe fn reset_range(&mut self) {
+		self.range.reset();
+	}
+
+	/// Resets the dictionary.
+	///
+	/// Note that resetting might corrupt the decoding.
+	pub unsafe fn reset_dictionary(&mut self) {
+		self.window.reset();
+	}
+
+	/// Pushes uncompressed data to the dictionary and the given writer.
+	///
+	/// Note that pushing data might corrupt the decoding.
+	pub unsafe fn push<W: Write>(&mut self, mut writer: W, data: &[u8]) -> Result<(), Error> {
+		for &byte in data {
+			try!(self.window.push(writer.by_ref(), byte));
+		}
+
+		self.decoded += data.len() as u64;
+
+		Ok(())
+	}
+
+	/// Returns the amount of decoded bytes.
+	pub fn decoded(&self) -> u64 {
+		self.decoded
+	}
+
+	/// Returns where and why decoding failed, once it has.
+	///
+	/// The errors stay the ones of `Error`, this tells the offsets and the
+	/// packet they were found at.
+	pub fn failure(&self) -> Option<Failure> {
+		self.failure
+	}
+
+	/// Sets whether the cost in bits of the decoded data is computed, which
+	/// slows down decoding.
+	pub fn set_cost(&mut self, value: bool) {
+		self.range.set_cost(value);
+	}
+
+	/// Returns the cost in bits of the data decoded since enabling it.
+	pub fn cost(&self) -> Option<f64> {
+		self.range.cost()
+	}
+
+	/// Checks if the range decoder has been left in a clean state.
+	pub fn is_range_finished(&self) -> bool {
+		self.range.is_finished()
+	}
+
+	/// Captures the decoder state, `compressed` being the offset of the next
+	/// byte in the compressed stream.
+	///
+	/// Note that any leftover data in the internal buffer is not captured.
+	pub fn checkpoint(&self, compressed: u64) -> Checkpoint {
+		Checkpoint {
+			compressed: compressed,
+			decoded:    self.decoded,
+
+			range:  self.range.clone(),
+			window: self.window.clone(),
+
+			literal:  self.literal.clone(),
+			position: self.position.clone(),
+
+			length: self.length.clone(),
+			repeat: self.repeat.clone(),
+
+			slot:  self.slot.clone(),
+			align: self.align.clone(),
+
+			state: self.state,
+			rep:   self.rep,
+
+			is_match:     self.is_match.clone(),
+			is_rep:       self.is_rep.clone(),
+			is_rep_g0:    self.is_rep_g0.clone(),
+			is_rep_g1:    self.is_rep_g1.clone(),
+			is_rep_g2:    self.is_rep_g2.clone(),
+			is_rep0_long: self.is_rep0_long.clone(),
+		}
+	}
+
+	/// Captures the full decoder state, including any leftover data, so
+	/// decoding can be resumed later with `restore`.
+	pub fn snapshot(&self) -> Snapshot {
+		let leftover = match self.buffer.as_ref() {
+			Some(buffer) =>
+				buffer[self.offset ..].to_vec(),
+
+			None =>
+				Vec::new()
+		};
+
+		Snapshot {
+			properties: self.properties,
+			base:       self.base,
+			leftover:   leftover,
+			checkpoint: self.checkpoint(self.range.count()),
+		}
+	}
+
+	/// Resumes decoding from the given checkpoint, the stream must be
+	/// positioned at the checkpoint compressed offset.
+	///
+	/// Note that resuming from a checkpoint of another stream might corrupt the
+	/// decoding.
+	pub unsafe fn resume(&mut self, checkpoint: &Checkpoint) {
+		self.buffer  = None;
+		self.offset  = 0;
+		self.decoded = checkpoint.decoded;
+
+		self.range  = checkpoint.range.clone();
+		self.window = checkpoint.window.clone();
+
+		self.literal  = checkpoint.literal.clone();
+		self.position = checkpoint.position.clone();
+
+		self.length = checkpoint.length.clone();
+		self.repeat = checkpoint.repeat.clone();
+
+		self.slot  = checkpoint.slot.clone();
+		self.align = checkpoint.align.clone();
+
+		self.state = checkpoint.state;
+		self.rep   = checkpoint.rep;
+
+		self.is_match     = checkpoint.is_match.clone();
+		self.is_rep       = checkpoint.is_rep.clone();
+		self.is_rep_g0    = checkpoint.is_rep_g0.clone();
+		self.is_rep_g1    = checkpoint.is_rep_g1.clone();
+		self.is_rep_g2    = checkpoint.is_rep_g2.clone();
+		self.is_rep0_long = checkpoint.is_rep0_long.clone();
+	}
+
 	fn distance(&mut self, length: usize) -> Result<usize, Error> {
 		let state = if length > LENGTH_TO_POSITION_STATES - 1 {
 			LENGTH_TO_POSITION_STATES - 1
@@ -195,7 +474,7 @@ impl<R: Read> Reader<R> {
 		Ok(distance as usize)
 	}
 
-	fn literal<W: Write>(&mut self, writer: W, state: usize, rep0: u32) -> Result<(), Error> {
+	fn literal<W: Write>(&mut self, writer: W, state: usize, rep0: u32) -> Result<u8, Error> {
 		let prev = if !self.window.is_empty() {
 			self.window[1] as u32
 		}
@@ -238,85 +517,170 @@ impl<R: Read> Reader<R> {
 			byte  |= if bit { 1 } else { 0 };
 		}
 
-		self.window.push(writer, byte as u8)
+		try!(self.window.push(writer, byte as u8));
+
+		Ok(byte as u8)
+	}
+
+	/// Returns an iterator over the packets of the stream, the bytes they
+	/// produce are available from the iterator.
+	///
+	/// Skipping packets still decodes them, so it costs as much as decoding.
+	///
+	/// Fails with `Error::InvalidState` if the internal cache holds bytes
+	/// that haven't been read yet.
+	pub fn packets(&mut self) -> Result<Packets<'_, R>, Error> {
+		if self.cached() != 0 {
+			return Err(Error::InvalidState);
+		}
+
+		Ok(Packets::new(self))
 	}
 
 	/// Decode one unit and return the decoded amount.
 	///
 	/// Note the writer should not do partial writes, or some of the decoded data
 	/// will be lost.
-	pub fn decode<W: Write>(&mut self, mut writer: W) -> Result<usize, Error> {
+	pub fn decode<W: Write>(&mut self, writer: W) -> Result<usize, Error> {
+		Ok(try!(self.decode_packet(writer)).map_or(0, |packet| packet.length()))
+	}
+
+	/// Decode one packet and return it, `None` once a stream without EOS marker
+	/// has been decoded entirely.
+	///
+	/// Note the writer should not do partial writes, or some of the decoded data
+	/// will be lost.
+	pub fn decode_packet<W: Write>(&mut self, writer: W) -> Result<Option<Packet>, Error> {
+		self.kind = PacketKind::Unknown;
+
+		// a code of zero is valid in the middle of a stream, it only tells the
+		// marker is missing when the stream is also over
+		let finished = (self.properties.uncompressed.is_none() || (self.marker && self.properties.uncompressed == Some(self.decoded))) &&
+			self.range.is_seeded() && self.range.is_finished();
+
+		let result = self.unit(writer);
+
+		let cause = match result {
+			Err(Error::IO(ref err)) if err.kind() == io::ErrorKind::UnexpectedEof && finished =>
+				return Err(Error::MissingMarker),
+
+			Err(Error::IO(ref err)) if err.kind() == io::ErrorKind::UnexpectedEof =>
+				Cause::Truncated,
+
+			Err(Error::IO(_)) | Ok(_) =>
+				return result,
+
+			Err(_) => match self.cause.take().or_else(|| self.range.failure()) {
+				Some(cause) =>
+					cause,
+
+				None =>
+					return result
+			}
+		};
+
+		self.failure = Some(Failure {
+			position: Position {
+				compressed:   self.base + self.range.count(),
+				uncompressed: self.decoded,
+				packet:       self.kind,
+			},
+
+			cause: cause,
+		});
+
+		result
+	}
+
+	// keeps the cause of the failure for `failure`
+	fn fail(&mut self, cause: Cause) -> Error {
+		self.cause = Some(cause);
+		cause.error()
+	}
+
+	fn unit<W: Write>(&mut self, mut writer: W) -> Result<Option<Packet>, Error> {
 		if !self.range.is_seeded() {
 			try!(self.range.seed(self.stream.by_ref()));
 		}
 
 		if let Some(size) = self.properties.uncompressed {
-			if self.decoded == size {
-				return Ok(0);
+			// a required marker still has to be decoded after the data
+			if self.decoded == size && (!self.marker || self.rep[0] == 0xffffffff) {
+				return Ok(None);
 			}
 		}
-		else {
-			if self.range.is_finished() {
-				return Err(Error::MissingMarker);
-			}
+		else if self.rep[0] == 0xffffffff {
+			// the EOS marker has already been decoded, reading on stays at the end
+			return Ok(None);
 		}
 
 		let pos = self.window.total() & ((1 << self.properties.pb) - 1);
 
-		if !try!(self.range.probabilistic(self.stream.by_ref(), &mut self.is_match[((pos << POSITION_BITS_MAX) + self.state) as usize])) {
+		if !try!(self.range.probabilistic(self.stream.by_ref(), &mut self.is_match[((self.state << POSITION_BITS_MAX) + pos) as usize])) {
+			self.kind = PacketKind::Literal;
+
 			// check if there's more data to read
 			if let Some(size) = self.properties.uncompressed {
 				if self.decoded == size {
-					return Err(Error::HasMoreData);
+					return Err(self.fail(Cause::HasMoreData));
 				}
 			}
 
 			let rep   = self.rep[0];
 			let state = self.state;
-			try!(self.literal(writer.by_ref(), state as usize, rep));
+			let byte  = try!(self.literal(writer.by_ref(), state as usize, rep));
 
 			self.state    = State::Literal(self.state).update();
 			self.decoded += 1;
 
-			return Ok(1);
+			return Ok(Some(Packet::Literal(byte)));
 		}
 
 		let mut length;
 
 		if try!(self.range.probabilistic(self.stream.by_ref(), &mut self.is_rep[self.state as usize])) {
+			self.kind = PacketKind::Rep(0);
+
 			// check if there's more data to read
 			if let Some(size) = self.properties.uncompressed {
 				if self.decoded == size {
-					return Err(Error::HasMoreData);
+					return Err(self.fail(Cause::HasMoreData));
 				}
 			}
 
+			// reported as data after the end, like it always was
 			if self.window.is_empty() {
+				self.fail(Cause::DistanceBeyondHistory);
 				return Err(Error::HasMoreData);
 			}
 
 			if !try!(self.range.probabilistic(self.stream.by_ref(), &mut self.is_rep_g0[self.state as usize])) {
 				if !try!(self.range.probabilistic(self.stream.by_ref(), &mut self.is_rep0_long[((self.state << POSITION_BITS_MAX) + pos) as usize])) {
+					self.kind = PacketKind::ShortRep;
+
 					let byte = self.window[self.rep[0] + 1];
 					try!(self.window.push(writer.by_ref(), byte));
 
 					self.state    = State::ShortRepetition(self.state).update();
 					self.decoded += 1;
 
-					return Ok(1);
+					return Ok(Some(Packet::ShortRep));
 				}
 			}
 			else {
 				let distance;
 				
 				if !try!(self.range.probabilistic(self.stream.by_ref(), &mut self.is_rep_g1[self.state as usize])) {
-					distance = self.rep[1];
+					self.kind = PacketKind::Rep(1);
+					distance  = self.rep[1];
 				}
 				else {
 					if !try!(self.range.probabilistic(self.stream.by_ref(), &mut self.is_rep_g2[self.state as usize])) {
-						distance = self.rep[2];
+						self.kind = PacketKind::Rep(2);
+						distance  = self.rep[2];
 					}
 					else {
+						self.kind   = PacketKind::Rep(3);
 						distance    = self.rep[3];
 						self.rep[3] = self.rep[2];
 					}
@@ -333,6 +697,8 @@ impl<R: Read> Reader<R> {
 			self.state = State::Repetition(self.state).update();
 		}
 		else {
+			self.kind = PacketKind::Match;
+
 			length = try!(self.length.decode(self.stream.by_ref(), &mut self.range, pos as usize));
 
 			self.rep[3] = self.rep[2];
@@ -342,25 +708,31 @@ impl<R: Read> Reader<R> {
 
 			// EOS marker found
 			if self.rep[0] == 0xffffffff {
+				self.kind = PacketKind::EndMarker;
+
 				// if the range finished correctly
 				if self.range.is_finished() {
 					// return error if EOS when the uncompressed size is defined
 					if let Some(size) = self.properties.uncompressed {
 						if self.decoded != size {
-							return Err(Error::NeedMoreData);
+							return Err(self.fail(Cause::NeedMoreData));
 						}
 					}
 
 					// return EOF
-					return Ok(0);
+					return Ok(Some(Packet::EndMarker));
 				}
 				else {
-					return Err(Error::NeedMoreData);
+					return Err(self.fail(Cause::UnfinishedRange));
 				}
 			}
 
-			if self.rep[0] >= self.properties.dictionary || !self.window.check(self.rep[0]) {
-				return Err(Error::Corrupted);
+			if self.rep[0] >= self.properties.dictionary {
+				return Err(self.fail(Cause::DistanceBeyondDictionary));
+			}
+
+			if !self.window.check(self.rep[0] + 1) {
+				return Err(self.fail(Cause::DistanceBeyondHistory));
 			}
 
 			self.state = State::Match(self.state).update();
@@ -368,16 +740,24 @@ impl<R: Read> Reader<R> {
 
 		length += MATCH_MINIMUM_LENGTH;
 
+		let packet = match self.kind {
+			PacketKind::Rep(index) =>
+				Packet::Rep { index: index, len: length },
+
+			_ =>
+				Packet::Match { distance: self.rep[0] + 1, len: length },
+		};
+
 		if let Some(size) = self.properties.uncompressed {
 			if self.decoded + length as u64 > size {
-				return Err(Error::HasMoreData);
+				return Err(self.fail(Cause::HasMoreData));
 			}
 		}
 
 		try!(self.window.copy(writer.by_ref(), self.rep[0] + 1, length));
 		self.decoded += length as u64;
 
-		Ok(length)
+		Ok(Some(packet))
 	}
 }
 
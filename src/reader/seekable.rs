@@ -0,0 +1,247 @@
+use std::io::{self, Read, Write, Seek, SeekFrom};
+use std::cmp;
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+
+use {Error, properties};
+use super::{Reader, Counter, Checkpoint};
+
+/// The magic bytes at the start of a checkpoint index.
+pub const INDEX_MAGIC: [u8; 4] = [b'L', b'Z', b'C', b'K'];
+
+/// The version of the checkpoint index format.
+pub const INDEX_VERSION: u8 = 1;
+
+/// A seekable LZMA stream reader.
+///
+/// The decoder state is saved every `interval` decoded bytes, so seeking only
+/// decodes again from the nearest checkpoint before the target.
+#[derive(Debug)]
+pub struct Seekable<R: Read + Seek> {
+	inner:       Reader<Counter<R>>,
+	interval:    u64,
+	checkpoints: Vec<Checkpoint>,
+	size:        Option<u64>,
+}
+
+impl<R: Read + Seek> Seekable<R> {
+	/// Creates a seekable reader from the given stream, reading the model
+	/// properties at the current position.
+	///
+	/// Every checkpoint keeps a copy of the window, so the interval should be
+	/// a few times the dictionary size.
+	pub fn new(stream: R, interval: u64) -> Result<Seekable<R>, Error> {
+		Seekable::new_with_memlimit(stream, interval, u64::MAX)
+	}
+
+	/// Creates a seekable reader like `new`, with a memory limit for the
+	/// decoder, the checkpoints come on top of it.
+	pub fn new_with_memlimit(mut stream: R, interval: u64, memlimit: u64) -> Result<Seekable<R>, Error> {
+		let start = try!(stream.stream_position());
+		let inner = try!(Reader::from_with_memlimit(Counter::new(stream, start), memlimit));
+		let first = inner.checkpoint(inner.get_ref().count());
+
+		Ok(Seekable {
+			size:        inner.properties().uncompressed,
+			inner:       inner,
+			interval:    cmp::max(interval, 1),
+			checkpoints: vec![first],
+		})
+	}
+
+	/// Returns the wrapped reader.
+	pub fn get_ref(&self) -> &Reader<Counter<R>> {
+		&self.inner
+	}
+
+	/// Returns the checkpoint interval.
+	pub fn interval(&self) -> u64 {
+		self.interval
+	}
+
+	/// Returns the checkpoints recorded so far.
+	pub fn checkpoints(&self) -> &[Checkpoint] {
+		&self.checkpoints
+	}
+
+	/// Returns the current position in the decoded data.
+	pub fn position(&self) -> u64 {
+		self.inner.decoded() - self.inner.cached() as u64
+	}
+
+	/// Unwraps this `Seekable`, returning the underlying reader.
+	pub fn into_inner(self) -> R {
+		self.inner.into_inner().into_inner()
+	}
+
+	/// Writes the checkpoints to the given stream, so they can be loaded again
+	/// instead of decoding the stream from the start.
+	pub fn save_index<W: Write>(&self, mut stream: W) -> Result<(), Error> {
+		try!(stream.write_all(&INDEX_MAGIC));
+		try!(stream.write_u8(INDEX_VERSION));
+		try!(properties::write_model(stream.by_ref(), self.inner.properties()));
+		try!(stream.write_u64::<LittleEndian>(self.interval));
+		try!(stream.write_u64::<LittleEndian>(self.checkpoints.len() as u64));
+
+		for checkpoint in &self.checkpoints {
+			try!(checkpoint.write(stream.by_ref()));
+		}
+
+		Ok(())
+	}
+
+	/// Loads the checkpoints saved by `save_index`, replacing the current
+	/// ones.
+	///
+	/// The index must have been saved for the same stream.
+	pub fn load_index<T: Read>(&mut self, mut stream: T) -> Result<(), Error> {
+		let mut magic = [0u8; 4];
+		try!(stream.read_exact(&mut magic));
+
+		if magic != INDEX_MAGIC {
+			return Err(Error::InvalidHeader);
+		}
+
+		if try!(stream.read_u8()) != INDEX_VERSION {
+			return Err(Error::Unsupported);
+		}
+
+		let model = try!(properties::read_model(stream.by_ref()));
+
+		if model.lc != self.inner.properties().lc || model.lp != self.inner.properties().lp ||
+		   model.pb != self.inner.properties().pb || model.dictionary != self.inner.properties().dictionary
+		{
+			return Err(Error::InvalidHeader);
+		}
+
+		let     interval    = try!(stream.read_u64::<LittleEndian>());
+		let     count       = try!(stream.read_u64::<LittleEndian>());
+		let mut checkpoints = Vec::new();
+
+		for _ in 0 .. count {
+			let checkpoint = try!(Checkpoint::read(stream.by_ref(), self.inner.properties()));
+
+			// they have to be in stream order
+			if let Some(last) = checkpoints.last() {
+				if !is_after(&checkpoint, last) {
+					return Err(Error::Corrupted);
+				}
+			}
+
+			checkpoints.push(checkpoint);
+		}
+
+		// seeking needs a checkpoint before any position
+		if checkpoints.first().map(|c| c.decoded) != Some(0) {
+			return Err(Error::Corrupted);
+		}
+
+		self.interval    = cmp::max(interval, 1);
+		self.checkpoints = checkpoints;
+
+		Ok(())
+	}
+
+	// records a checkpoint if the decoder went far enough since the last one
+	fn record(&mut self) {
+		if self.inner.cached() != 0 {
+			return;
+		}
+
+		let next = self.checkpoints.last().unwrap().decoded + self.interval;
+
+		if self.inner.decoded() >= next {
+			let compressed = self.inner.get_ref().count();
+			self.checkpoints.push(self.inner.checkpoint(compressed));
+		}
+	}
+
+	// moves to the given position, or to the end if it's beyond it
+	fn seek_to(&mut self, target: u64) -> Result<u64, Error> {
+		let position = self.position();
+		let nearest  = match self.checkpoints.binary_search_by_key(&target, |c| c.decoded) {
+			Ok(index)  => index,
+			Err(index) => index - 1,
+		};
+
+		// decoding forward is cheaper when the checkpoint is behind us
+		if position > target || position < self.checkpoints[nearest].decoded {
+			let checkpoint = &self.checkpoints[nearest];
+
+			unsafe {
+				try!(self.inner.inner().seek(SeekFrom::Start(checkpoint.compressed)));
+				self.inner.resume(checkpoint);
+			}
+		}
+
+		let mut buffer = [0u8; 4096];
+
+		while self.position() < target {
+			let length = cmp::min(target - self.position(), buffer.len() as u64) as usize;
+
+			if try!(self.read(&mut buffer[.. length])) == 0 {
+				self.size = Some(self.position());
+				break;
+			}
+		}
+
+		Ok(self.position())
+	}
+}
+
+impl<R: Read + Seek> Read for Seekable<R> {
+	fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+		let read = try!(self.inner.read(buf));
+		self.record();
+
+		Ok(read)
+	}
+}
+
+impl<R: Read + Seek> Seek for Seekable<R> {
+	/// Seeks in the decoded data, seeking beyond the end stops at the end.
+	///
+	/// Seeking from the end of a stream of unknown size needs decoding it
+	/// entirely the first time.
+	fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+		let target = match pos {
+			SeekFrom::Start(offset) =>
+				Some(offset),
+
+			SeekFrom::Current(offset) =>
+				relative(self.position(), offset),
+
+			SeekFrom::End(offset) => {
+				let size = match self.size {
+					Some(size) =>
+						size,
+
+					None =>
+						try!(self.seek_to(u64::MAX).map_err(Error::into_io))
+				};
+
+				relative(size, offset)
+			}
+		};
+
+		match target {
+			Some(target) =>
+				self.seek_to(target).map_err(Error::into_io),
+
+			None =>
+				Err(io::Error::new(io::ErrorKind::InvalidInput, "invalid seek to a negative position"))
+		}
+	}
+}
+
+fn relative(base: u64, offset: i64) -> Option<u64> {
+	if offset < 0 {
+		base.checked_sub(offset.wrapping_neg() as u64)
+	}
+	else {
+		base.checked_add(offset as u64)
+	}
+}
+
+fn is_after(checkpoint: &Checkpoint, previous: &Checkpoint) -> bool {
+	checkpoint.decoded > previous.decoded && checkpoint.compressed >= previous.compressed
+}
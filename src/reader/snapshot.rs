@@ -0,0 +1,90 @@
+use std::io::{Read, Write};
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+
+use {Error, Properties, properties};
+use super::Checkpoint;
+
+/// The magic bytes at the start of a snapshot.
+pub const SNAPSHOT_MAGIC: [u8; 4] = [b'L', b'Z', b'S', b'N'];
+
+/// The version of the snapshot format.
+pub const SNAPSHOT_VERSION: u8 = 1;
+
+/// The full state of a `Reader`, see `Reader::snapshot`.
+#[derive(Clone, Debug)]
+pub struct Snapshot {
+	pub(crate) properties: Properties,
+	pub(crate) base:       u64,
+	pub(crate) leftover:   Vec<u8>,
+	pub(crate) checkpoint: Checkpoint,
+}
+
+impl Snapshot {
+	/// Returns the model properties.
+	pub fn properties(&self) -> &Properties {
+		&self.properties
+	}
+
+	/// Returns the amount of bytes the decoder read from the stream, headers
+	/// included, the stream has to be positioned right after them to resume
+	/// decoding.
+	pub fn compressed(&self) -> u64 {
+		self.base + self.checkpoint.compressed
+	}
+
+	/// Returns the amount of bytes returned by the reader.
+	pub fn decoded(&self) -> u64 {
+		self.checkpoint.decoded - self.leftover.len() as u64
+	}
+
+	/// Writes the snapshot to the given stream.
+	pub fn write<W: Write>(&self, mut stream: W) -> Result<(), Error> {
+		try!(stream.write_all(&SNAPSHOT_MAGIC));
+		try!(stream.write_u8(SNAPSHOT_VERSION));
+		try!(properties::write(stream.by_ref(), &self.properties));
+		try!(stream.write_u64::<LittleEndian>(self.base));
+
+		try!(stream.write_u32::<LittleEndian>(self.leftover.len() as u32));
+		try!(stream.write_all(&self.leftover));
+
+		self.checkpoint.write(stream)
+	}
+
+	/// Reads a snapshot written by `write`.
+	pub fn read<R: Read>(mut stream: R) -> Result<Snapshot, Error> {
+		let mut magic = [0u8; 4];
+		try!(stream.read_exact(&mut magic));
+
+		if magic != SNAPSHOT_MAGIC {
+			return Err(Error::InvalidHeader);
+		}
+
+		if try!(stream.read_u8()) != SNAPSHOT_VERSION {
+			return Err(Error::Unsupported);
+		}
+
+		let properties = try!(properties::read(stream.by_ref()));
+		let base       = try!(stream.read_u64::<LittleEndian>());
+		let length     = try!(stream.read_u32::<LittleEndian>());
+
+		let mut leftover = Vec::new();
+		try!(stream.by_ref().take(length as u64).read_to_end(&mut leftover));
+
+		if leftover.len() != length as usize {
+			return Err(Error::NeedMoreData);
+		}
+
+		let checkpoint = try!(Checkpoint::read(stream, &properties));
+
+		if (leftover.len() as u64) > checkpoint.decoded {
+			return Err(Error::Corrupted);
+		}
+
+		Ok(Snapshot {
+			properties: properties,
+			base:       base,
+			leftover:   leftover,
+			checkpoint: checkpoint,
+		})
+	}
+}
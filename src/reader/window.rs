@@ -1,11 +1,15 @@
 use std::ops::{Deref, Index};
-use std::io::Write;
-use byteorder::WriteBytesExt;
+use std::io::{Read, Write};
+use std::cmp;
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
 
 use {Error};
 
+/// The buffer never grows by less than this.
+const MINIMUM_GROWTH: usize = 1 << 12;
+
 /// A sliding window implementation.
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct Window {
 	buffer: Vec<u8>,
 	size:   u32,
@@ -17,10 +21,11 @@ pub struct Window {
 }
 
 impl Window {
-	/// Creates a sliding window with the given size.
+	/// Creates a sliding window with the given size, the buffer grows
+	/// gradually up to it.
 	pub fn new(size: u32) -> Self {
 		Window {
-			buffer: Vec::with_capacity(size as usize),
+			buffer: Vec::new(),
 			size:   size,
 
 			position: 0,
@@ -40,7 +45,8 @@ impl Window {
 		self.position
 	}
 
-	/// Gets the total position.
+	/// Gets the total position, wrapping around after 4 GiB, only its low
+	/// bits are used for the literal and position states.
 	pub fn total(&self) -> u32 {
 		self.total
 	}
@@ -57,6 +63,8 @@ impl Window {
 
 	#[doc(hidden)]
 	pub unsafe fn reset(&mut self) {
+		self.buffer.clear();
+
 		self.position = 0;
 		self.total    = 0;
 		self.full     = false;
@@ -66,10 +74,20 @@ impl Window {
 	pub fn push<W: Write>(&mut self, mut stream: W, byte: u8) -> Result<(), Error> {
 		try!(stream.write_u8(byte));
 
-		self.buffer.insert(self.position as usize, byte);
+		if self.is_full() {
+			self.buffer[self.position as usize] = byte;
+		}
+		else {
+			if self.buffer.len() == self.buffer.capacity() {
+				let length = self.buffer.len();
+				self.buffer.reserve_exact(cmp::min(cmp::max(length, MINIMUM_GROWTH), self.size as usize - length));
+			}
+
+			self.buffer.push(byte);
+		}
 
 		self.position += 1;
-		self.total    += 1;
+		self.total     = self.total.wrapping_add(1);
 
 		if self.position() == self.size() {
 			self.position = 0;
@@ -89,9 +107,59 @@ impl Window {
 		Ok(())
 	}
 
+	/// Writes the window and its contents to the given stream.
+	pub fn write<W: Write>(&self, mut stream: W) -> Result<(), Error> {
+		try!(stream.write_u32::<LittleEndian>(self.size));
+		try!(stream.write_u32::<LittleEndian>(self.position));
+		try!(stream.write_u32::<LittleEndian>(self.total));
+		try!(stream.write_u8(self.full as u8));
+		try!(stream.write_u32::<LittleEndian>(self.buffer.len() as u32));
+		try!(stream.write_all(&self.buffer));
+
+		Ok(())
+	}
+
+	/// Reads a window written by `write`, failing if it's bigger than the
+	/// given size.
+	pub fn read<R: Read>(mut stream: R, maximum: u32) -> Result<Window, Error> {
+		let size     = try!(stream.read_u32::<LittleEndian>());
+		let position = try!(stream.read_u32::<LittleEndian>());
+		let total    = try!(stream.read_u32::<LittleEndian>());
+		let full     = try!(stream.read_u8());
+		let length   = try!(stream.read_u32::<LittleEndian>());
+
+		let valid = size <= maximum && match full {
+			0 => position == length && length < size,
+			1 => position < size && length == size,
+			_ => false,
+		};
+
+		if !valid {
+			return Err(Error::Corrupted);
+		}
+
+		// the length is only trusted as far as there's data for it
+		let mut buffer = Vec::new();
+		try!(stream.take(length as u64).read_to_end(&mut buffer));
+
+		if buffer.len() != length as usize {
+			return Err(Error::NeedMoreData);
+		}
+
+		Ok(Window {
+			buffer: buffer,
+			size:   size,
+
+			position: position,
+			total:    total,
+
+			full: full == 1,
+		})
+	}
+
 	/// Checks if the distance is valid.
 	pub fn check(&self, distance: u32) -> bool {
-		distance <= self.position || self.full
+		distance != 0 && (distance <= self.position || (self.full && distance <= self.size))
 	}
 }
 
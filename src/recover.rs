@@ -0,0 +1,288 @@
+use std::io::{Read, Write, Seek, SeekFrom};
+use byteorder::ReadBytesExt;
+
+use {Error, Format, Info, format, reader, xz, lzip};
+use reader::Counter;
+use xz::{Check, Hasher, Record};
+use xz::decoder::{self, Decoder};
+
+/// A damaged part of a stream, see `recover`.
+#[derive(Debug)]
+pub struct Damage {
+	/// The offset in the stream of the damaged block or member, or of the
+	/// failure for `.lzma` streams and when the layout couldn't be read.
+	pub offset: u64,
+
+	/// The offset in the output where the missing data starts.
+	pub position: u64,
+
+	/// The amount of bytes missing from the output, if known from the headers,
+	/// the index or the trailer.
+	pub lost: Option<u64>,
+
+	/// The error that stopped decoding.
+	pub error: Error,
+}
+
+/// The result of recovering a stream, see `recover`.
+#[derive(Debug)]
+pub struct Recovery {
+	/// The format of the stream.
+	pub format: Format,
+
+	/// The amount of bytes written to the output.
+	pub recovered: u64,
+
+	/// The damaged parts, in stream order.
+	pub damaged: Vec<Damage>,
+}
+
+impl Recovery {
+	/// Checks if the stream was intact.
+	pub fn is_ok(&self) -> bool {
+		self.damaged.is_empty()
+	}
+}
+
+// the output with the amount of bytes written to it
+struct Output<W: Write> {
+	inner: W,
+	count: u64,
+}
+
+// copies everything decoded to the output, failures of the output are the
+// outer error while failures of the decoder are returned
+fn copy<R: Read, W: Write, F: FnMut(&[u8])>(mut decoder: R, output: &mut Output<W>, mut each: F) -> Result<Option<Error>, Error> {
+	let mut buffer = vec![0u8; 1 << 16];
+
+	loop {
+		match decoder.read(&mut buffer) {
+			Ok(0) =>
+				return Ok(None),
+
+			Ok(n) => {
+				each(&buffer[.. n]);
+				try!(output.inner.write_all(&buffer[.. n]));
+				output.count += n as u64;
+			}
+
+			Err(err) =>
+				return Ok(Some(Error::from_io(err)))
+		}
+	}
+}
+
+fn lzma<R: Read, W: Write>(stream: R, memlimit: u64, output: &mut Output<W>, damaged: &mut Vec<Damage>) -> Result<(), Error> {
+	let mut decoder = try!(reader::Reader::from_with_memlimit(Counter::new(stream, 0), memlimit));
+
+	if let Some(err) = try!(copy(&mut decoder, output, |_| ())) {
+		damaged.push(Damage {
+			offset:   decoder.failure().map_or(decoder.get_ref().count(), |failure| failure.position.compressed),
+			position: output.count,
+			lost:     decoder.properties().uncompressed.map(|size| size.saturating_sub(decoder.decoded())),
+			error:    err,
+		});
+	}
+
+	Ok(())
+}
+
+// reads the header of a block found through the index, returns it with the
+// size of what follows, the stream is left right after the header
+fn read_block<R: Read + Seek>(stream: &mut R, offset: u64, record: &Record, check: Check) -> Result<(xz::Block, u64), Error> {
+	try!(stream.seek(SeekFrom::Start(offset)));
+
+	let     first = try!(stream.read_u8());
+	let mut block = try!(xz::Block::read(stream.by_ref(), first));
+
+	let compressed = match record.unpadded.checked_sub(block.size + check.size() as u64) {
+		Some(size) if size > 0 =>
+			size,
+
+		_ =>
+			return Err(Error::Corrupted)
+	};
+
+	block.compressed   = block.compressed.or(Some(compressed));
+	block.uncompressed = block.uncompressed.or(Some(record.uncompressed));
+
+	Ok((block, compressed + (4 - compressed % 4) % 4 + check.size() as u64))
+}
+
+// decodes a block found through the index, returns the decoding failure if any
+fn block<R: Read + Seek, W: Write>(stream: &mut R, offset: u64, record: &Record, check: Check, memlimit: u64, output: &mut Output<W>) -> Result<Option<Error>, Error> {
+	let (block, size) = match read_block(stream, offset, record, check) {
+		Ok(block) =>
+			block,
+
+		Err(err) =>
+			return Ok(Some(err))
+	};
+
+	let mut decoder = match Decoder::new(Counter::new(stream.by_ref().take(size), 0), &block, memlimit) {
+		Ok(decoder) =>
+			decoder,
+
+		Err(err) =>
+			return Ok(Some(err))
+	};
+
+	let mut hasher = Hasher::new(check);
+	let     start  = output.count;
+
+	if let Some(err) = try!(copy(&mut decoder, output, |buffer| hasher.update(buffer))) {
+		return Ok(Some(err));
+	}
+
+	let mut data = decoder.into_inner();
+	Ok(decoder::finish(&mut data, &block, 0, output.count - start, &hasher, check).err())
+}
+
+fn xz<R: Read + Seek, W: Write>(mut stream: R, memlimit: u64, output: &mut Output<W>, damaged: &mut Vec<Damage>) -> Result<(), Error> {
+	let start   = try!(stream.stream_position());
+	let streams = match xz::locate(&mut stream) {
+		Ok(streams) =>
+			streams,
+
+		// without the indexes blocks can only be followed one after the other
+		Err(_) => {
+			try!(stream.seek(SeekFrom::Start(start)));
+			let mut reader = try!(xz::Reader::with_memlimit(stream, memlimit));
+
+			if let Some(err) = try!(copy(&mut reader, output, |_| ())) {
+				damaged.push(Damage {
+					offset:   reader.compressed(),
+					position: output.count,
+					lost:     None,
+					error:    err,
+				});
+			}
+
+			return Ok(());
+		}
+	};
+
+	for located in streams {
+		let check      = located.flags.check;
+		let mut offset = located.offset + 12;
+
+		for record in &located.index.records {
+			let start = output.count;
+
+			if let Some(err) = try!(block(&mut stream, offset, record, check, memlimit, output)) {
+				damaged.push(Damage {
+					offset:   offset,
+					position: output.count,
+					lost:     Some(record.uncompressed.saturating_sub(output.count - start)),
+					error:    err,
+				});
+			}
+
+			offset += (record.unpadded + 3) & !3;
+		}
+	}
+
+	Ok(())
+}
+
+fn lzip<R: Read + Seek, W: Write>(mut stream: R, memlimit: u64, output: &mut Output<W>, damaged: &mut Vec<Damage>) -> Result<(), Error> {
+	let start   = try!(stream.stream_position());
+	let members = match Info::read(&mut stream) {
+		Ok(Info::Lzip(members)) =>
+			members,
+
+		// without the trailers members can only be followed one after the other
+		_ => {
+			try!(stream.seek(SeekFrom::Start(start)));
+			let mut reader = try!(lzip::Reader::with_memlimit(stream, memlimit));
+
+			if let Some(err) = try!(copy(&mut reader, output, |_| ())) {
+				damaged.push(Damage {
+					offset:   start + reader.compressed(),
+					position: output.count,
+					lost:     None,
+					error:    err,
+				});
+			}
+
+			return Ok(());
+		}
+	};
+
+	for member in members {
+		let start = output.count;
+
+		try!(stream.seek(SeekFrom::Start(member.offset)));
+
+		let result = match lzip::Reader::with_memlimit(stream.by_ref().take(member.size), memlimit) {
+			Ok(reader) =>
+				try!(copy(reader, output, |_| ())),
+
+			Err(err) =>
+				Some(err)
+		};
+
+		if let Some(err) = result {
+			damaged.push(Damage {
+				offset:   member.offset,
+				position: output.count,
+				lost:     Some(member.uncompressed.saturating_sub(output.count - start)),
+				error:    err,
+			});
+		}
+	}
+
+	Ok(())
+}
+
+/// Decodes as much as possible of a damaged stream to the output, never
+/// throwing away decoded data.
+///
+/// A `.lzma` stream is decoded up to the failure. Blocks of xz streams are
+/// found through the index and members of lzip streams through their
+/// trailers, so damaged ones are skipped and decoding goes on with the next.
+/// When the index or trailers are damaged too decoding stops at the first
+/// failure.
+///
+/// Only a stream that can't be started at all or a failing output are errors,
+/// the damaged parts end up in the result.
+pub fn recover<R: Read + Seek, W: Write>(stream: R, output: W) -> Result<Recovery, Error> {
+	recover_with_memlimit(stream, output, u64::MAX)
+}
+
+/// Decodes a damaged stream like `recover` with a memory limit, blocks and
+/// members needing more than the limit are reported as damaged while a
+/// `.lzma` stream can't be started.
+pub fn recover_with_memlimit<R: Read + Seek, W: Write>(mut stream: R, output: W, memlimit: u64) -> Result<Recovery, Error> {
+	let start = try!(stream.stream_position());
+
+	let mut header = Vec::new();
+	try!(stream.by_ref().take(format::DETECT_SIZE as u64).read_to_end(&mut header));
+	try!(stream.seek(SeekFrom::Start(start)));
+
+	let format = match format::detect(&header) {
+		Some(format) =>
+			format,
+
+		None =>
+			return Err(Error::InvalidHeader)
+	};
+
+	let mut output  = Output { inner: output, count: 0 };
+	let mut damaged = Vec::new();
+
+	match format {
+		Format::Lzma => try!(lzma(stream, memlimit, &mut output, &mut damaged)),
+		Format::Xz   => try!(xz(stream, memlimit, &mut output, &mut damaged)),
+		Format::Lzip => try!(lzip(stream, memlimit, &mut output, &mut damaged)),
+		_            => return Err(Error::Unsupported),
+	}
+
+	try!(output.inner.flush());
+
+	Ok(Recovery {
+		format:    format,
+		recovered: output.count,
+		damaged:   damaged,
+	})
+}
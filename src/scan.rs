@@ -0,0 +1,223 @@
+use std::io::{self, Read};
+use std::cmp;
+use byteorder::{LittleEndian, ByteOrder};
+
+use {Error, Properties, Format, properties, reader, xz, lzip, detect};
+use check::crc32;
+use format::SEVEN_ZIP_MAGIC;
+use reader::Counter;
+
+/// Size of the 7z signature header.
+const SEVEN_ZIP_HEADER_SIZE: u64 = 32;
+
+/// The memory limit `scan` decodes candidates with, enough for the
+/// dictionaries of every xz and lzma preset.
+pub const TRIAL_MEMLIMIT: u64 = 1 << 28;
+
+/// A stream found by `scan`.
+#[derive(Debug)]
+pub struct Candidate {
+	/// Offset of the stream in the scanned data.
+	pub offset: usize,
+
+	/// The format of the stream.
+	pub format: Format,
+
+	/// The model properties, for formats with a single LZMA model.
+	pub properties: Option<Properties>,
+
+	/// Size of the stream, headers included, or of what was read before trial
+	/// decoding failed.
+	pub compressed: u64,
+
+	/// Size of the decoded data, or of what was decoded before trial decoding
+	/// failed, `None` for formats that aren't decoded.
+	pub decompressed: Option<u64>,
+
+	/// Why trial decoding failed, like a stream cut short.
+	pub error: Option<Error>,
+}
+
+impl Clone for Candidate {
+	fn clone(&self) -> Candidate {
+		Candidate {
+			offset:     self.offset,
+			format:     self.format,
+			properties: self.properties,
+
+			compressed:   self.compressed,
+			decompressed: self.decompressed,
+			error:        self.error.as_ref().map(Error::duplicate),
+		}
+	}
+}
+
+/// Scans the data for embedded .lzma, .xz, .lzip and 7z streams.
+///
+/// Every offset is checked, and a candidate is reported when trial decoding
+/// succeeds or fails in a way a damaged stream would, with the error it
+/// failed with, 7z archives are checked against their header CRC instead.
+/// Candidates are decoded up to their end with the output thrown away, so the
+/// memory used doesn't grow with their size. The scan resumes after the end of
+/// each stream decoded successfully.
+pub fn scan(data: &[u8]) -> Vec<Candidate> {
+	scan_with_memlimit(data, TRIAL_MEMLIMIT)
+}
+
+/// Scans the data like `scan`, decoding candidates with the given memory
+/// limit instead of `TRIAL_MEMLIMIT`.
+pub fn scan_with_memlimit(data: &[u8], memlimit: u64) -> Vec<Candidate> {
+	let mut candidates = Vec::new();
+	let mut offset     = 0;
+
+	while offset < data.len() {
+		let rest = &data[offset ..];
+
+		let candidate = match detect(rest) {
+			Some(Format::Lzma) =>
+				lzma(rest, memlimit),
+
+			Some(Format::Xz) =>
+				xz(rest, memlimit),
+
+			Some(Format::Lzip) =>
+				lzip(rest, memlimit),
+
+			Some(Format::SevenZip) =>
+				seven_zip(rest),
+
+			_ =>
+				Err(Error::InvalidHeader)
+		};
+
+		if let Ok(mut candidate) = candidate {
+			candidate.offset = offset;
+			offset          += if candidate.error.is_none() { cmp::max(candidate.compressed as usize, 1) } else { 1 };
+
+			candidates.push(candidate);
+		}
+		else {
+			offset += 1;
+		}
+	}
+
+	candidates
+}
+
+// decodes the whole stream, returning how many bytes were decoded and the
+// error decoding stopped with
+fn trial<R: Read>(mut reader: R) -> (u64, Option<Error>) {
+	let mut buffer  = [0u8; 4096];
+	let mut decoded = 0;
+
+	loop {
+		match reader.read(&mut buffer) {
+			Ok(0) =>
+				return (decoded, None),
+
+			Ok(read) =>
+				decoded += read as u64,
+
+			Err(ref err) if err.kind() == io::ErrorKind::Interrupted =>
+				(),
+
+			Err(err) =>
+				return (decoded, Some(Error::from_io(err)))
+		}
+	}
+}
+
+fn lzma(data: &[u8], memlimit: u64) -> Result<Candidate, Error> {
+	let     properties       = try!(properties::read(data));
+	let mut reader           = try!(reader::Reader::with_memlimit(Counter::new(&data[13 ..], 13), properties, memlimit));
+	let     (decoded, error) = trial(&mut reader);
+	let     compressed       = reader.get_ref().count();
+
+	// the header is too weak to tell a damaged stream from random data, unless
+	// it was decoded up to the end of the data
+	if decoded == 0 || (error.is_some() && compressed != data.len() as u64) {
+		return Err(Error::Corrupted);
+	}
+
+	Ok(Candidate {
+		offset:     0,
+		format:     Format::Lzma,
+		properties: Some(properties),
+
+		compressed:   compressed,
+		decompressed: Some(decoded),
+		error:        error,
+	})
+}
+
+fn xz(data: &[u8], memlimit: u64) -> Result<Candidate, Error> {
+	let mut input = Counter::new(data, 0);
+
+	let (decoded, error, finished) = {
+		let mut reader = try!(xz::Reader::with_memlimit(&mut input, memlimit));
+		reader.set_concatenated(false);
+
+		let (decoded, error) = trial(&mut reader);
+		(decoded, error, if reader.is_finished() { Some(reader.compressed()) } else { None })
+	};
+
+	Ok(Candidate {
+		offset:     0,
+		format:     Format::Xz,
+		properties: None,
+
+		compressed:   finished.unwrap_or(input.count()),
+		decompressed: Some(decoded),
+		error:        error,
+	})
+}
+
+fn lzip(data: &[u8], memlimit: u64) -> Result<Candidate, Error> {
+	let mut input = Counter::new(data, 0);
+
+	let (properties, decoded, error, finished) = {
+		let mut reader     = try!(lzip::Reader::with_memlimit(&mut input, memlimit));
+		let     properties = reader.properties().cloned();
+
+		let (decoded, error) = trial(&mut reader);
+		(properties, decoded, error, if reader.is_finished() { Some(reader.compressed()) } else { None })
+	};
+
+	Ok(Candidate {
+		offset:     0,
+		format:     Format::Lzip,
+		properties: properties,
+
+		compressed:   finished.unwrap_or(input.count()),
+		decompressed: Some(decoded),
+		error:        error,
+	})
+}
+
+fn seven_zip(data: &[u8]) -> Result<Candidate, Error> {
+	if data.len() < SEVEN_ZIP_HEADER_SIZE as usize || data[.. 6] != SEVEN_ZIP_MAGIC {
+		return Err(Error::InvalidHeader);
+	}
+
+	if crc32(&data[12 .. 32]) != LittleEndian::read_u32(&data[8 .. 12]) {
+		return Err(Error::Checksum);
+	}
+
+	let offset = LittleEndian::read_u64(&data[12 .. 20]);
+	let size   = LittleEndian::read_u64(&data[20 .. 28]);
+	let total  = offset.saturating_add(size).saturating_add(SEVEN_ZIP_HEADER_SIZE);
+
+	if total > data.len() as u64 {
+		return Err(Error::NeedMoreData);
+	}
+
+	Ok(Candidate {
+		offset:     0,
+		format:     Format::SevenZip,
+		properties: None,
+
+		compressed:   total,
+		decompressed: None,
+		error:        None,
+	})
+}
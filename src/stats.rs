@@ -0,0 +1,153 @@
+use std::io::Read;
+
+use {Error, Reader, Packet};
+use consts::MATCH_MAXIMUM_LENGTH;
+
+/// Statistics about one kind of packet.
+#[derive(PartialEq, Clone, Copy, Default, Debug)]
+pub struct Category {
+	/// The amount of packets.
+	pub count: u64,
+
+	/// The amount of bytes they produced.
+	pub bytes: u64,
+
+	/// The amount of bits they took.
+	pub bits: f64,
+}
+
+impl Category {
+	/// Returns the average amount of bits per packet.
+	pub fn bits_per_packet(&self) -> f64 {
+		if self.count == 0 {
+			0.0
+		}
+		else {
+			self.bits / self.count as f64
+		}
+	}
+
+	/// Returns the average amount of bits per produced byte.
+	pub fn bits_per_byte(&self) -> f64 {
+		if self.bytes == 0 {
+			0.0
+		}
+		else {
+			self.bits / self.bytes as f64
+		}
+	}
+
+	fn add(&mut self, bytes: usize, bits: f64) {
+		self.count += 1;
+		self.bytes += bytes as u64;
+		self.bits  += bits;
+	}
+}
+
+/// Statistics about the packets of a stream, see `stats`.
+#[derive(PartialEq, Clone, Debug)]
+pub struct Stats {
+	/// Literal bytes.
+	pub literals: Category,
+
+	/// Matches with a new distance.
+	pub matches: Category,
+
+	/// Single bytes at the last distance.
+	pub short_reps: Category,
+
+	/// Matches at one of the last four distances, by index.
+	pub reps: [Category; 4],
+
+	/// The EOS marker, if any.
+	pub marker: Category,
+
+	/// The amount of matches and repeated matches by length, from 0 to 273.
+	pub lengths: Vec<u64>,
+
+	/// The amount of matches by distance, the index being the number of bits
+	/// of the distance, 1 being the last decoded byte.
+	pub distances: Vec<u64>,
+}
+
+impl Stats {
+	fn new() -> Stats {
+		Stats {
+			literals:   Category::default(),
+			matches:    Category::default(),
+			short_reps: Category::default(),
+			reps:       [Category::default(); 4],
+			marker:     Category::default(),
+
+			lengths:   vec![0; MATCH_MAXIMUM_LENGTH + 1],
+			distances: vec![0; 33],
+		}
+	}
+
+	/// Returns the amount of decoded bytes.
+	pub fn decoded(&self) -> u64 {
+		self.literals.bytes + self.match_bytes()
+	}
+
+	/// Returns the amount of bits that went to literals.
+	pub fn literal_bits(&self) -> f64 {
+		self.literals.bits
+	}
+
+	/// Returns the amount of bits that went to matches of any kind.
+	pub fn match_bits(&self) -> f64 {
+		self.matches.bits + self.short_reps.bits + self.reps.iter().map(|c| c.bits).sum::<f64>()
+	}
+
+	/// Returns the amount of bytes produced by matches of any kind.
+	pub fn match_bytes(&self) -> u64 {
+		self.matches.bytes + self.short_reps.bytes + self.reps.iter().map(|c| c.bytes).sum::<u64>()
+	}
+
+	/// Returns the total amount of bits, the range decoder flushing aside.
+	pub fn bits(&self) -> f64 {
+		self.literal_bits() + self.match_bits() + self.marker.bits
+	}
+}
+
+/// Decodes the whole stream, gathering statistics about its packets.
+///
+/// The bits a packet takes are computed from the probabilities the decoder
+/// used for it.
+pub fn stats<R: Read>(mut reader: Reader<R>) -> Result<Stats, Error> {
+	let mut stats = Stats::new();
+	reader.set_cost(true);
+
+	let mut last    = 0.0;
+	let mut packets = try!(reader.packets());
+
+	while let Some(item) = packets.next() {
+		let (_, packet) = try!(item);
+		let     cost    = packets.get_ref().cost().unwrap_or(0.0) - last;
+		last += cost;
+
+		match packet {
+			Packet::Literal(..) =>
+				stats.literals.add(1, cost),
+
+			Packet::Match { distance, len } => {
+				stats.matches.add(len, cost);
+				stats.lengths[len] += 1;
+				stats.distances[(32 - distance.leading_zeros()) as usize] += 1;
+			}
+
+			Packet::ShortRep =>
+				stats.short_reps.add(1, cost),
+
+			Packet::Rep { index, len } => {
+				stats.reps[index as usize].add(len, cost);
+				stats.lengths[len] += 1;
+			}
+
+			Packet::EndMarker =>
+				stats.marker.add(0, cost),
+		}
+	}
+
+	Ok(stats)
+}
@@ -0,0 +1,193 @@
+use std::io::{self, Read, Write};
+use std::cmp;
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+
+use {Error, Properties, properties, reader, writer};
+
+/// Size of the SWF header common to every variant.
+const HEADER_SIZE: usize = 8;
+
+/// A LZMA compressed SWF reader, producing the uncompressed SWF file.
+#[derive(Debug)]
+pub struct Reader<R: Read> {
+	header: [u8; HEADER_SIZE],
+	offset: usize,
+
+	compressed: u32,
+	inner:      reader::Reader<R>,
+}
+
+impl<R: Read> Reader<R> {
+	/// Creates a SWF reader from a "ZWS" stream.
+	pub fn new(stream: R) -> Result<Reader<R>, Error> {
+		Reader::with_memlimit(stream, u64::MAX)
+	}
+
+	/// Creates a SWF reader like `new`, failing before allocating anything if
+	/// decoding needs more memory than the limit.
+	pub fn with_memlimit(mut stream: R, memlimit: u64) -> Result<Reader<R>, Error> {
+		let mut magic = [0u8; 3];
+		try!(stream.read_exact(&mut magic));
+
+		if &magic != b"ZWS" {
+			return Err(Error::InvalidHeader);
+		}
+
+		let version    = try!(stream.read_u8());
+		let length     = try!(stream.read_u32::<LittleEndian>());
+		let compressed = try!(stream.read_u32::<LittleEndian>());
+
+		if (length as usize) < HEADER_SIZE {
+			return Err(Error::InvalidHeader);
+		}
+
+		let mut properties = try!(properties::read_model(stream.by_ref()));
+		properties.uncompressed = Some(length as u64 - HEADER_SIZE as u64);
+
+		let mut header = [0u8; HEADER_SIZE];
+		header[.. 3].copy_from_slice(b"FWS");
+		header[3] = version;
+		try!((&mut header[4 ..]).write_u32::<LittleEndian>(length));
+
+		Ok(Reader {
+			header: header,
+			offset: 0,
+
+			compressed: compressed,
+			inner:      try!(reader::Reader::with_memlimit(stream, properties, memlimit)),
+		})
+	}
+
+	/// Returns the SWF version.
+	pub fn version(&self) -> u8 {
+		self.header[3]
+	}
+
+	/// Returns the size of the uncompressed SWF file, header included.
+	pub fn length(&self) -> u32 {
+		(&self.header[4 ..]).read_u32::<LittleEndian>().unwrap()
+	}
+
+	/// Returns the size of the LZMA data as declared in the header.
+	pub fn compressed(&self) -> u32 {
+		self.compressed
+	}
+
+	/// Returns the model properties.
+	pub fn properties(&self) -> &Properties {
+		self.inner.properties()
+	}
+
+	/// Unwraps this `Reader`, returning the underlying reader.
+	pub fn into_inner(self) -> R {
+		self.inner.into_inner()
+	}
+}
+
+impl<R: Read> Read for Reader<R> {
+	fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+		if self.offset < HEADER_SIZE {
+			let length = cmp::min(buf.len(), HEADER_SIZE - self.offset);
+
+			buf[.. length].copy_from_slice(&self.header[self.offset .. self.offset + length]);
+			self.offset += length;
+
+			return Ok(length);
+		}
+
+		self.inner.read(buf)
+	}
+}
+
+/// A SWF writer, turning an uncompressed "FWS" file into a "ZWS" one.
+///
+/// The compressed data is kept in memory until `finish` since its size is
+/// part of the header.
+#[derive(Debug)]
+pub struct Writer<W: Write> {
+	stream:     W,
+	properties: Properties,
+
+	header: Vec<u8>,
+	inner:  Option<writer::Writer<Vec<u8>>>,
+}
+
+impl<W: Write> Writer<W> {
+	/// Creates a SWF writer with the given model properties, the uncompressed
+	/// size is taken from the SWF header.
+	pub fn new(stream: W, properties: Properties) -> Result<Writer<W>, Error> {
+		Ok(Writer {
+			stream:     stream,
+			properties: properties,
+
+			header: Vec::with_capacity(HEADER_SIZE),
+			inner:  None,
+		})
+	}
+
+	/// Compresses the remaining data and writes the "ZWS" file, returning the
+	/// underlying writer.
+	pub fn finish(mut self) -> Result<W, Error> {
+		let data = match self.inner.take() {
+			Some(inner) =>
+				try!(inner.finish()),
+
+			None =>
+				return Err(Error::NeedMoreData)
+		};
+
+		try!(self.stream.write_all(b"ZWS"));
+		try!(self.stream.write_all(&self.header[3 ..]));
+		try!(self.stream.write_u32::<LittleEndian>(data.len() as u32));
+		try!(properties::write_model(self.stream.by_ref(), &self.properties));
+		try!(self.stream.write_all(&data));
+		try!(self.stream.flush());
+
+		Ok(self.stream)
+	}
+}
+
+impl<W: Write> Write for Writer<W> {
+	fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+		if let Some(inner) = self.inner.as_mut() {
+			return inner.write(buf);
+		}
+
+		let length = cmp::min(buf.len(), HEADER_SIZE - self.header.len());
+		self.header.extend_from_slice(&buf[.. length]);
+
+		if self.header.len() == HEADER_SIZE {
+			let size = (&self.header[4 ..]).read_u32::<LittleEndian>().unwrap() as usize;
+
+			if &self.header[.. 3] != b"FWS" || size < HEADER_SIZE {
+				return Err(io::Error::new(io::ErrorKind::InvalidData, Error::InvalidHeader));
+			}
+
+			self.properties.uncompressed = Some((size - HEADER_SIZE) as u64);
+
+			match writer::Writer::new(Vec::new(), self.properties) {
+				Ok(inner) =>
+					self.inner = Some(inner),
+
+				Err(err) =>
+					return Err(io::Error::other(err)),
+			}
+		}
+
+		Ok(length)
+	}
+
+	fn flush(&mut self) -> io::Result<()> {
+		Ok(())
+	}
+}
+
+/// Create a SWF stream from a "ZWS" stream.
+pub fn read<T: Read>(stream: T) -> Result<Reader<T>, Error> {
+	Reader::new(stream)
+}
+
+/// Create a "ZWS" stream to another stream.
+pub fn write<T: Write>(stream: T, properties: Properties) -> Result<Writer<T>, Error> {
+	Writer::new(stream, properties)
+}
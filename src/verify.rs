@@ -0,0 +1,242 @@
+use std::io::{self, Read, Write, BufRead};
+use byteorder::{LittleEndian, ReadBytesExt};
+
+use {Error, Failure, Position, PacketKind, Cause, Format, Packet, format, reader, xz, lzip};
+use check::Crc32;
+use reader::Counter;
+
+/// The result of verifying a stream, see `verify`.
+#[derive(Debug)]
+pub struct Report {
+	/// The format of the stream.
+	pub format: Format,
+
+	/// The amount of compressed bytes read.
+	pub compressed: u64,
+
+	/// The amount of bytes decoded.
+	pub uncompressed: u64,
+
+	/// The uncompressed size declared by the headers or trailers, if any.
+	pub declared: Option<u64>,
+
+	/// Whether the data ended with an EOS marker, `None` for xz which doesn't
+	/// use it.
+	pub marker: Option<bool>,
+
+	/// The amount of checksums of the uncompressed data that were verified.
+	pub checksums: u64,
+
+	/// The error that stopped the verification, `None` if the stream is
+	/// intact.
+	pub error: Option<Error>,
+
+	/// Where and why decoding failed, for `.lzma` streams.
+	pub failure: Option<Failure>,
+}
+
+impl Report {
+	fn new(format: Format) -> Report {
+		Report {
+			format:       format,
+			compressed:   0,
+			uncompressed: 0,
+			declared:     None,
+			marker:       None,
+			checksums:    0,
+			error:        None,
+			failure:      None,
+		}
+	}
+
+	/// Checks if the stream is intact.
+	pub fn is_ok(&self) -> bool {
+		self.error.is_none()
+	}
+}
+
+// a writer computing the CRC of what goes through it
+struct Digest(Crc32);
+
+impl Write for Digest {
+	fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+		self.0.update(buf);
+
+		Ok(buf.len())
+	}
+
+	fn flush(&mut self) -> io::Result<()> {
+		Ok(())
+	}
+}
+
+// decodes packets until the end of the data, returns whether it ended with the
+// EOS marker
+fn decode<R: Read, W: Write>(decoder: &mut reader::Reader<R>, mut writer: W) -> Result<bool, Error> {
+	loop {
+		match try!(decoder.decode_packet(&mut writer)) {
+			Some(Packet::EndMarker) =>
+				return Ok(true),
+
+			Some(_) =>
+				(),
+
+			None =>
+				return Ok(false)
+		}
+	}
+}
+
+fn lzma<R: Read>(stream: R, report: &mut Report) -> Result<(), Error> {
+	let mut decoder = try!(reader::Reader::from(Counter::new(stream, 0)));
+	report.declared = decoder.properties().uncompressed;
+
+	let result = decode(&mut decoder, io::sink());
+
+	report.compressed   = decoder.get_ref().count();
+	report.uncompressed = decoder.decoded();
+	report.failure      = decoder.failure();
+	report.marker       = Some(try!(result));
+
+	if report.marker == Some(true) || decoder.is_range_finished() {
+		return Ok(());
+	}
+
+	// the range coder must be done at the declared size, unless an EOS marker
+	// follows it
+	unsafe {
+		decoder.set_uncompressed(None);
+	}
+
+	match decoder.decode_packet(io::sink()) {
+		Ok(Some(Packet::EndMarker)) => {
+			report.compressed = decoder.get_ref().count();
+			report.marker     = Some(true);
+
+			Ok(())
+		}
+
+		result => {
+			report.failure = Some(Failure {
+				position: Position {
+					compressed:   report.compressed,
+					uncompressed: report.uncompressed,
+					packet:       result.ok().and_then(|packet| packet).map_or(PacketKind::Unknown, |packet| packet.kind()),
+				},
+
+				cause: Cause::HasMoreData,
+			});
+
+			Err(Error::HasMoreData)
+		}
+	}
+}
+
+fn lzip<R: Read>(stream: R, report: &mut Report) -> Result<(), Error> {
+	let mut stream = Counter::new(stream, 0);
+	let mut header = try!(lzip::read_header(&mut stream));
+
+	if header.is_none() {
+		return Err(Error::InvalidHeader);
+	}
+
+	report.declared = Some(0);
+	report.marker   = Some(true);
+
+	while let Some((_, dictionary)) = header {
+		let     start   = stream.count() - lzip::HEADER_SIZE;
+		let mut decoder = try!(reader::Reader::new(stream, lzip::properties(dictionary)));
+		let mut digest  = Digest(Crc32::new());
+		let     result  = decode(&mut decoder, &mut digest);
+		let     decoded = decoder.decoded();
+
+		stream = decoder.into_inner();
+
+		report.compressed    = stream.count();
+		report.uncompressed += decoded;
+
+		// members always end with the marker
+		if !try!(result) {
+			report.marker = Some(false);
+			return Err(Error::Corrupted);
+		}
+
+		let crc    = try!(stream.read_u32::<LittleEndian>());
+		let size   = try!(stream.read_u64::<LittleEndian>());
+		let member = try!(stream.read_u64::<LittleEndian>());
+
+		report.compressed = stream.count();
+		report.declared   = report.declared.map(|declared| declared + size);
+
+		if crc != digest.0.value() {
+			return Err(Error::Checksum);
+		}
+
+		if size != decoded || member != stream.count() - start {
+			return Err(Error::Corrupted);
+		}
+
+		report.checksums += 1;
+
+		// anything that isn't another member is trailing data
+		header = try!(lzip::read_header(&mut stream));
+	}
+
+	Ok(())
+}
+
+fn xz<R: Read>(stream: R, report: &mut Report) -> Result<(), Error> {
+	let mut reader = try!(xz::Reader::new(stream));
+	let mut buffer = vec![0u8; 1 << 16];
+
+	let result = loop {
+		match reader.read(&mut buffer) {
+			Ok(0) =>
+				break Ok(()),
+
+			Ok(n) =>
+				report.uncompressed += n as u64,
+
+			Err(err) =>
+				break Err(Error::from_io(err))
+		}
+	};
+
+	report.compressed = reader.compressed();
+	report.checksums  = reader.checked();
+
+	result
+}
+
+/// Decodes a whole stream without writing the output, checking the EOS
+/// marker, the declared sizes and the checksums of the container.
+///
+/// The format is detected from the first bytes, `.lzma`, xz and lzip streams
+/// are supported. Only a stream that can't be started at all is an error,
+/// failures while decoding end up in the report.
+pub fn verify<R: BufRead>(stream: R) -> Result<Report, Error> {
+	let (format, stream) = try!(format::peek(stream));
+
+	let format = match format {
+		Some(format) =>
+			format,
+
+		None =>
+			return Err(Error::InvalidHeader)
+	};
+
+	let mut report = Report::new(format);
+
+	let result = match format {
+		Format::Lzma => lzma(stream, &mut report),
+		Format::Xz   => xz(stream, &mut report),
+		Format::Lzip => lzip(stream, &mut report),
+		_            => return Err(Error::Unsupported),
+	};
+
+	if let Err(err) = result {
+		report.error = Some(err);
+	}
+
+	Ok(report)
+}
@@ -0,0 +1,68 @@
+use std::io::Write;
+
+use {Error};
+use reader::Probabilities;
+use super::Range;
+
+/// A bit tree encoder.
+#[derive(Clone, Debug)]
+pub struct BitTree {
+	probabilities: Probabilities,
+	bits:          usize,
+}
+
+impl BitTree {
+	/// Creates a new bit tree of the given size.
+	pub fn new(bits: usize) -> BitTree {
+		BitTree {
+			probabilities: Probabilities::new(1 << bits),
+			bits:          bits,
+		}
+	}
+
+	/// Gets the number of bits in the tree.
+	pub fn bits(&self) -> usize {
+		self.bits
+	}
+
+	/// Encodes bits.
+	pub fn encode<W: Write>(&mut self, mut stream: W, range: &mut Range, value: usize) -> Result<(), Error> {
+		let mut m = 1usize;
+
+		for i in (0 .. self.bits()).rev() {
+			let bit = (value >> i) & 1 == 1;
+			try!(range.probabilistic(stream.by_ref(), &mut self.probabilities[m], bit));
+
+			m <<= 1;
+
+			if bit {
+				m += 1;
+			}
+		}
+
+		Ok(())
+	}
+
+	/// Encodes bits in reverse order.
+	pub fn reverse<W: Write>(&mut self, stream: W, range: &mut Range, value: usize) -> Result<(), Error> {
+		reverse(stream, &mut self.probabilities, self.bits, range, value)
+	}
+}
+
+#[doc(hidden)]
+pub fn reverse<W: Write>(mut stream: W, probs: &mut [u16], bits: usize, range: &mut Range, value: usize) -> Result<(), Error> {
+	let mut m = 1;
+
+	for i in 0 .. bits {
+		let bit = (value >> i) & 1 == 1;
+		try!(range.probabilistic(stream.by_ref(), &mut probs[m], bit));
+
+		m <<= 1;
+
+		if bit {
+			m += 1;
+		}
+	}
+
+	Ok(())
+}
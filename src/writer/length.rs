@@ -0,0 +1,52 @@
+use std::io::Write;
+
+use {Error};
+use consts::{PROBABILITY_INITIAL_VALUE, POSITION_BITS_MAX};
+use super::{BitTree, Range};
+
+/// A length encoder.
+#[derive(Clone, Debug)]
+pub struct Length {
+	choice: [u16; 2],
+
+	low: Vec<BitTree>,
+	mid: Vec<BitTree>,
+	hig: BitTree,
+}
+
+impl Length {
+	/// Creates a new length encoder.
+	pub fn new() -> Self {
+		Length {
+			choice: [PROBABILITY_INITIAL_VALUE; 2],
+
+			low: vec![BitTree::new(3); 1 << POSITION_BITS_MAX],
+			mid: vec![BitTree::new(3); 1 << POSITION_BITS_MAX],
+			hig: BitTree::new(8),
+		}
+	}
+
+	/// Encode a length, already reduced by the minimum match length.
+	pub fn encode<W: Write>(&mut self, mut stream: W, range: &mut Range, state: usize, length: usize) -> Result<(), Error> {
+		if length < 8 {
+			try!(range.probabilistic(stream.by_ref(), &mut self.choice[0], false));
+			self.low[state].encode(stream.by_ref(), range, length)
+		}
+		else if length < 16 {
+			try!(range.probabilistic(stream.by_ref(), &mut self.choice[0], true));
+			try!(range.probabilistic(stream.by_ref(), &mut self.choice[1], false));
+			self.mid[state].encode(stream.by_ref(), range, length - 8)
+		}
+		else {
+			try!(range.probabilistic(stream.by_ref(), &mut self.choice[0], true));
+			try!(range.probabilistic(stream.by_ref(), &mut self.choice[1], true));
+			self.hig.encode(stream.by_ref(), range, length - 16)
+		}
+	}
+}
+
+impl Default for Length {
+	fn default() -> Self {
+		Length::new()
+	}
+}
@@ -0,0 +1,30 @@
+mod range;
+pub use self::range::Range;
+
+mod bit_tree;
+pub use self::bit_tree::BitTree;
+
+mod length;
+pub use self::length::Length;
+
+mod window;
+pub use self::window::Window;
+
+mod writer;
+pub use self::writer::Writer;
+
+use std::io::{Write, BufWriter};
+use std::fs::File;
+use std::path::Path;
+
+use {Error, Properties};
+
+/// Create a file as a LZMA stream.
+pub fn create<T: AsRef<Path>>(path: T, properties: Properties) -> Result<Writer<BufWriter<File>>, Error> {
+	write(BufWriter::new(try!(File::create(path))), properties)
+}
+
+/// Create a LZMA stream to another stream.
+pub fn write<T: Write>(stream: T, properties: Properties) -> Result<Writer<T>, Error> {
+	Writer::to(stream, properties)
+}
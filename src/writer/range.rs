@@ -0,0 +1,127 @@
+use std::io::Write;
+use byteorder::WriteBytesExt;
+
+use Error;
+use consts::{MODEL_TOTAL_BITS, TOP_VALUE, MOVE_BITS};
+
+/// A range encoder.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct Range {
+	low:   u64,
+	range: u32,
+
+	cache:   u8,
+	pending: u64,
+
+	written: u64,
+}
+
+impl Range {
+	/// Creates a new range encoder.
+	pub fn new() -> Range {
+		Range {
+			low:   0,
+			range: 0xffffffff,
+
+			cache:   0,
+			pending: 1,
+
+			written: 0,
+		}
+	}
+
+	/// Gets the amount of bytes written so far.
+	pub fn written(&self) -> u64 {
+		self.written
+	}
+
+	/// Gets the amount of bytes that `flush` will write.
+	pub fn pending(&self) -> u64 {
+		self.pending + 4
+	}
+
+	fn shift<W: Write>(&mut self, mut stream: W) -> Result<(), Error> {
+		if (self.low as u32) < 0xff000000 || (self.low >> 32) != 0 {
+			let carry = (self.low >> 32) as u8;
+			let mut byte = self.cache;
+
+			loop {
+				try!(stream.write_u8(byte.wrapping_add(carry)));
+				self.written += 1;
+
+				byte          = 0xff;
+				self.pending -= 1;
+
+				if self.pending == 0 {
+					break;
+				}
+			}
+
+			self.cache = (self.low >> 24) as u8;
+		}
+
+		self.pending += 1;
+		self.low      = (self.low & 0x00ffffff) << 8;
+
+		Ok(())
+	}
+
+	fn normalize<W: Write>(&mut self, stream: W) -> Result<(), Error> {
+		if self.range < TOP_VALUE {
+			self.range <<= 8;
+			try!(self.shift(stream));
+		}
+
+		Ok(())
+	}
+
+	/// Encodes the given amount of bits directly.
+	pub fn direct<W: Write>(&mut self, mut stream: W, value: usize, bits: usize) -> Result<(), Error> {
+		for i in (0 .. bits).rev() {
+			self.range >>= 1;
+
+			if (value >> i) & 1 == 1 {
+				self.low += self.range as u64;
+			}
+
+			try!(self.normalize(stream.by_ref()));
+		}
+
+		Ok(())
+	}
+
+	/// Encodes a bit using the probabilistic model.
+	pub fn probabilistic<W: Write>(&mut self, stream: W, prob: &mut u16, bit: bool) -> Result<(), Error> {
+		let mut v     = *prob;
+		let     bound = (self.range >> MODEL_TOTAL_BITS) * v as u32;
+
+		if !bit {
+			v          += ((1 << MODEL_TOTAL_BITS) - v) >> MOVE_BITS;
+			self.range  = bound;
+		}
+		else {
+			v          -= v >> MOVE_BITS;
+			self.low   += bound as u64;
+			self.range -= bound;
+		}
+
+		*prob = v;
+
+		self.normalize(stream)
+	}
+
+	/// Flushes the remaining state, the encoder cannot be used afterwards.
+	pub fn flush<W: Write>(&mut self, mut stream: W) -> Result<(), Error> {
+		for _ in 0 .. 5 {
+			try!(self.shift(stream.by_ref()));
+		}
+
+		Ok(())
+	}
+}
+
+impl Default for Range {
+	fn default() -> Self {
+		Range::new()
+	}
+}
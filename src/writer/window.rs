@@ -0,0 +1,217 @@
+use std::cmp;
+
+use consts::{MATCH_MINIMUM_LENGTH, MATCH_MAXIMUM_LENGTH};
+
+const HASH_BITS_MINIMUM: u32 = 16;
+const HASH_BITS_MAXIMUM: u32 = 24;
+
+/// A sliding window with a hash chain match finder.
+///
+/// Positions in the chains are stored truncated to 32 bits, every candidate is
+/// verified against the buffer so stale entries only cost a comparison.
+#[derive(Debug)]
+pub struct Window {
+	buffer: Vec<u8>,
+	size:   u32,
+
+	offset: u64,
+	cursor: usize,
+
+	bits:  u32,
+	head:  Vec<u32>,
+	chain: Vec<u32>,
+
+	depth: usize,
+	nice:  usize,
+}
+
+impl Window {
+	/// Creates a window for the given dictionary size, following at most
+	/// `depth` candidates and stopping at matches of `nice` length.
+	pub fn new(size: u32, depth: usize, nice: usize) -> Self {
+		let bits = bits(size);
+
+		Window {
+			buffer: Vec::new(),
+			size:   size,
+
+			offset: 0,
+			cursor: 0,
+
+			bits:  bits,
+			head:  vec![0; 1 << bits],
+			chain: vec![0; size as usize],
+
+			depth: depth,
+			nice:  cmp::min(nice, MATCH_MAXIMUM_LENGTH),
+		}
+	}
+
+	/// Returns the amount of memory in bytes the match finder needs for the
+	/// given size, the buffer holding the data comes on top of it.
+	pub fn memory_usage(size: u32) -> u64 {
+		4 * ((1u64 << bits(size)) + size as u64)
+	}
+
+	/// Gets the size.
+	pub fn size(&self) -> u32 {
+		self.size
+	}
+
+	/// Gets the nice length.
+	pub fn nice(&self) -> usize {
+		self.nice
+	}
+
+	/// Sets the amount of candidates followed and the length of a match good
+	/// enough to stop searching.
+	pub fn set_search(&mut self, depth: usize, nice: usize) {
+		self.depth = cmp::max(depth, 1);
+		self.nice  = nice.clamp(MATCH_MINIMUM_LENGTH, MATCH_MAXIMUM_LENGTH);
+	}
+
+	/// Gets the total position of the cursor.
+	pub fn total(&self) -> u64 {
+		self.offset + self.cursor as u64
+	}
+
+	/// Gets the amount of bytes available after the cursor.
+	pub fn available(&self) -> usize {
+		self.buffer.len() - self.cursor
+	}
+
+	/// Gets the amount of bytes available before the cursor.
+	pub fn history(&self) -> u64 {
+		cmp::min(self.total(), self.size as u64)
+	}
+
+	/// Appends data to the window, dropping history that went out of the
+	/// dictionary.
+	pub fn fill(&mut self, data: &[u8]) {
+		let keep = self.size as usize;
+
+		if self.cursor > keep * 2 {
+			let drop = self.cursor - keep;
+
+			self.buffer.drain(.. drop);
+			self.offset += drop as u64;
+			self.cursor -= drop;
+		}
+
+		self.buffer.extend_from_slice(data);
+	}
+
+	/// Drops the last `amount` bytes appended, which must not have been
+	/// encoded yet.
+	pub fn unfill(&mut self, amount: usize) {
+		let length = self.buffer.len() - cmp::min(amount, self.available());
+		self.buffer.truncate(length);
+	}
+
+	/// Gets the byte at the given offset from the cursor.
+	pub fn current(&self, offset: usize) -> u8 {
+		self.buffer[self.cursor + offset]
+	}
+
+	/// Gets the byte at the given distance before the cursor.
+	pub fn byte(&self, distance: u32) -> u8 {
+		self.buffer[self.cursor - distance as usize]
+	}
+
+	/// Gets the length of the match at the given distance, up to `limit`.
+	pub fn length(&self, distance: u32, limit: usize) -> usize {
+		self.length_at(0, distance, limit)
+	}
+
+	fn length_at(&self, offset: usize, distance: u32, limit: usize) -> usize {
+		let current = self.cursor + offset;
+		let limit   = cmp::min(limit, self.buffer.len() - current);
+		let history = cmp::min(self.total() + offset as u64, self.size as u64);
+
+		if distance == 0 || distance as u64 > history {
+			return 0;
+		}
+
+		let source = &self.buffer[current - distance as usize ..];
+		let target = &self.buffer[current ..];
+
+		let mut length = 0;
+
+		while length < limit && source[length] == target[length] {
+			length += 1;
+		}
+
+		length
+	}
+
+	fn hash(&self, position: usize) -> usize {
+		let value = (self.buffer[position] as u32)
+			| (self.buffer[position + 1] as u32) << 8
+			| (self.buffer[position + 2] as u32) << 16;
+
+		(value.wrapping_mul(2654435761) >> (32 - self.bits)) as usize
+	}
+
+	/// Searches the longest match at the given offset from the cursor, returns
+	/// the length and the distance.
+	pub fn search(&self, offset: usize, limit: usize) -> (usize, u32) {
+		let current = self.cursor + offset;
+		let limit   = cmp::min(limit, self.buffer.len() - current);
+
+		if limit < 3 {
+			return (0, 0);
+		}
+
+		let total = (self.offset + current as u64) as u32;
+		let nice  = cmp::min(self.nice, limit);
+
+		let mut best      = (0, 0);
+		let mut candidate = self.head[self.hash(current)];
+		let mut previous  = 0;
+
+		for _ in 0 .. self.depth {
+			let distance = total.wrapping_sub(candidate);
+
+			if distance <= previous || distance > self.size {
+				break;
+			}
+
+			let length = self.length_at(offset, distance, limit);
+
+			if length > best.0 {
+				best = (length, distance);
+
+				if length >= nice {
+					break;
+				}
+			}
+
+			previous  = distance;
+			candidate = self.chain[(candidate % self.size) as usize];
+		}
+
+		best
+	}
+
+	/// Moves the cursor forward, indexing the skipped positions.
+	pub fn skip(&mut self, amount: usize) {
+		for _ in 0 .. amount {
+			if self.cursor + 3 <= self.buffer.len() {
+				let hash  = self.hash(self.cursor);
+				let total = self.total() as u32;
+
+				self.chain[(total % self.size) as usize] = self.head[hash];
+				self.head[hash] = total;
+			}
+
+			self.cursor += 1;
+		}
+	}
+}
+
+// about one head every two dictionary bytes, so chains stay short enough for
+// the search depth to reach the whole dictionary
+fn bits(size: u32) -> u32 {
+	let bits = (32 - size.leading_zeros()).saturating_sub(1);
+	bits.clamp(HASH_BITS_MINIMUM, HASH_BITS_MAXIMUM)
+}
@@ -0,0 +1,540 @@
+use std::io::{self, Write};
+use std::cmp;
+
+use {Error, Properties, properties};
+use consts::{LENGTH_TO_POSITION_STATES, ALIGN_BITS, END_POSITION_MODEL_INDEX};
+use consts::{FULL_DISTANCES, STATES, POSITION_BITS_MAX, MATCH_MINIMUM_LENGTH};
+use consts::{MATCH_MAXIMUM_LENGTH, MINIMUM_DICTIONARY_SIZE};
+use reader::{Probabilities, State};
+use super::{Range, Window, Length, BitTree};
+
+/// Upper bound of the compressed size of a single literal or match.
+const UNIT_MAXIMUM_SIZE: u64 = 48;
+
+/// A LZMA stream writer.
+#[derive(Debug)]
+pub struct Writer<W: Write> {
+	stream:   W,
+	received: u64,
+	preset:   u64,
+
+	properties: Properties,
+	marker:     bool,
+
+	limit:     Option<u64>,
+	exhausted: bool,
+
+	range:  Range,
+	window: Window,
+
+	literal:  Probabilities,
+	position: Probabilities,
+
+	length: Length,
+	repeat: Length,
+
+	slot:  Vec<BitTree>,
+	align: BitTree,
+
+	state: u32,
+	rep:   [u32; 4],
+
+	is_match:     Probabilities,
+	is_rep:       Probabilities,
+	is_rep_g0:    Probabilities,
+	is_rep_g1:    Probabilities,
+	is_rep_g2:    Probabilities,
+	is_rep0_long: Probabilities,
+}
+
+impl<W: Write> Writer<W> {
+	/// Creates a LZMA writer with the given model properties and the given
+	/// stream.
+	///
+	/// The EOS marker is written only if the uncompressed size is unknown.
+	pub fn new(stream: W, properties: Properties) -> Result<Writer<W>, Error> {
+		try!(properties::encode(&properties));
+
+		let dictionary = cmp::max(properties.dictionary, MINIMUM_DICTIONARY_SIZE);
+		let window     = Window::new(dictionary, 32, 64);
+
+		let literal = Probabilities::new(0x300 << (properties.lc + properties.lp));
+
+		Ok(Writer {
+			stream:   stream,
+			received: 0,
+			preset:   0,
+
+			properties: properties,
+			marker:     properties.uncompressed.is_none(),
+
+			limit:     None,
+			exhausted: false,
+
+			range:  Range::new(),
+			window: window,
+
+			literal:  literal,
+			position: Probabilities::new(1 + FULL_DISTANCES - END_POSITION_MODEL_INDEX),
+
+			length: Length::new(),
+			repeat: Length::new(),
+
+			slot:  vec![BitTree::new(6); LENGTH_TO_POSITION_STATES],
+			align: BitTree::new(ALIGN_BITS),
+
+			state: 0,
+			rep:   [0; 4],
+
+			is_match:     Probabilities::new(STATES << POSITION_BITS_MAX),
+			is_rep:       Probabilities::new(STATES),
+			is_rep_g0:    Probabilities::new(STATES),
+			is_rep_g1:    Probabilities::new(STATES),
+			is_rep_g2:    Probabilities::new(STATES),
+			is_rep0_long: Probabilities::new(STATES << POSITION_BITS_MAX),
+		})
+	}
+
+	/// Creates a LZMA writer like `new`, preloading the history with a preset
+	/// dictionary.
+	///
+	/// The stream can only be decoded with the same preset dictionary.
+	pub fn with_preset(stream: W, properties: Properties, dictionary: &[u8]) -> Result<Writer<W>, Error> {
+		let mut writer = try!(Writer::new(stream, properties));
+
+		writer.window.fill(dictionary);
+		writer.window.skip(dictionary.len());
+		writer.preset = dictionary.len() as u64;
+
+		Ok(writer)
+	}
+
+	/// Creates a LZMA stream to the given stream, writing the model
+	/// properties.
+	pub fn to(mut stream: W, properties: Properties) -> Result<Writer<W>, Error> {
+		try!(properties::write(stream.by_ref(), &properties));
+
+		Writer::new(stream, properties)
+	}
+
+	/// Returns the model properties.
+	pub fn properties(&self) -> &Properties {
+		&self.properties
+	}
+
+	/// Sets whether the EOS marker is written.
+	///
+	/// Note that without the marker the decoder must know the uncompressed size
+	/// from elsewhere.
+	pub fn set_marker(&mut self, value: bool) {
+		self.marker = value;
+	}
+
+	/// Sets the maximum size of the compressed data, the encoding stops before
+	/// going over it and any further input is refused.
+	///
+	/// The EOS marker does not fit in the limit, so it should be disabled.
+	pub fn set_limit(&mut self, value: Option<u64>) {
+		self.limit = value;
+	}
+
+	/// Sets how hard the match finder searches, following at most `depth`
+	/// candidates and stopping at matches of `nice` length, 32 and 64 by
+	/// default.
+	///
+	/// Deeper searches and longer nice lengths compress better but slower.
+	pub fn set_search(&mut self, depth: usize, nice: usize) {
+		self.window.set_search(depth, nice);
+	}
+
+	/// Checks if the compressed size limit has been reached.
+	pub fn is_exhausted(&self) -> bool {
+		self.exhausted
+	}
+
+	/// Returns the amount of compressed bytes written so far.
+	pub fn written(&self) -> u64 {
+		self.range.written()
+	}
+
+	/// Returns the amount of uncompressed bytes encoded so far.
+	pub fn encoded(&self) -> u64 {
+		self.window.total() - self.preset
+	}
+
+	/// Returns the inner stream.
+	///
+	/// Note that writing bytes to the raw stream will corrupt the encoding.
+	pub unsafe fn inner(&mut self) -> &mut W {
+		&mut self.stream
+	}
+
+	fn literal(&mut self) -> Result<(), Error> {
+		let total = self.window.total();
+
+		let prev = if total > 0 {
+			self.window.byte(1) as u32
+		}
+		else {
+			0
+		};
+
+		let byte = self.window.current(0) as u32;
+		let pos  = total as u32 & ((1 << self.properties.pb) - 1);
+
+		try!(self.range.probabilistic(self.stream.by_ref(),
+			&mut self.is_match[((self.state << POSITION_BITS_MAX) + pos) as usize], false));
+
+		let lit = ((total as u32 & ((1 << self.properties.lp) - 1)) << self.properties.lc)
+			+ (prev >> (8 - self.properties.lc as u32));
+
+		let probs = &mut self.literal[0x300 * lit as usize ..];
+
+		// the byte with an additional 9th control bit
+		let mut symbol = 1u32;
+		let mut bits   = 8;
+
+		// we have to use the distance
+		if self.state >= 7 {
+			let match_byte = self.window.byte(self.rep[0] + 1) as u32;
+
+			while bits > 0 {
+				bits -= 1;
+
+				let bit       = (byte >> bits) & 1;
+				let match_bit = (match_byte >> bits) & 1;
+
+				try!(self.range.probabilistic(self.stream.by_ref(),
+					&mut probs[(((1 + match_bit) << 8) + symbol) as usize], bit == 1));
+
+				symbol = (symbol << 1) | bit;
+
+				if match_bit != bit {
+					break;
+				}
+			}
+		}
+
+		while bits > 0 {
+			bits -= 1;
+
+			let bit = (byte >> bits) & 1;
+			try!(self.range.probabilistic(self.stream.by_ref(), &mut probs[symbol as usize], bit == 1));
+
+			symbol = (symbol << 1) | bit;
+		}
+
+		self.state = State::Literal(self.state).update();
+		self.window.skip(1);
+
+		Ok(())
+	}
+
+	fn distance(&mut self, distance: u32, length: usize) -> Result<(), Error> {
+		let state = cmp::min(length, LENGTH_TO_POSITION_STATES - 1);
+
+		let slot = if distance < 4 {
+			distance as usize
+		}
+		else {
+			let bits = 31 - distance.leading_zeros();
+			((bits << 1) | ((distance >> (bits - 1)) & 1)) as usize
+		};
+
+		try!(self.slot[state].encode(self.stream.by_ref(), &mut self.range, slot));
+
+		if slot < 4 {
+			return Ok(());
+		}
+
+		let direct  = (slot >> 1) - 1;
+		let base    = (2 | (slot & 1)) << direct;
+		let reduced = distance as usize - base;
+
+		if slot < END_POSITION_MODEL_INDEX {
+			try!(super::bit_tree::reverse(self.stream.by_ref(),
+				&mut self.position[base - slot ..], direct, &mut self.range, reduced));
+		}
+		else {
+			try!(self.range.direct(self.stream.by_ref(), reduced >> ALIGN_BITS, direct - ALIGN_BITS));
+			try!(self.align.reverse(self.stream.by_ref(), &mut self.range, reduced & ((1 << ALIGN_BITS) - 1)));
+		}
+
+		Ok(())
+	}
+
+	fn matched(&mut self, distance: u32, length: usize) -> Result<(), Error> {
+		let pos = self.window.total() as u32 & ((1 << self.properties.pb) - 1);
+
+		try!(self.range.probabilistic(self.stream.by_ref(),
+			&mut self.is_match[((self.state << POSITION_BITS_MAX) + pos) as usize], true));
+		try!(self.range.probabilistic(self.stream.by_ref(),
+			&mut self.is_rep[self.state as usize], false));
+
+		try!(self.length.encode(self.stream.by_ref(), &mut self.range, pos as usize, length - MATCH_MINIMUM_LENGTH));
+		try!(self.distance(distance - 1, length - MATCH_MINIMUM_LENGTH));
+
+		self.rep[3] = self.rep[2];
+		self.rep[2] = self.rep[1];
+		self.rep[1] = self.rep[0];
+		self.rep[0] = distance - 1;
+
+		self.state = State::Match(self.state).update();
+		self.window.skip(length);
+
+		Ok(())
+	}
+
+	fn repeated(&mut self, index: usize, length: usize) -> Result<(), Error> {
+		let pos = self.window.total() as u32 & ((1 << self.properties.pb) - 1);
+
+		try!(self.range.probabilistic(self.stream.by_ref(),
+			&mut self.is_match[((self.state << POSITION_BITS_MAX) + pos) as usize], true));
+		try!(self.range.probabilistic(self.stream.by_ref(),
+			&mut self.is_rep[self.state as usize], true));
+
+		if index == 0 {
+			try!(self.range.probabilistic(self.stream.by_ref(),
+				&mut self.is_rep_g0[self.state as usize], false));
+			try!(self.range.probabilistic(self.stream.by_ref(),
+				&mut self.is_rep0_long[((self.state << POSITION_BITS_MAX) + pos) as usize], length != 1));
+
+			if length == 1 {
+				self.state = State::ShortRepetition(self.state).update();
+				self.window.skip(1);
+
+				return Ok(());
+			}
+		}
+		else {
+			try!(self.range.probabilistic(self.stream.by_ref(),
+				&mut self.is_rep_g0[self.state as usize], true));
+			try!(self.range.probabilistic(self.stream.by_ref(),
+				&mut self.is_rep_g1[self.state as usize], index != 1));
+
+			if index != 1 {
+				try!(self.range.probabilistic(self.stream.by_ref(),
+					&mut self.is_rep_g2[self.state as usize], index != 2));
+			}
+
+			let distance = self.rep[index];
+
+			for i in (1 .. index + 1).rev() {
+				self.rep[i] = self.rep[i - 1];
+			}
+
+			self.rep[0] = distance;
+		}
+
+		try!(self.repeat.encode(self.stream.by_ref(), &mut self.range, pos as usize, length - MATCH_MINIMUM_LENGTH));
+
+		self.state = State::Repetition(self.state).update();
+		self.window.skip(length);
+
+		Ok(())
+	}
+
+	fn marker(&mut self) -> Result<(), Error> {
+		let pos = self.window.total() as u32 & ((1 << self.properties.pb) - 1);
+
+		try!(self.range.probabilistic(self.stream.by_ref(),
+			&mut self.is_match[((self.state << POSITION_BITS_MAX) + pos) as usize], true));
+		try!(self.range.probabilistic(self.stream.by_ref(),
+			&mut self.is_rep[self.state as usize], false));
+
+		try!(self.length.encode(self.stream.by_ref(), &mut self.range, pos as usize, 0));
+		self.distance(0xffffffff, 0)
+	}
+
+	/// Encode one unit and return the encoded amount.
+	fn encode(&mut self) -> Result<usize, Error> {
+		let limit = cmp::min(self.window.available(), MATCH_MAXIMUM_LENGTH);
+		let nice  = self.window.nice();
+
+		if limit == 0 || self.exhausted {
+			return Ok(0);
+		}
+
+		if let Some(size) = self.limit {
+			if self.range.written() + self.range.pending() + UNIT_MAXIMUM_SIZE > size {
+				self.exhausted = true;
+				return Ok(0);
+			}
+		}
+
+		// look for the longest repetition first since they're cheaper
+		let mut rep = (0, 0);
+
+		for (index, &distance) in self.rep.iter().enumerate() {
+			let length = self.window.length(distance + 1, limit);
+
+			if length > rep.0 {
+				rep = (length, index);
+			}
+		}
+
+		if rep.0 >= nice {
+			try!(self.repeated(rep.1, rep.0));
+			return Ok(rep.0);
+		}
+
+		let (length, distance) = self.window.search(0, limit);
+
+		if length >= nice {
+			try!(self.matched(distance, length));
+			return Ok(length);
+		}
+
+		if rep.0 >= MATCH_MINIMUM_LENGTH && (rep.0 + 1 >= length ||
+		   (rep.0 + 2 >= length && distance >= (1 << 9)) ||
+		   (rep.0 + 3 >= length && distance >= (1 << 15)))
+		{
+			try!(self.repeated(rep.1, rep.0));
+			return Ok(rep.0);
+		}
+
+		// short matches far away cost more than the literals
+		if length < 3 || (length == 3 && distance >= (1 << 14)) {
+			if self.window.history() > self.rep[0] as u64 && self.window.current(0) == self.window.byte(self.rep[0] + 1) {
+				try!(self.repeated(0, 1));
+			}
+			else {
+				try!(self.literal());
+			}
+
+			return Ok(1);
+		}
+
+		// lazy matching, emit a literal if the next position has a better match
+		if limit > length {
+			let (next, next_distance) = self.window.search(1, limit - 1);
+
+			if next > length + 1 || (next == length + 1 && next_distance <= distance) ||
+			   (next == length && next_distance < distance >> 7)
+			{
+				try!(self.literal());
+				return Ok(1);
+			}
+		}
+
+		try!(self.matched(distance, length));
+		Ok(length)
+	}
+
+	/// Encodes the buffered data as far as the limit allows, returns whether
+	/// everything has been encoded.
+	pub fn drain(&mut self) -> Result<bool, Error> {
+		while try!(self.encode()) != 0 { }
+
+		Ok(self.window.available() == 0)
+	}
+
+	/// Flushes the range encoder and starts a new one, keeping the state and
+	/// the dictionary, the limit then applies to the new one.
+	///
+	/// Note that restarting might corrupt the encoding.
+	pub unsafe fn restart(&mut self) -> Result<(), Error> {
+		try!(self.range.flush(self.stream.by_ref()));
+
+		self.range     = Range::new();
+		self.exhausted = false;
+
+		Ok(())
+	}
+
+	/// Resets the state, the distances and the probabilities, keeping the
+	/// dictionary.
+	///
+	/// Note that resetting might corrupt the encoding.
+	pub unsafe fn reset_state(&mut self) {
+		self.literal.reset();
+		self.position.reset();
+
+		self.length = Length::new();
+		self.repeat = Length::new();
+
+		self.slot  = vec![BitTree::new(6); LENGTH_TO_POSITION_STATES];
+		self.align = BitTree::new(ALIGN_BITS);
+
+		self.state = 0;
+		self.rep   = [0; 4];
+
+		self.is_match.reset();
+		self.is_rep.reset();
+		self.is_rep_g0.reset();
+		self.is_rep_g1.reset();
+		self.is_rep_g2.reset();
+		self.is_rep0_long.reset();
+	}
+
+	/// Encodes the remaining data and finishes the stream, returning the
+	/// underlying writer.
+	pub fn finish(mut self) -> Result<W, Error> {
+		if let Some(size) = self.properties.uncompressed {
+			if self.received != size {
+				return Err(Error::NeedMoreData);
+			}
+		}
+
+		while try!(self.encode()) != 0 { }
+
+		if self.marker {
+			try!(self.marker());
+		}
+
+		try!(self.range.flush(self.stream.by_ref()));
+		try!(self.stream.flush());
+
+		Ok(self.stream)
+	}
+}
+
+impl<W: Write> Write for Writer<W> {
+	fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+		if let Some(size) = self.properties.uncompressed {
+			if self.received + buf.len() as u64 > size {
+				return Err(io::Error::other(Error::HasMoreData));
+			}
+		}
+
+		if self.exhausted {
+			return Ok(0);
+		}
+
+		self.window.fill(buf);
+		self.received += buf.len() as u64;
+
+		// keep enough data around for the longest match and the lazy lookup
+		while self.window.available() > MATCH_MAXIMUM_LENGTH + 1 {
+			match self.encode() {
+				Err(Error::IO(err)) =>
+					return Err(err),
+
+				Err(err) =>
+					return Err(io::Error::other(err)),
+
+				Ok(0) =>
+					break,
+
+				Ok(_) =>
+					()
+			}
+		}
+
+		// the part of the buffer the limit left out is given back to the caller
+		if self.exhausted {
+			let refused = cmp::min(self.window.available(), buf.len());
+
+			self.window.unfill(refused);
+			self.received -= refused as u64;
+
+			return Ok(buf.len() - refused);
+		}
+
+		Ok(buf.len())
+	}
+
+	fn flush(&mut self) -> io::Result<()> {
+		self.stream.flush()
+	}
+}
@@ -0,0 +1,129 @@
+use std::fmt;
+
+use check::{Crc32, Crc64, Sha256};
+
+/// The integrity check of a stream.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum Check {
+	/// No check.
+	None,
+
+	/// CRC32 of the uncompressed data.
+	Crc32,
+
+	/// CRC64 of the uncompressed data.
+	Crc64,
+
+	/// SHA-256 of the uncompressed data.
+	Sha256,
+
+	/// A reserved check, it can be skipped but not verified.
+	Unknown(u8),
+}
+
+impl Check {
+	/// Gets the check from its identifier.
+	pub fn from_id(id: u8) -> Check {
+		match id {
+			0x00 => Check::None,
+			0x01 => Check::Crc32,
+			0x04 => Check::Crc64,
+			0x0a => Check::Sha256,
+			n    => Check::Unknown(n),
+		}
+	}
+
+	/// Gets the identifier of the check.
+	pub fn id(&self) -> u8 {
+		match *self {
+			Check::None       => 0x00,
+			Check::Crc32      => 0x01,
+			Check::Crc64      => 0x04,
+			Check::Sha256     => 0x0a,
+			Check::Unknown(n) => n,
+		}
+	}
+
+	/// Gets the size of the check field.
+	pub fn size(&self) -> usize {
+		match self.id() {
+			0x00 =>
+				0,
+
+			n =>
+				4 << ((n - 1) / 3)
+		}
+	}
+}
+
+impl fmt::Display for Check {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			&Check::None       => f.write_str("None"),
+			&Check::Crc32      => f.write_str("CRC32"),
+			&Check::Crc64      => f.write_str("CRC64"),
+			&Check::Sha256     => f.write_str("SHA-256"),
+			&Check::Unknown(n) => write!(f, "Unknown-{}", n),
+		}
+	}
+}
+
+/// A running integrity check.
+#[derive(Clone, Debug)]
+pub enum Hasher {
+	/// Nothing to compute.
+	None,
+
+	/// A running CRC32.
+	Crc32(Crc32),
+
+	/// A running CRC64.
+	Crc64(Crc64),
+
+	/// A running SHA-256.
+	Sha256(Sha256),
+}
+
+impl Hasher {
+	/// Creates a new hasher for the given check, unknown checks are not
+	/// computed.
+	pub fn new(check: Check) -> Hasher {
+		match check {
+			Check::Crc32  => Hasher::Crc32(Crc32::new()),
+			Check::Crc64  => Hasher::Crc64(Crc64::new()),
+			Check::Sha256 => Hasher::Sha256(Sha256::new()),
+			_             => Hasher::None,
+		}
+	}
+
+	/// Updates the check with the given data.
+	pub fn update(&mut self, data: &[u8]) {
+		match *self {
+			Hasher::None               => (),
+			Hasher::Crc32(ref mut h)   => h.update(data),
+			Hasher::Crc64(ref mut h)   => h.update(data),
+			Hasher::Sha256(ref mut h)  => h.update(data),
+		}
+	}
+
+	/// Gets the check field, `None` if it cannot be computed.
+	pub fn value(&self) -> Option<Vec<u8>> {
+		match *self {
+			Hasher::None =>
+				None,
+
+			Hasher::Crc32(ref h) => {
+				let v = h.value();
+				Some((0 .. 4).map(|i| (v >> (i * 8)) as u8).collect())
+			}
+
+			Hasher::Crc64(ref h) => {
+				let v = h.value();
+				Some((0 .. 8).map(|i| (v >> (i * 8)) as u8).collect())
+			}
+
+			Hasher::Sha256(ref h) =>
+				Some(h.value().to_vec()),
+		}
+	}
+}
@@ -0,0 +1,219 @@
+use std::io::{self, Read};
+use std::cmp;
+use byteorder::ReadBytesExt;
+
+use {Error, lzma2, filter};
+use filter::{X86, Delta};
+use reader::Counter;
+use super::{Flags, Filter, Block, Record, Check, Hasher, HEADER_MAGIC};
+
+/// The LZMA2 decoder of a block.
+#[derive(Debug)]
+pub enum Lzma2<R: Read> {
+	Serial(Box<lzma2::Reader<Counter<R>>>),
+	Parallel(Box<lzma2::Parallel<Counter<R>>>),
+}
+
+impl<R: Read> Lzma2<R> {
+	/// Returns the inner stream.
+	pub fn get_ref(&self) -> &Counter<R> {
+		match *self {
+			Lzma2::Serial(ref decoder)   => decoder.get_ref(),
+			Lzma2::Parallel(ref decoder) => decoder.get_ref(),
+		}
+	}
+
+	/// Unwraps the decoder, returning the inner stream.
+	pub fn into_inner(self) -> Counter<R> {
+		match self {
+			Lzma2::Serial(decoder)   => decoder.into_inner(),
+			Lzma2::Parallel(decoder) => decoder.into_inner(),
+		}
+	}
+}
+
+impl<R: Read> Read for Lzma2<R> {
+	fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+		match *self {
+			Lzma2::Serial(ref mut decoder)   => decoder.read(buf),
+			Lzma2::Parallel(ref mut decoder) => decoder.read(buf),
+		}
+	}
+}
+
+/// A block decoder with its filter chain.
+#[derive(Debug)]
+pub enum Decoder<R: Read> {
+	None(Lzma2<R>),
+	X86(filter::Reader<Lzma2<R>, X86>),
+	Delta(Box<filter::Reader<Lzma2<R>, Delta>>),
+}
+
+impl<R: Read> Decoder<R> {
+	/// Creates a decoder for the block compressed data in the stream.
+	pub fn new(stream: Counter<R>, block: &Block, memlimit: u64) -> Result<Decoder<R>, Error> {
+		Decoder::with_threads(stream, block, memlimit, 1)
+	}
+
+	/// Creates a decoder like `new`, the LZMA2 data is decoded on the given
+	/// amount of threads when there's more than one.
+	pub fn with_threads(stream: Counter<R>, block: &Block, memlimit: u64, threads: usize) -> Result<Decoder<R>, Error> {
+		let dictionary = try!(dictionary(block));
+
+		let decoder = if threads > 1 {
+			Lzma2::Parallel(Box::new(try!(lzma2::Parallel::with_memlimit(stream, dictionary, threads, memlimit))))
+		}
+		else {
+			Lzma2::Serial(Box::new(try!(lzma2::Reader::with_memlimit(stream, dictionary, memlimit))))
+		};
+
+		Ok(match block.filters[.. block.filters.len() - 1] {
+			[] =>
+				Decoder::None(decoder),
+
+			[Filter::X86] =>
+				Decoder::X86(filter::Reader::new(decoder, X86::decoder())),
+
+			[Filter::Delta(distance)] =>
+				Decoder::Delta(Box::new(filter::Reader::new(decoder, Delta::decoder(distance)))),
+
+			_ =>
+				return Err(Error::Unsupported)
+		})
+	}
+
+	/// Returns the inner stream.
+	pub fn get_ref(&self) -> &Counter<R> {
+		match *self {
+			Decoder::None(ref decoder)  => decoder.get_ref(),
+			Decoder::X86(ref decoder)   => decoder.get_ref().get_ref(),
+			Decoder::Delta(ref decoder) => decoder.get_ref().get_ref(),
+		}
+	}
+
+	/// Unwraps the decoder, returning the inner stream.
+	pub fn into_inner(self) -> Counter<R> {
+		match self {
+			Decoder::None(decoder)  => decoder.into_inner(),
+			Decoder::X86(decoder)   => decoder.into_inner().into_inner(),
+			Decoder::Delta(decoder) => decoder.into_inner().into_inner(),
+		}
+	}
+}
+
+impl<R: Read> Read for Decoder<R> {
+	fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+		match *self {
+			Decoder::None(ref mut decoder)  => decoder.read(buf),
+			Decoder::X86(ref mut decoder)   => decoder.read(buf),
+			Decoder::Delta(ref mut decoder) => decoder.read(buf),
+		}
+	}
+}
+
+// the dictionary size of the block, no bigger than the block itself
+fn dictionary(block: &Block) -> Result<u32, Error> {
+	let dictionary = match block.filters.last() {
+		Some(&Filter::Lzma2(dictionary)) =>
+			dictionary,
+
+		_ =>
+			return Err(Error::Unsupported)
+	};
+
+	Ok(match block.uncompressed {
+		Some(size) =>
+			cmp::min(dictionary as u64, cmp::max(size, 1)) as u32,
+
+		None =>
+			dictionary
+	})
+}
+
+/// Checks that a decoder can be created for the block within the memory
+/// limit, before the stream is handed over to it.
+pub fn check(block: &Block, memlimit: u64) -> Result<(), Error> {
+	let dictionary = try!(dictionary(block));
+
+	match block.filters[.. block.filters.len() - 1] {
+		[] | [Filter::X86] | [Filter::Delta(_)] =>
+			(),
+
+		_ =>
+			return Err(Error::Unsupported)
+	}
+
+	let needed = lzma2::decoder_memory_usage(dictionary);
+
+	if needed > memlimit {
+		return Err(Error::MemoryLimit { needed: needed, limit: memlimit });
+	}
+
+	Ok(())
+}
+
+/// Verifies what follows the compressed data of a block, `start` being the
+/// offset of the compressed data in the stream, and returns its index record.
+pub fn finish<R: Read>(stream: &mut Counter<R>, block: &Block, start: u64, decoded: u64, hasher: &Hasher, check: Check) -> Result<Record, Error> {
+	let compressed = stream.count() - start;
+
+	if block.compressed.is_some_and(|size| size != compressed) ||
+	   block.uncompressed.is_some_and(|size| size != decoded)
+	{
+		return Err(Error::Corrupted);
+	}
+
+	for _ in 0 .. (4 - compressed % 4) % 4 {
+		if try!(stream.read_u8()) != 0 {
+			return Err(Error::Corrupted);
+		}
+	}
+
+	let mut value = vec![0u8; check.size()];
+	try!(stream.read_exact(&mut value));
+
+	if let Some(expected) = hasher.value() {
+		if expected != value {
+			return Err(Error::Checksum);
+		}
+	}
+
+	Ok(Record {
+		unpadded:     block.size + compressed + value.len() as u64,
+		uncompressed: decoded,
+	})
+}
+
+/// Skips the stream padding after a stream footer, returning the flags of the
+/// next stream if there's one.
+pub fn next_stream<R: Read>(stream: &mut Counter<R>) -> Result<Option<Flags>, Error> {
+	loop {
+		let mut buffer = [0u8; 4];
+		let mut length = 0;
+
+		while length < 4 {
+			match try!(stream.read(&mut buffer[length ..])) {
+				0 => break,
+				n => length += n,
+			}
+		}
+
+		if length == 0 {
+			return Ok(None);
+		}
+
+		if length < 4 {
+			return Err(Error::Corrupted);
+		}
+
+		if buffer == [0, 0, 0, 0] {
+			continue;
+		}
+
+		if buffer[..] != HEADER_MAGIC[.. 4] {
+			return Err(Error::Corrupted);
+		}
+
+		return Ok(Some(try!(Flags::read_header((&buffer[..]).chain(stream.by_ref())))));
+	}
+}
@@ -0,0 +1,277 @@
+use std::io::{Read, Write};
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+
+use {Error, lzma2};
+use check::crc32;
+use super::{Check, HEADER_MAGIC, FOOTER_MAGIC, read_vli, write_vli};
+
+/// The flags of a stream, repeated in its header and footer.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub struct Flags {
+	/// The integrity check of the blocks.
+	pub check: Check,
+}
+
+impl Flags {
+	fn decode(bytes: &[u8]) -> Result<Flags, Error> {
+		if bytes[0] != 0 || bytes[1] & 0xf0 != 0 {
+			return Err(Error::Unsupported);
+		}
+
+		Ok(Flags {
+			check: Check::from_id(bytes[1]),
+		})
+	}
+
+	fn encode(&self) -> [u8; 2] {
+		[0, self.check.id()]
+	}
+
+	/// Reads the stream header, magic bytes included.
+	pub fn read_header<T: Read>(mut stream: T) -> Result<Flags, Error> {
+		let mut buffer = [0u8; 12];
+		try!(stream.read_exact(&mut buffer));
+
+		if buffer[.. 6] != HEADER_MAGIC {
+			return Err(Error::InvalidHeader);
+		}
+
+		if crc32(&buffer[6 .. 8]) != try!((&buffer[8 ..]).read_u32::<LittleEndian>()) {
+			return Err(Error::Checksum);
+		}
+
+		Flags::decode(&buffer[6 .. 8])
+	}
+
+	/// Reads the stream footer, returning the flags and the size of the index.
+	pub fn read_footer<T: Read>(mut stream: T) -> Result<(Flags, u64), Error> {
+		let mut buffer = [0u8; 12];
+		try!(stream.read_exact(&mut buffer));
+
+		if buffer[10 ..] != FOOTER_MAGIC {
+			return Err(Error::InvalidHeader);
+		}
+
+		if crc32(&buffer[4 .. 10]) != try!((&buffer[.. 4]).read_u32::<LittleEndian>()) {
+			return Err(Error::Checksum);
+		}
+
+		let backward = try!((&buffer[4 .. 8]).read_u32::<LittleEndian>()) as u64;
+
+		Ok((try!(Flags::decode(&buffer[8 .. 10])), (backward + 1) * 4))
+	}
+
+	/// Writes the stream header.
+	pub fn write_header<T: Write>(&self, mut stream: T) -> Result<(), Error> {
+		let flags = self.encode();
+
+		try!(stream.write_all(&HEADER_MAGIC));
+		try!(stream.write_all(&flags));
+		try!(stream.write_u32::<LittleEndian>(crc32(&flags)));
+
+		Ok(())
+	}
+
+	/// Writes the stream footer for an index of the given size.
+	pub fn write_footer<T: Write>(&self, mut stream: T, index: u64) -> Result<(), Error> {
+		let mut buffer = Vec::with_capacity(6);
+		try!(buffer.write_u32::<LittleEndian>((index / 4 - 1) as u32));
+		buffer.extend_from_slice(&self.encode());
+
+		try!(stream.write_u32::<LittleEndian>(crc32(&buffer)));
+		try!(stream.write_all(&buffer));
+		try!(stream.write_all(&FOOTER_MAGIC));
+
+		Ok(())
+	}
+}
+
+/// A filter in the block chain.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub enum Filter {
+	/// LZMA2 with the given dictionary size.
+	Lzma2(u32),
+
+	/// x86 BCJ.
+	X86,
+
+	/// Delta with the given distance.
+	Delta(usize),
+
+	/// Any other filter, with its identifier and properties.
+	Unknown(u64, Vec<u8>),
+}
+
+impl Filter {
+	fn read<T: Read>(mut stream: T) -> Result<Filter, Error> {
+		let id   = try!(read_vli(stream.by_ref()));
+		let size = try!(read_vli(stream.by_ref()));
+
+		if size > 1024 {
+			return Err(Error::Corrupted);
+		}
+
+		let mut properties = vec![0u8; size as usize];
+		try!(stream.read_exact(&mut properties));
+
+		Ok(match (id, properties.len()) {
+			(0x21, 1) =>
+				Filter::Lzma2(try!(lzma2::dictionary(properties[0]))),
+
+			(0x04, 0) =>
+				Filter::X86,
+
+			// a start offset of zero is the same as none
+			(0x04, 4) if properties == [0, 0, 0, 0] =>
+				Filter::X86,
+
+			(0x03, 1) =>
+				Filter::Delta(properties[0] as usize + 1),
+
+			_ =>
+				Filter::Unknown(id, properties)
+		})
+	}
+
+	fn write(&self, buffer: &mut Vec<u8>) -> Result<(), Error> {
+		match *self {
+			Filter::Lzma2(dictionary) => {
+				write_vli(0x21, buffer);
+				write_vli(1, buffer);
+				buffer.push(lzma2::dictionary_byte(dictionary));
+			}
+
+			Filter::X86 => {
+				write_vli(0x04, buffer);
+				write_vli(0, buffer);
+			}
+
+			Filter::Delta(distance) => {
+				if distance == 0 || distance > 256 {
+					return Err(Error::InvalidProperties);
+				}
+
+				write_vli(0x03, buffer);
+				write_vli(1, buffer);
+				buffer.push((distance - 1) as u8);
+			}
+
+			Filter::Unknown(id, ref properties) => {
+				write_vli(id, buffer);
+				write_vli(properties.len() as u64, buffer);
+				buffer.extend_from_slice(properties);
+			}
+		}
+
+		Ok(())
+	}
+}
+
+/// A block header.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct Block {
+	/// The size of the header.
+	pub size: u64,
+
+	/// The size of the compressed data, if stored.
+	pub compressed: Option<u64>,
+
+	/// The size of the uncompressed data, if stored.
+	pub uncompressed: Option<u64>,
+
+	/// The filter chain, in encoding order.
+	pub filters: Vec<Filter>,
+}
+
+impl Block {
+	/// Reads the block header after its first byte, which is the encoded size.
+	pub fn read<T: Read>(mut stream: T, first: u8) -> Result<Block, Error> {
+		let     size   = (first as usize + 1) * 4;
+		let mut buffer = vec![0u8; size];
+
+		buffer[0] = first;
+		try!(stream.read_exact(&mut buffer[1 ..]));
+
+		if crc32(&buffer[.. size - 4]) != try!((&buffer[size - 4 ..]).read_u32::<LittleEndian>()) {
+			return Err(Error::Checksum);
+		}
+
+		let flags = buffer[1];
+
+		if flags & 0x3c != 0 {
+			return Err(Error::Unsupported);
+		}
+
+		let mut fields = &buffer[2 .. size - 4];
+
+		let compressed = if flags & 0x40 != 0 {
+			Some(try!(read_vli(&mut fields)))
+		}
+		else {
+			None
+		};
+
+		let uncompressed = if flags & 0x80 != 0 {
+			Some(try!(read_vli(&mut fields)))
+		}
+		else {
+			None
+		};
+
+		let mut filters = Vec::new();
+
+		for _ in 0 .. (flags & 0x03) + 1 {
+			filters.push(try!(Filter::read(&mut fields)));
+		}
+
+		// the rest is padding
+		if fields.iter().any(|&b| b != 0) {
+			return Err(Error::Corrupted);
+		}
+
+		Ok(Block {
+			size: size as u64,
+
+			compressed:   compressed,
+			uncompressed: uncompressed,
+
+			filters: filters,
+		})
+	}
+
+	/// Encodes the block header, the size is updated to match.
+	pub fn encode(&mut self) -> Result<Vec<u8>, Error> {
+		if self.filters.is_empty() || self.filters.len() > 4 {
+			return Err(Error::InvalidProperties);
+		}
+
+		let mut buffer = vec![0u8, (self.filters.len() - 1) as u8];
+
+		if let Some(size) = self.compressed {
+			buffer[1] |= 0x40;
+			write_vli(size, &mut buffer);
+		}
+
+		if let Some(size) = self.uncompressed {
+			buffer[1] |= 0x80;
+			write_vli(size, &mut buffer);
+		}
+
+		for filter in &self.filters {
+			try!(filter.write(&mut buffer));
+		}
+
+		while buffer.len() % 4 != 0 {
+			buffer.push(0);
+		}
+
+		buffer[0] = (buffer.len() / 4) as u8;
+
+		let crc = crc32(&buffer);
+		try!(buffer.write_u32::<LittleEndian>(crc));
+
+		self.size = buffer.len() as u64;
+
+		Ok(buffer)
+	}
+}
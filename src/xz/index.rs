@@ -0,0 +1,209 @@
+use std::io::{self, Read, Write, Seek, SeekFrom};
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+
+use Error;
+use check::crc32;
+use super::{Flags, read_vli, write_vli};
+
+/// A record of the index.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub struct Record {
+	/// The size of the block header, compressed data and check.
+	pub unpadded: u64,
+
+	/// The size of the uncompressed data.
+	pub uncompressed: u64,
+}
+
+/// The index of a stream.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct Index {
+	/// The records of the blocks.
+	pub records: Vec<Record>,
+
+	/// The size of the encoded index.
+	pub size: u64,
+}
+
+// keeps a copy of everything read for the CRC
+struct Recorder<'a, R: Read + 'a> {
+	stream: &'a mut R,
+	buffer: Vec<u8>,
+}
+
+impl<'a, R: Read> Read for Recorder<'a, R> {
+	fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+		let read = try!(self.stream.read(buf));
+		self.buffer.extend_from_slice(&buf[.. read]);
+
+		Ok(read)
+	}
+}
+
+impl Index {
+	/// Reads the index after its indicator byte.
+	pub fn read<T: Read>(stream: &mut T) -> Result<Index, Error> {
+		let mut recorder = Recorder {
+			stream: stream,
+			buffer: vec![0x00],
+		};
+
+		let     count   = try!(read_vli(&mut recorder));
+		let mut records = Vec::new();
+
+		for _ in 0 .. count {
+			let unpadded     = try!(read_vli(&mut recorder));
+			let uncompressed = try!(read_vli(&mut recorder));
+
+			if unpadded == 0 {
+				return Err(Error::Corrupted);
+			}
+
+			records.push(Record {
+				unpadded:     unpadded,
+				uncompressed: uncompressed,
+			});
+		}
+
+		while !recorder.buffer.len().is_multiple_of(4) {
+			if try!(recorder.read_u8()) != 0 {
+				return Err(Error::Corrupted);
+			}
+		}
+
+		let crc = crc32(&recorder.buffer);
+
+		if crc != try!(recorder.stream.read_u32::<LittleEndian>()) {
+			return Err(Error::Checksum);
+		}
+
+		Ok(Index {
+			records: records,
+			size:    recorder.buffer.len() as u64 + 4,
+		})
+	}
+
+	/// Encodes the index, indicator included.
+	pub fn encode(records: &[Record]) -> Vec<u8> {
+		let mut buffer = vec![0x00];
+		write_vli(records.len() as u64, &mut buffer);
+
+		for record in records {
+			write_vli(record.unpadded, &mut buffer);
+			write_vli(record.uncompressed, &mut buffer);
+		}
+
+		while buffer.len() % 4 != 0 {
+			buffer.push(0);
+		}
+
+		let crc = crc32(&buffer);
+		buffer.write_u32::<LittleEndian>(crc).unwrap();
+
+		buffer
+	}
+
+	/// Writes the index, returning its size.
+	pub fn write<T: Write>(mut stream: T, records: &[Record]) -> Result<u64, Error> {
+		let buffer = Index::encode(records);
+		try!(stream.write_all(&buffer));
+
+		Ok(buffer.len() as u64)
+	}
+}
+
+// a stream found from its footer
+#[derive(Debug)]
+pub(crate) struct Located {
+	pub offset:  u64,
+	pub size:    u64,
+	pub padding: u64,
+	pub flags:   Flags,
+	pub index:   Index,
+}
+
+// reads the indexes of all the streams backwards from the end, the stream is
+// left where it was
+pub(crate) fn locate<R: Read + Seek>(stream: &mut R) -> Result<Vec<Located>, Error> {
+	let     start   = try!(stream.stream_position());
+	let mut end     = try!(stream.seek(SeekFrom::End(0)));
+	let mut located = Vec::new();
+	let mut padding = 0;
+
+	while end > start {
+		if end - start < 4 {
+			return Err(Error::Corrupted);
+		}
+
+		let mut bytes = [0u8; 4];
+		try!(stream.seek(SeekFrom::Start(end - 4)));
+		try!(stream.read_exact(&mut bytes));
+
+		if bytes == [0, 0, 0, 0] {
+			end     -= 4;
+			padding += 4;
+
+			continue;
+		}
+
+		if end - start < 24 {
+			return Err(Error::Corrupted);
+		}
+
+		try!(stream.seek(SeekFrom::Start(end - 12)));
+		let (flags, size) = try!(Flags::read_footer(stream.by_ref()));
+
+		let offset = match (end - 12).checked_sub(size) {
+			Some(offset) if offset >= start + 12 =>
+				offset,
+
+			_ =>
+				return Err(Error::Corrupted)
+		};
+
+		try!(stream.seek(SeekFrom::Start(offset)));
+
+		if try!(stream.read_u8()) != 0x00 {
+			return Err(Error::Corrupted);
+		}
+
+		let index = try!(Index::read(stream));
+
+		if index.size != size {
+			return Err(Error::Corrupted);
+		}
+
+		let blocks = index.records.iter().try_fold(0u64, |total, record|
+			total.checked_add((record.unpadded + 3) & !3));
+
+		let header = match blocks.and_then(|blocks| blocks.checked_add(12)).and_then(|size| offset.checked_sub(size)) {
+			Some(header) if header >= start =>
+				header,
+
+			_ =>
+				return Err(Error::Corrupted)
+		};
+
+		try!(stream.seek(SeekFrom::Start(header)));
+
+		if try!(Flags::read_header(stream.by_ref())) != flags {
+			return Err(Error::Corrupted);
+		}
+
+		located.push(Located {
+			offset:  header,
+			size:    end - header,
+			padding: padding,
+			flags:   flags,
+			index:   index,
+		});
+
+		end     = header;
+		padding = 0;
+	}
+
+	try!(stream.seek(SeekFrom::Start(start)));
+	located.reverse();
+
+	Ok(located)
+}
@@ -0,0 +1,85 @@
+mod check;
+pub use self::check::{Check, Hasher};
+
+mod header;
+pub use self::header::{Flags, Filter, Block};
+
+mod index;
+pub use self::index::{Record, Index};
+pub(crate) use self::index::locate;
+
+pub(crate) mod decoder;
+
+mod reader;
+pub use self::reader::Reader;
+
+mod parallel;
+pub use self::parallel::Parallel;
+
+mod writer;
+pub use self::writer::Writer;
+
+use std::io::{Read, Write, BufReader, BufWriter};
+use std::fs::File;
+use std::path::Path;
+use byteorder::ReadBytesExt;
+
+use {Error, Properties};
+
+/// The magic bytes at the start of a stream.
+pub const HEADER_MAGIC: [u8; 6] = [0xfd, b'7', b'z', b'X', b'Z', 0x00];
+
+/// The magic bytes at the end of a stream.
+pub const FOOTER_MAGIC: [u8; 2] = [b'Y', b'Z'];
+
+/// Reads a variable length integer.
+pub fn read_vli<T: Read>(mut stream: T) -> Result<u64, Error> {
+	let mut value = 0u64;
+
+	for i in 0 .. 9 {
+		let byte = try!(stream.read_u8());
+		value |= ((byte & 0x7f) as u64) << (i * 7);
+
+		if byte & 0x80 == 0 {
+			// the encoding must be the shortest possible
+			if byte == 0 && i != 0 {
+				return Err(Error::Corrupted);
+			}
+
+			return Ok(value);
+		}
+	}
+
+	Err(Error::Corrupted)
+}
+
+/// Writes a variable length integer.
+pub fn write_vli(mut value: u64, buffer: &mut Vec<u8>) {
+	while value >= 0x80 {
+		buffer.push(value as u8 | 0x80);
+		value >>= 7;
+	}
+
+	buffer.push(value as u8);
+}
+
+/// Open a file as a xz stream.
+pub fn open<T: AsRef<Path>>(path: T) -> Result<Reader<BufReader<File>>, Error> {
+	read(BufReader::new(try!(File::open(path))))
+}
+
+/// Create a xz stream from another stream.
+pub fn read<T: Read>(stream: T) -> Result<Reader<T>, Error> {
+	Reader::new(stream)
+}
+
+/// Create a file as a xz stream, the blocks are encoded on one thread.
+pub fn create<T: AsRef<Path>>(path: T, properties: Properties) -> Result<Writer<BufWriter<File>>, Error> {
+	write(BufWriter::new(try!(File::create(path))), properties)
+}
+
+/// Create a xz stream to another stream, the blocks are encoded on one
+/// thread.
+pub fn write<T: Write>(stream: T, properties: Properties) -> Result<Writer<T>, Error> {
+	Writer::new(stream, properties, 1)
+}
@@ -0,0 +1,374 @@
+use std::io::{self, Read, Seek, Cursor};
+use std::collections::VecDeque;
+use std::sync::mpsc;
+use byteorder::ReadBytesExt;
+
+use Error;
+use error::{self, Sticky};
+use pool::Pool;
+use reader::Counter;
+use super::{Flags, Block, Record, Index, Check, Hasher, index};
+use super::decoder::{self, Decoder};
+
+// the biggest compressed block buffered for the workers
+const BLOCK_MAXIMUM: u64 = 1 << 23;
+
+// the size of the pieces the workers send their output in
+const PIECE_SIZE: usize = 1 << 20;
+
+// part of a block decoded by a worker, the last one is its index record
+#[derive(Debug)]
+enum Piece {
+	Data(Vec<u8>),
+	End(Record),
+}
+
+type Decoded = Result<Piece, Error>;
+
+#[derive(Debug)]
+enum Pending {
+	// a block sent to the workers
+	Block(mpsc::Receiver<Decoded>),
+
+	// a block of unknown size or too big to be buffered, the stream is right
+	// after its header
+	Inline(Block, u64),
+
+	// the end of a stream
+	Index(Index),
+}
+
+#[derive(Debug)]
+struct Inline<R: Read> {
+	decoder: Decoder<R>,
+	block:   Block,
+	start:   u64,
+	decoded: u64,
+	hasher:  Hasher,
+}
+
+/// A xz stream reader decoding blocks on several threads.
+///
+/// Blocks are decoded in parallel when their compressed size is known, either
+/// from their header or from the index, and at most 8 MiB. Other blocks are
+/// decoded one at a time split at the LZMA2 dictionary resets, like
+/// `lzma2::Parallel` does. The output is returned in order.
+///
+/// At most `threads` blocks are sent to the workers at any time, every one
+/// holding its compressed data and up to 2 MiB of output.
+#[derive(Debug)]
+pub struct Parallel<R: Read> {
+	stream: Option<Counter<R>>,
+	inline: Option<Inline<R>>,
+
+	pool:    Pool,
+	pending: VecDeque<Pending>,
+	current: Option<mpsc::Receiver<Decoded>>,
+	output:  Cursor<Vec<u8>>,
+
+	flags:   Flags,
+	sizes:   VecDeque<Record>,
+	records: Vec<Record>,
+	streams: u64,
+
+	memlimit: u64,
+	finished: bool,
+	failed:   Option<Error>,
+}
+
+impl<R: Read> Parallel<R> {
+	/// Creates a xz reader using the given amount of threads, reading the
+	/// stream header.
+	pub fn new(stream: R, threads: usize) -> Result<Parallel<R>, Error> {
+		Parallel::with_memlimit(stream, threads, u64::MAX)
+	}
+
+	/// Creates a xz reader like `new`, every block fails before allocating
+	/// anything if decoding it needs more memory than the limit.
+	pub fn with_memlimit(stream: R, threads: usize, memlimit: u64) -> Result<Parallel<R>, Error> {
+		Parallel::with_sizes(stream, threads, memlimit, VecDeque::new())
+	}
+
+	fn with_sizes(stream: R, threads: usize, memlimit: u64, sizes: VecDeque<Record>) -> Result<Parallel<R>, Error> {
+		let mut stream = Counter::new(stream, 0);
+		let     flags  = try!(Flags::read_header(&mut stream));
+
+		Ok(Parallel {
+			stream: Some(stream),
+			inline: None,
+
+			pool:    Pool::new(threads),
+			pending: VecDeque::new(),
+			current: None,
+			output:  Cursor::new(Vec::new()),
+
+			flags:   flags,
+			sizes:   sizes,
+			records: Vec::new(),
+			streams: 1,
+
+			memlimit: memlimit,
+			finished: false,
+			failed:   None,
+		})
+	}
+
+	/// Returns the amount of threads.
+	pub fn threads(&self) -> usize {
+		self.pool.threads()
+	}
+
+	/// Returns the amount of streams encountered so far.
+	pub fn streams(&self) -> u64 {
+		self.streams
+	}
+
+	// reads the next block header or index, queueing the block for decoding
+	fn next(&mut self) -> Result<(), Error> {
+		let check  = self.flags.check;
+		let stream = self.stream.as_mut().unwrap();
+		let first  = try!(stream.read_u8());
+
+		if first == 0x00 {
+			let index         = try!(Index::read(stream));
+			let (flags, size) = try!(Flags::read_footer(stream.by_ref()));
+
+			if flags != self.flags || size != index.size {
+				return Err(Error::Corrupted);
+			}
+
+			self.pending.push_back(Pending::Index(index));
+
+			match try!(decoder::next_stream(stream)) {
+				Some(flags) => {
+					self.flags    = flags;
+					self.streams += 1;
+				}
+
+				None =>
+					self.finished = true
+			}
+
+			return Ok(());
+		}
+
+		let mut block = try!(Block::read(stream.by_ref(), first));
+
+		// sizes missing from the header come from the index, they're verified
+		// against the decoded block like the ones in the header
+		if let Some(record) = self.sizes.pop_front() {
+			let compressed = match record.unpadded.checked_sub(block.size + check.size() as u64) {
+				Some(size) if size > 0 =>
+					size,
+
+				_ =>
+					return Err(Error::Corrupted)
+			};
+
+			block.compressed   = block.compressed.or(Some(compressed));
+			block.uncompressed = block.uncompressed.or(Some(record.uncompressed));
+		}
+
+		let compressed = match block.compressed {
+			Some(size) if size <= BLOCK_MAXIMUM =>
+				size,
+
+			_ => {
+				let start = stream.count();
+				self.pending.push_back(Pending::Inline(block, start));
+
+				return Ok(());
+			}
+		};
+
+		let     size = compressed + (4 - compressed % 4) % 4 + check.size() as u64;
+		let mut data = Vec::new();
+		try!(stream.by_ref().take(size).read_to_end(&mut data));
+
+		if (data.len() as u64) != size {
+			return Err(Error::NeedMoreData);
+		}
+
+		let memlimit = self.memlimit;
+		let receiver = self.pool.spawn(move |sender| decode(&block, data, check, memlimit, sender).map(Piece::End));
+
+		self.pending.push_back(Pending::Block(receiver));
+
+		Ok(())
+	}
+
+	// keeps the workers busy, stopping at blocks that have to be decoded here
+	fn fill(&mut self) -> Result<(), Error> {
+		while !self.finished && self.stream.is_some() && self.pending.len() < self.pool.threads() {
+			if let Some(&Pending::Inline(..)) = self.pending.back() {
+				break;
+			}
+
+			try!(self.next());
+		}
+
+		Ok(())
+	}
+
+	// takes the next pending item, returns false when everything was read
+	fn advance(&mut self) -> Result<bool, Error> {
+		try!(self.fill());
+
+		match self.pending.pop_front() {
+			Some(Pending::Block(receiver)) =>
+				self.current = Some(receiver),
+
+			Some(Pending::Inline(block, start)) => {
+				try!(decoder::check(&block, self.memlimit));
+				let stream = self.stream.take().unwrap();
+
+				self.inline = Some(Inline {
+					decoder: try!(Decoder::with_threads(stream, &block, self.memlimit, self.pool.threads())),
+					block:   block,
+					start:   start,
+					decoded: 0,
+					hasher:  Hasher::new(self.flags.check),
+				});
+			}
+
+			Some(Pending::Index(index)) => {
+				if index.records != self.records {
+					return Err(Error::Corrupted);
+				}
+
+				self.records.clear();
+			}
+
+			None =>
+				return Ok(false)
+		}
+
+		Ok(true)
+	}
+
+	// takes the next piece of the block decoded by a worker
+	fn receive(&mut self) -> Result<(), Error> {
+		let piece = match self.current.as_ref().unwrap().recv() {
+			Ok(result) =>
+				try!(result),
+
+			Err(_) =>
+				return Err(Error::IO(io::Error::other("decoding thread died")))
+		};
+
+		match piece {
+			Piece::Data(data) =>
+				self.output = Cursor::new(data),
+
+			Piece::End(record) => {
+				self.records.push(record);
+				self.current = None;
+			}
+		}
+
+		Ok(())
+	}
+
+	// verifies the end of the block decoded on this thread
+	fn end(&mut self) -> Result<(), Error> {
+		let     inline = self.inline.take().unwrap();
+		let mut stream = inline.decoder.into_inner();
+
+		// nothing after the block has been read, so the flags are still its own
+		self.records.push(try!(decoder::finish(&mut stream, &inline.block, inline.start, inline.decoded, &inline.hasher, self.flags.check)));
+		self.stream = Some(stream);
+
+		Ok(())
+	}
+}
+
+impl<R: Read + Seek> Parallel<R> {
+	/// Creates a xz reader like `new`, reading the indexes at the end of the
+	/// stream first so that blocks without sizes in their header are decoded
+	/// in parallel too.
+	pub fn indexed(stream: R, threads: usize) -> Result<Parallel<R>, Error> {
+		Parallel::indexed_with_memlimit(stream, threads, u64::MAX)
+	}
+
+	/// Creates a xz reader like `indexed`, with a memory limit.
+	pub fn indexed_with_memlimit(mut stream: R, threads: usize, memlimit: u64) -> Result<Parallel<R>, Error> {
+		let sizes = try!(records(&mut stream));
+
+		Parallel::with_sizes(stream, threads, memlimit, sizes)
+	}
+}
+
+impl<R: Read> Sticky for Parallel<R> {
+	fn failed(&mut self) -> &mut Option<Error> {
+		&mut self.failed
+	}
+
+	fn decode(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+		loop {
+			let read = try!(self.output.read(buf));
+
+			if read != 0 {
+				return Ok(read);
+			}
+
+			if self.inline.is_some() {
+				let read = try!(self.inline.as_mut().unwrap().decoder.read(buf));
+
+				if read != 0 {
+					let inline = self.inline.as_mut().unwrap();
+					inline.decoded += read as u64;
+					inline.hasher.update(&buf[.. read]);
+
+					return Ok(read);
+				}
+
+				try!(self.end());
+				continue;
+			}
+
+			if self.current.is_some() {
+				try!(self.receive());
+			}
+			else if !try!(self.advance()) {
+				return Ok(0);
+			}
+		}
+	}
+}
+
+impl<R: Read> Read for Parallel<R> {
+	fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+		error::read(self, buf)
+	}
+}
+
+// decodes a block on a worker, sending the output as it comes
+fn decode(block: &Block, data: Vec<u8>, check: Check, memlimit: u64, sender: &mpsc::SyncSender<Decoded>) -> Result<Record, Error> {
+	let mut decoder = try!(Decoder::new(Counter::new(Cursor::new(data), 0), block, memlimit));
+	let mut hasher  = Hasher::new(check);
+	let mut decoded = 0;
+
+	loop {
+		let mut piece = Vec::with_capacity(PIECE_SIZE);
+		try!(decoder.by_ref().take(PIECE_SIZE as u64).read_to_end(&mut piece));
+
+		if piece.is_empty() {
+			break;
+		}
+
+		hasher.update(&piece);
+		decoded += piece.len() as u64;
+
+		if sender.send(Ok(Piece::Data(piece))).is_err() {
+			return Err(Error::IO(io::Error::new(io::ErrorKind::BrokenPipe, "the reader is gone")));
+		}
+	}
+
+	let mut stream = decoder.into_inner();
+	decoder::finish(&mut stream, block, 0, decoded, &hasher, check)
+}
+
+// the records of all the streams, the stream is left where it was
+fn records<R: Read + Seek>(stream: &mut R) -> Result<VecDeque<Record>, Error> {
+	Ok(try!(index::locate(stream)).into_iter().flat_map(|located| located.index.records).collect())
+}
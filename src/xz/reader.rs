@@ -0,0 +1,255 @@
+use std::io::{self, Read};
+use byteorder::ReadBytesExt;
+
+use Error;
+use error::{self, Sticky};
+use reader::Counter;
+use super::{Flags, Block, Record, Index, Hasher};
+use super::decoder::{self, Decoder};
+
+/// A xz stream reader, concatenated streams are read one after the other.
+#[derive(Debug)]
+pub struct Reader<R: Read> {
+	stream:  Option<Counter<R>>,
+	decoder: Option<Decoder<R>>,
+
+	flags:   Flags,
+	block:   Option<Block>,
+	start:   u64,
+	decoded: u64,
+	hasher:  Hasher,
+	records: Vec<Record>,
+	streams: u64,
+	checked: u64,
+
+	memlimit:     u64,
+	concatenated: bool,
+	finished:     bool,
+	failed:       Option<Error>,
+}
+
+impl<R: Read> Reader<R> {
+	/// Creates a xz reader from the given stream, reading the stream header.
+	pub fn new(stream: R) -> Result<Reader<R>, Error> {
+		Reader::with_memlimit(stream, u64::MAX)
+	}
+
+	/// Creates a xz reader like `new`, every block fails before allocating
+	/// anything if decoding it needs more memory than the limit.
+	pub fn with_memlimit(stream: R, memlimit: u64) -> Result<Reader<R>, Error> {
+		let mut stream = Counter::new(stream, 0);
+		let     flags  = try!(Flags::read_header(&mut stream));
+
+		Ok(Reader {
+			stream:  Some(stream),
+			decoder: None,
+
+			flags:   flags,
+			block:   None,
+			start:   0,
+			decoded: 0,
+			hasher:  Hasher::None,
+			records: Vec::new(),
+			streams: 1,
+			checked: 0,
+
+			memlimit:     memlimit,
+			concatenated: true,
+			finished:     false,
+			failed:       None,
+		})
+	}
+
+	/// Returns the flags of the current stream.
+	pub fn flags(&self) -> &Flags {
+		&self.flags
+	}
+
+	/// Returns the header of the block being decoded.
+	pub fn block(&self) -> Option<&Block> {
+		self.block.as_ref()
+	}
+
+	/// Returns the amount of streams encountered so far.
+	pub fn streams(&self) -> u64 {
+		self.streams
+	}
+
+	/// Returns the amount of blocks whose integrity check has been verified.
+	pub fn checked(&self) -> u64 {
+		self.checked
+	}
+
+	/// Returns the amount of bytes read from the underlying stream.
+	pub fn compressed(&self) -> u64 {
+		match self.stream {
+			Some(ref stream) =>
+				stream.count(),
+
+			None => match self.decoder {
+				Some(ref decoder) =>
+					decoder.get_ref().count(),
+
+				// the stream went away with a decoder that failed to start
+				None =>
+					self.start
+			}
+		}
+	}
+
+	/// Sets whether concatenated streams are read, enabled by default.
+	///
+	/// When disabled the reader finishes after the first stream footer,
+	/// leaving anything after it in the stream.
+	pub fn set_concatenated(&mut self, value: bool) {
+		self.concatenated = value;
+	}
+
+	/// Checks if the end of the last stream has been reached.
+	pub fn is_finished(&self) -> bool {
+		self.finished
+	}
+
+	/// Unwraps this `Reader`, returning the underlying reader.
+	///
+	/// Note that unwrapping in the middle of a block loses the decoder state.
+	pub fn into_inner(self) -> R {
+		match self.stream {
+			Some(stream) =>
+				stream.into_inner(),
+
+			None =>
+				self.decoder.unwrap().into_inner().into_inner()
+		}
+	}
+
+	// reads the next block header, returns false when the stream is over
+	fn next(&mut self) -> Result<bool, Error> {
+		let mut stream = self.stream.take().unwrap();
+		let     start  = stream.count();
+
+		let result = match stream.read_u8() {
+			Ok(0x00) =>
+				self.index(&mut stream).map(|_| false),
+
+			Ok(first) =>
+				Block::read(&mut stream, first).map(|block| {
+					self.block = Some(block);
+					true
+				}),
+
+			Err(err) =>
+				Err(Error::from(err))
+		};
+
+		// the stream is only handed over once the decoder can't fail to start
+		let result = result.and_then(|more| {
+			if more {
+				try!(decoder::check(self.block.as_ref().unwrap(), self.memlimit));
+			}
+
+			Ok(more)
+		});
+
+		if let Ok(true) = result {
+			let block = self.block.as_ref().unwrap();
+
+			self.start   = start + block.size;
+			self.decoded = 0;
+			self.hasher  = Hasher::new(self.flags.check);
+			self.decoder = Some(try!(Decoder::new(stream, block, self.memlimit)));
+		}
+		else {
+			self.stream = Some(stream);
+		}
+
+		result
+	}
+
+	// verifies what follows the compressed data of a block
+	fn end(&mut self) -> Result<(), Error> {
+		let mut stream = self.decoder.take().unwrap().into_inner();
+		let     block  = self.block.take().unwrap();
+		let     start  = self.start;
+
+		let record = decoder::finish(&mut stream, &block, start, self.decoded, &self.hasher, self.flags.check);
+		self.stream = Some(stream);
+		self.records.push(try!(record));
+
+		if self.hasher.value().is_some() {
+			self.checked += 1;
+		}
+
+		Ok(())
+	}
+
+	// verifies the index and the footer, then moves to the next stream if any
+	fn index(&mut self, stream: &mut Counter<R>) -> Result<(), Error> {
+		let index = try!(Index::read(stream));
+
+		if index.records != self.records {
+			return Err(Error::Corrupted);
+		}
+
+		let (flags, size) = try!(Flags::read_footer(stream.by_ref()));
+
+		if flags != self.flags || size != index.size {
+			return Err(Error::Corrupted);
+		}
+
+		self.records.clear();
+
+		if !self.concatenated {
+			self.finished = true;
+			return Ok(());
+		}
+
+		match try!(decoder::next_stream(stream)) {
+			Some(flags) => {
+				self.flags    = flags;
+				self.streams += 1;
+			}
+
+			None =>
+				self.finished = true
+		}
+
+		Ok(())
+	}
+}
+
+impl<R: Read> Sticky for Reader<R> {
+	fn failed(&mut self) -> &mut Option<Error> {
+		&mut self.failed
+	}
+
+	fn decode(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+		loop {
+			if self.decoder.is_some() {
+				let read = try!(self.decoder.as_mut().unwrap().read(buf));
+
+				if read != 0 {
+					self.decoded += read as u64;
+					self.hasher.update(&buf[.. read]);
+
+					return Ok(read);
+				}
+
+				try!(self.end());
+				continue;
+			}
+
+			if self.finished {
+				return Ok(0);
+			}
+
+			try!(self.next());
+		}
+	}
+}
+
+impl<R: Read> Read for Reader<R> {
+	fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+		error::read(self, buf)
+	}
+}
@@ -0,0 +1,236 @@
+use std::io::{self, Write};
+use std::collections::VecDeque;
+use std::sync::mpsc;
+use std::cmp;
+use std::mem;
+
+use {Error, Properties, lzma2};
+use pool::Pool;
+use super::{Flags, Filter, Block, Record, Index, Check, Hasher};
+
+// a block encoded by a worker, with its index record
+type Encoded = Result<(Record, Vec<u8>), Error>;
+
+/// A xz stream writer encoding blocks on several threads.
+///
+/// The input is split in blocks of a fixed size which are encoded
+/// independently, so the output is the same whatever the amount of threads.
+///
+/// At most `threads` blocks are held in memory besides the one being filled.
+#[derive(Debug)]
+pub struct Writer<W: Write> {
+	stream:     W,
+	properties: Properties,
+	search:     (usize, usize),
+
+	pool:    Pool,
+	pending: VecDeque<mpsc::Receiver<Encoded>>,
+	input:   Vec<u8>,
+
+	flags:   Flags,
+	size:    usize,
+	records: Vec<Record>,
+	started: bool,
+}
+
+impl<W: Write> Writer<W> {
+	/// Creates a xz writer using the given amount of threads, the blocks are
+	/// encoded as LZMA2 with the given model properties.
+	pub fn new(stream: W, properties: Properties, threads: usize) -> Result<Writer<W>, Error> {
+		if properties.lc + properties.lp > 4 || properties.pb > 4 {
+			return Err(Error::InvalidProperties);
+		}
+
+		Ok(Writer {
+			stream:     stream,
+			properties: properties,
+			search:     (32, 64),
+
+			pool:    Pool::new(threads),
+			pending: VecDeque::new(),
+			input:   Vec::new(),
+
+			flags:   Flags { check: Check::Crc64 },
+			size:    cmp::max(3 * properties.dictionary as usize, 1 << 20),
+			records: Vec::new(),
+			started: false,
+		})
+	}
+
+	/// Returns the amount of threads.
+	pub fn threads(&self) -> usize {
+		self.pool.threads()
+	}
+
+	/// Returns the flags of the stream.
+	pub fn flags(&self) -> &Flags {
+		&self.flags
+	}
+
+	/// Returns the uncompressed size of the blocks.
+	pub fn block_size(&self) -> usize {
+		self.size
+	}
+
+	/// Sets the integrity check of the blocks, CRC64 by default.
+	///
+	/// It must be set before writing anything.
+	pub fn set_check(&mut self, value: Check) -> Result<(), Error> {
+		if self.started || !self.input.is_empty() {
+			return Err(Error::InvalidState);
+		}
+
+		if let Check::Unknown(_) = value {
+			return Err(Error::Unsupported);
+		}
+
+		self.flags.check = value;
+
+		Ok(())
+	}
+
+	/// Sets the uncompressed size of the blocks, three times the dictionary
+	/// size and at least 1 MiB by default.
+	///
+	/// It must be set before writing anything.
+	pub fn set_block_size(&mut self, value: usize) -> Result<(), Error> {
+		if self.started || !self.input.is_empty() {
+			return Err(Error::InvalidState);
+		}
+
+		self.size = cmp::max(value, 1);
+
+		Ok(())
+	}
+
+	/// Sets how hard the match finder searches, see `lzma::Writer::set_search`.
+	pub fn set_search(&mut self, depth: usize, nice: usize) {
+		self.search = (depth, nice);
+	}
+
+	// writes the stream header the first time
+	fn start(&mut self) -> Result<(), Error> {
+		if !self.started {
+			try!(self.flags.write_header(self.stream.by_ref()));
+			self.started = true;
+		}
+
+		Ok(())
+	}
+
+	// sends the filled block to the workers, waiting for the oldest one if
+	// they're all busy
+	fn dispatch(&mut self) -> Result<(), Error> {
+		try!(self.start());
+
+		while self.pending.len() >= self.pool.threads() {
+			try!(self.collect());
+		}
+
+		let data       = mem::take(&mut self.input);
+		let properties = self.properties;
+		let search     = self.search;
+		let check      = self.flags.check;
+
+		self.pending.push_back(self.pool.spawn(move |_| encode(&data, properties, search, check)));
+
+		Ok(())
+	}
+
+	// writes the oldest pending block
+	fn collect(&mut self) -> Result<(), Error> {
+		let receiver = self.pending.pop_front().unwrap();
+
+		let (record, data) = match receiver.recv() {
+			Ok(result) =>
+				try!(result),
+
+			Err(_) =>
+				return Err(Error::IO(io::Error::other("encoding thread died")))
+		};
+
+		try!(self.stream.write_all(&data));
+		self.records.push(record);
+
+		Ok(())
+	}
+
+	/// Encodes the remaining data and writes the index and the end of the
+	/// stream, returning the underlying writer.
+	pub fn finish(mut self) -> Result<W, Error> {
+		try!(self.start());
+
+		if !self.input.is_empty() {
+			try!(self.dispatch());
+		}
+
+		while !self.pending.is_empty() {
+			try!(self.collect());
+		}
+
+		let size = try!(Index::write(self.stream.by_ref(), &self.records));
+		try!(self.flags.write_footer(self.stream.by_ref(), size));
+		try!(self.stream.flush());
+
+		Ok(self.stream)
+	}
+}
+
+impl<W: Write> Write for Writer<W> {
+	fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+		let mut written = 0;
+
+		while written < buf.len() {
+			let length = cmp::min(self.size - self.input.len(), buf.len() - written);
+			self.input.extend_from_slice(&buf[written .. written + length]);
+			written += length;
+
+			if self.input.len() == self.size {
+				try!(self.dispatch().map_err(Error::into_io));
+			}
+		}
+
+		Ok(written)
+	}
+
+	fn flush(&mut self) -> io::Result<()> {
+		self.stream.flush()
+	}
+}
+
+fn encode(data: &[u8], properties: Properties, search: (usize, usize), check: Check) -> Encoded {
+	let mut encoder = try!(lzma2::Writer::new(Vec::new(), properties));
+	encoder.set_search(search.0, search.1);
+	try!(encoder.write_all(data));
+	let compressed = try!(encoder.finish());
+
+	let mut block = Block {
+		size: 0,
+
+		compressed:   Some(compressed.len() as u64),
+		uncompressed: Some(data.len() as u64),
+
+		filters: vec![Filter::Lzma2(properties.dictionary)],
+	};
+
+	let mut output = try!(block.encode());
+	output.extend_from_slice(&compressed);
+
+	while output.len() % 4 != 0 {
+		output.push(0);
+	}
+
+	let mut hasher = Hasher::new(check);
+	hasher.update(data);
+
+	if let Some(value) = hasher.value() {
+		output.extend_from_slice(&value);
+	}
+
+	let record = Record {
+		unpadded:     block.size + compressed.len() as u64 + check.size() as u64,
+		uncompressed: data.len() as u64,
+	};
+
+	Ok((record, output))
+}
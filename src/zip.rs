@@ -0,0 +1,55 @@
+use std::io::{Read, Write};
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+
+use {Error, Properties, properties};
+use reader::Reader;
+use writer::Writer;
+
+/// The LZMA SDK version written in the entry header.
+pub const VERSION: [u8; 2] = [9, 20];
+
+/// The general purpose flag telling the entry ends with an EOS marker.
+pub const MARKER_FLAG: u16 = 1 << 1;
+
+/// Size of the model properties in the entry header.
+const PROPERTIES_SIZE: u16 = 5;
+
+/// Create a LZMA stream from a ZIP entry, the uncompressed size and the
+/// general purpose flags come from the central directory.
+///
+/// The entry is decoded up to the uncompressed size, with `MARKER_FLAG` set
+/// the EOS marker must come right after it.
+pub fn read<T: Read>(stream: T, uncompressed: u64, flags: u16) -> Result<Reader<T>, Error> {
+	read_with_memlimit(stream, uncompressed, flags, u64::MAX)
+}
+
+/// Create a LZMA stream from a ZIP entry like `read`, failing before
+/// allocating anything if decoding needs more memory than the limit.
+pub fn read_with_memlimit<T: Read>(mut stream: T, uncompressed: u64, flags: u16, memlimit: u64) -> Result<Reader<T>, Error> {
+	let mut version = [0u8; 2];
+	try!(stream.read_exact(&mut version));
+
+	if try!(stream.read_u16::<LittleEndian>()) != PROPERTIES_SIZE {
+		return Err(Error::InvalidProperties);
+	}
+
+	let mut properties = try!(properties::read_model(stream.by_ref()));
+	properties.uncompressed = Some(uncompressed);
+
+	let mut reader = try!(Reader::with_memlimit(stream, properties, memlimit));
+	reader.set_marker(flags & MARKER_FLAG != 0);
+
+	Ok(reader)
+}
+
+/// Create a LZMA stream for a ZIP entry.
+///
+/// The uncompressed size is never stored, if it's unknown the EOS marker is
+/// written and `MARKER_FLAG` must be set in the entry general purpose flags.
+pub fn write<T: Write>(mut stream: T, properties: Properties) -> Result<Writer<T>, Error> {
+	try!(stream.write_all(&VERSION));
+	try!(stream.write_u16::<LittleEndian>(PROPERTIES_SIZE));
+	try!(properties::write_model(stream.by_ref(), &properties));
+
+	Writer::new(stream, properties)
+}
@@ -0,0 +1,65 @@
+use std::io::{Read, BufReader};
+use std::fs::File;
+
+extern crate lzma;
+
+use lzma::Format;
+
+fn header(path: &str) -> Vec<u8> {
+	let mut buffer = Vec::new();
+	File::open(path).unwrap().read_to_end(&mut buffer).unwrap();
+	buffer.truncate(lzma::format::DETECT_SIZE);
+
+	buffer
+}
+
+#[test]
+fn detect() {
+	assert_eq!(lzma::detect(&header("tests/assets/a.lzma")), Some(Format::Lzma));
+	assert_eq!(lzma::detect(&header("tests/assets/a_eos.lzma")), Some(Format::Lzma));
+	assert_eq!(lzma::detect(&header("tests/assets/a.xz")), Some(Format::Xz));
+	assert_eq!(lzma::detect(&header("tests/assets/a.lz")), Some(Format::Lzip));
+	assert_eq!(lzma::detect(&header("tests/assets/a.pbzx")), Some(Format::Pbzx));
+	assert_eq!(lzma::detect(&header("tests/assets/a.swf")), Some(Format::Swf));
+	assert_eq!(lzma::detect(b"7z\xbc\xaf\x27\x1c\x00\x04"), Some(Format::SevenZip));
+
+	assert_eq!(lzma::detect(&header("tests/assets/a.txt")), None);
+	assert_eq!(lzma::detect(&header("tests/assets/a_x86.lzma86")), None);
+	assert_eq!(lzma::detect(&[0x5d, 0, 0, 0, 0, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x00]), None);
+}
+
+#[test]
+fn open_any() {
+	let mut control = Vec::new();
+	File::open("tests/assets/a.txt").unwrap().read_to_end(&mut control).unwrap();
+
+	for path in &["tests/assets/a.lzma", "tests/assets/a.xz", "tests/assets/a.lz", "tests/assets/a.pbzx"] {
+		let mut reader = lzma::open_any(path).unwrap();
+		let mut result = Vec::new();
+
+		reader.read_to_end(&mut result).unwrap();
+
+		assert_eq!(control, result);
+	}
+}
+
+#[test]
+fn read_any_small_buffer() {
+	let mut control = Vec::new();
+	File::open("tests/assets/a.txt").unwrap().read_to_end(&mut control).unwrap();
+
+	// a buffer shorter than the bytes the detection needs
+	for path in &["tests/assets/a.lzma", "tests/assets/a.xz", "tests/assets/a.lz"] {
+		let mut reader = lzma::read_any(BufReader::with_capacity(1, File::open(path).unwrap())).unwrap();
+		let mut result = Vec::new();
+
+		reader.read_to_end(&mut result).unwrap();
+
+		assert_eq!(control, result);
+	}
+}
+
+#[test]
+fn open_any_unknown() {
+	assert!(lzma::open_any("tests/assets/a.txt").is_err());
+}
@@ -0,0 +1,127 @@
+use std::io::{Write, Cursor};
+
+extern crate lzma;
+
+use lzma::{Info, Format};
+use lzma::xz::{Check, Filter};
+
+#[test]
+fn lzma() {
+	let info = lzma::info("tests/assets/a.lzma").unwrap();
+
+	assert_eq!(info.format(), Format::Lzma);
+	assert_eq!(info.compressed(), 117);
+	assert_eq!(info.uncompressed(), Some(327));
+
+	match info {
+		Info::Lzma(ref lzma) =>
+			assert_eq!((lzma.properties.lc, lzma.properties.lp, lzma.properties.pb, lzma.properties.dictionary), (3, 0, 2, 1 << 23)),
+
+		_ =>
+			unreachable!()
+	}
+
+	let info = lzma::info("tests/assets/a_eos.lzma").unwrap();
+	assert_eq!(info.uncompressed(), None);
+	assert_eq!(info.ratio(), None);
+}
+
+#[test]
+fn xz() {
+	let info = lzma::info("tests/assets/a_concatenated.xz").unwrap();
+
+	assert_eq!(info.compressed(), 256);
+	assert_eq!(info.uncompressed(), Some(327));
+
+	let streams = match info {
+		Info::Xz(streams) => streams,
+		_                 => unreachable!(),
+	};
+
+	assert_eq!(streams.len(), 2);
+	assert_eq!((streams[0].offset, streams[0].size, streams[0].padding, streams[0].check), (0, 128, 4, Check::Crc32));
+	assert_eq!((streams[1].offset, streams[1].size, streams[1].padding, streams[1].check), (132, 124, 0, Check::None));
+
+	assert_eq!((streams[0].blocks[0].offset, streams[0].blocks[0].compressed, streams[0].blocks[0].uncompressed), (12, 68, 150));
+	assert_eq!((streams[1].blocks[0].offset, streams[1].blocks[0].compressed, streams[1].blocks[0].uncompressed), (144, 66, 177));
+
+	let info = lzma::info("tests/assets/a_delta.xz").unwrap();
+
+	match info {
+		Info::Xz(ref streams) =>
+			assert_eq!(streams[0].blocks[0].filters, vec![Filter::Delta(2), Filter::Lzma2(1 << 23)]),
+
+		_ =>
+			unreachable!()
+	}
+}
+
+#[test]
+fn xz_blocks() {
+	let properties = lzma::Properties { lc: 3, lp: 0, pb: 2, dictionary: 1 << 16, uncompressed: None };
+	let mut writer = lzma::xz::write(Vec::new(), properties).unwrap();
+	writer.set_block_size(1000).unwrap();
+	writer.write_all(&[42u8; 3500]).unwrap();
+
+	let data = writer.finish().unwrap();
+	let info = Info::read(Cursor::new(&data)).unwrap();
+
+	assert_eq!(info.compressed(), data.len() as u64);
+	assert_eq!(info.uncompressed(), Some(3500));
+
+	match info {
+		Info::Xz(ref streams) => {
+			let sizes = streams[0].blocks.iter().map(|block| block.uncompressed).collect::<Vec<_>>();
+			assert_eq!(sizes, vec![1000, 1000, 1000, 500]);
+
+			// the dictionary is no bigger than the block
+			assert!(streams[0].blocks.iter().all(|block| block.memory.unwrap() < 1 << 16));
+		}
+
+		_ =>
+			unreachable!()
+	}
+}
+
+#[test]
+fn lzip() {
+	let info = lzma::info("tests/assets/a_members.lz").unwrap();
+
+	assert_eq!(info.compressed(), 200);
+	assert_eq!(info.uncompressed(), Some(327));
+
+	match info {
+		Info::Lzip(ref members) => {
+			assert_eq!(members.len(), 2);
+			assert_eq!((members[0].offset, members[0].size, members[0].uncompressed), (0, 87, 100));
+			assert_eq!((members[1].offset, members[1].size, members[1].uncompressed), (87, 113, 227));
+		}
+
+		_ =>
+			unreachable!()
+	}
+}
+
+#[test]
+fn json() {
+	let mut json = Vec::new();
+	lzma::info("tests/assets/a_delta.xz").unwrap().write_json(&mut json).unwrap();
+
+	assert_eq!(String::from_utf8(json).unwrap(), concat!(
+		"{\"format\":\"xz\",\"compressed\":204,\"uncompressed\":327,\"ratio\":0.6239,\"memory\":28597,",
+		"\"streams\":[{\"offset\":0,\"size\":204,\"padding\":0,\"uncompressed\":327,\"check\":\"CRC64\",",
+		"\"blocks\":[{\"offset\":12,\"header\":20,\"compressed\":139,\"uncompressed\":327,\"memory\":28597,",
+		"\"filters\":[{\"id\":\"delta\",\"distance\":2},{\"id\":\"lzma2\",\"dictionary\":8388608}]}]}]}"));
+
+	let mut json = Vec::new();
+	lzma::info("tests/assets/a_eos.lzma").unwrap().write_json(&mut json).unwrap();
+
+	assert_eq!(String::from_utf8(json).unwrap(),
+		"{\"format\":\"lzma\",\"compressed\":122,\"uncompressed\":null,\"ratio\":null,\"memory\":81518,\"lc\":3,\"lp\":0,\"pb\":2,\"dictionary\":65536}");
+}
+
+#[test]
+fn unsupported() {
+	assert!(lzma::info("tests/assets/a.txt").is_err());
+	assert!(lzma::info("tests/assets/a.pbzx").is_err());
+}
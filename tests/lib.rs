@@ -1,4 +1,4 @@
-use std::io::Read;
+use std::io::{Read, Write, Cursor};
 use std::fs::File;
 
 extern crate lzma;
@@ -36,6 +36,11 @@ fn a_eos_and_size() {
 	check(decode("tests/assets/a_eos_and_size.lzma"));
 }
 
+#[test]
+fn a_lp4_lc0_pb4() {
+	check(decode("tests/assets/a_lp4_lc0_pb4.lzma"));
+}
+
 #[test]
 #[should_panic]
 fn bad_corrupted() {
@@ -53,3 +58,464 @@ fn bad_incorrect_size() {
 fn bad_eos_incorrect_size() {
 	check(decode("tests/assets/bad_eos_incorrect_size.lzma"));
 }
+
+fn encode(string: &str, properties: lzma::Properties) -> Vec<u8> {
+	let mut writer = lzma::write(Vec::new(), properties).unwrap();
+	writer.write_all(string.as_bytes()).unwrap();
+
+	writer.finish().unwrap()
+}
+
+fn control() -> String {
+	let mut reader  = File::open("tests/assets/a.txt").unwrap();
+	let mut control = String::new();
+
+	reader.read_to_string(&mut control).unwrap();
+
+	control
+}
+
+fn roundtrip(string: &str, properties: lzma::Properties) {
+	let mut reader = lzma::read(Cursor::new(encode(string, properties))).unwrap();
+	let mut result = String::new();
+
+	reader.read_to_string(&mut result).unwrap();
+
+	assert_eq!(string, result);
+}
+
+#[test]
+fn write_eos() {
+	roundtrip(&control(), lzma::Properties {
+		lc: 3, lp: 0, pb: 2, dictionary: 1 << 16, uncompressed: None });
+}
+
+#[test]
+fn write_size() {
+	let string = control();
+
+	roundtrip(&string, lzma::Properties {
+		lc: 3, lp: 0, pb: 2, dictionary: 1 << 16, uncompressed: Some(string.len() as u64) });
+}
+
+#[test]
+fn write_lp1_lc2_pb1() {
+	roundtrip(&control(), lzma::Properties {
+		lc: 2, lp: 1, pb: 1, dictionary: 1 << 16, uncompressed: None });
+}
+
+#[test]
+fn write_over_dictionary() {
+	let string = control().repeat(64);
+
+	roundtrip(&string, lzma::Properties {
+		lc: 3, lp: 0, pb: 2, dictionary: 1 << 12, uncompressed: None });
+}
+
+#[test]
+fn write_empty() {
+	roundtrip("", lzma::Properties {
+		lc: 3, lp: 0, pb: 2, dictionary: 1 << 16, uncompressed: None });
+}
+
+#[test]
+#[should_panic]
+fn write_bad_size() {
+	encode(&control(), lzma::Properties {
+		lc: 3, lp: 0, pb: 2, dictionary: 1 << 16, uncompressed: Some(1) });
+}
+
+#[test]
+fn memlimit() {
+	// a hostile header asking for a 4 GiB dictionary and 2^12 times the literals
+	let header = [8 + 9 * 4, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0];
+
+	match lzma::Reader::from_with_memlimit(Cursor::new(&header[..]), 1 << 26) {
+		Err(lzma::Error::MemoryLimit { needed, limit }) => {
+			assert!(needed > 0xffffffff + (0x300 << 12) * 2);
+			assert_eq!(limit, 1 << 26);
+		}
+
+		_ =>
+			panic!("memory limit not enforced")
+	}
+
+	let mut reader = lzma::Reader::from_with_memlimit(File::open("tests/assets/a.lzma").unwrap(), 1 << 24).unwrap();
+	let mut string = String::new();
+
+	reader.read_to_string(&mut string).unwrap();
+	check(string);
+}
+
+#[test]
+fn decoder_memory_usage() {
+	let properties = lzma::Properties {
+		lc: 3, lp: 0, pb: 2, dictionary: 1 << 16, uncompressed: None };
+
+	assert!(properties.decoder_memory_usage() > (1 << 16) + (0x300 << 3) * 2);
+	assert!(properties.decoder_memory_usage() < (1 << 16) + (0x300 << 3) * 2 + 8192);
+}
+
+#[test]
+fn window() {
+	let mut properties = lzma::Properties {
+		lc: 3, lp: 0, pb: 2, dictionary: 1 << 26, uncompressed: Some(2048) };
+
+	assert_eq!(properties.window(), 2048);
+	assert!(properties.decoder_memory_usage() < 1 << 16);
+
+	properties.uncompressed = None;
+	assert_eq!(properties.window(), 1 << 26);
+
+	// a huge dictionary on a small payload doesn't reserve anything up front
+	let mut buffer = Vec::new();
+	File::open("tests/assets/a.lzma").unwrap().read_to_end(&mut buffer).unwrap();
+	buffer[1 .. 5].copy_from_slice(&[0xff, 0xff, 0xff, 0xff]);
+
+	let mut reader = lzma::read(Cursor::new(buffer)).unwrap();
+	let mut string = String::new();
+
+	reader.read_to_string(&mut string).unwrap();
+	check(string);
+}
+
+#[test]
+fn preset() {
+	let dictionary = br#"{"type":"measurement","source":"sensor","unit":"celsius","location":{"building":"north","floor":3}}"#;
+	let record     = br#"{"type":"measurement","source":"sensor","unit":"celsius","location":{"building":"north","floor":4}}"#;
+
+	let properties = lzma::Properties {
+		lc: 3, lp: 0, pb: 2, dictionary: 1 << 16, uncompressed: Some(record.len() as u64) };
+
+	let mut plain = lzma::Writer::new(Vec::new(), properties).unwrap();
+	plain.write_all(record).unwrap();
+	let plain = plain.finish().unwrap();
+
+	let mut writer = lzma::Writer::with_preset(Vec::new(), properties, dictionary).unwrap();
+	writer.write_all(record).unwrap();
+	let buffer = writer.finish().unwrap();
+
+	assert!(buffer.len() * 4 < plain.len());
+
+	let mut reader = lzma::Reader::with_preset(Cursor::new(&buffer), properties, dictionary).unwrap();
+	let mut result = Vec::new();
+
+	reader.read_to_end(&mut result).unwrap();
+	assert_eq!(&result[..], &record[..]);
+
+	// without the dictionary the matches point nowhere
+	let mut reader = lzma::Reader::new(Cursor::new(&buffer), properties).unwrap();
+	assert!(reader.read_to_end(&mut Vec::new()).is_err());
+}
+
+#[test]
+fn snapshot() {
+	let mut string = String::new();
+
+	for i in 0 .. 2000 {
+		string.push_str(&format!("{} {}\n", i, control()));
+	}
+
+	let buffer = encode(&string, lzma::Properties {
+		lc: 3, lp: 0, pb: 2, dictionary: 1 << 16, uncompressed: None });
+
+	// odd reads so that some decoded data is left over in the reader
+	let mut reader = lzma::read(Cursor::new(&buffer)).unwrap();
+	let mut result = vec![0u8; 300000];
+	reader.read_exact(&mut result).unwrap();
+	assert!(reader.cached() > 0);
+
+	let mut saved = Vec::new();
+	reader.snapshot().write(&mut saved).unwrap();
+	drop(reader);
+
+	let snapshot = lzma::Snapshot::read(Cursor::new(&saved)).unwrap();
+	assert_eq!(snapshot.decoded(), 300000);
+
+	let mut stream = Cursor::new(&buffer);
+	stream.set_position(snapshot.compressed());
+
+	match lzma::Reader::restore_with_memlimit(stream.clone(), &snapshot, 4096) {
+		Err(lzma::Error::MemoryLimit { .. }) =>
+			(),
+
+		_ =>
+			panic!("memory limit not enforced")
+	}
+
+	let mut reader = lzma::Reader::restore_with_memlimit(stream, &snapshot, 1 << 24).unwrap();
+	reader.read_to_end(&mut result).unwrap();
+
+	assert_eq!(String::from_utf8(result).unwrap(), string);
+
+	assert!(lzma::Snapshot::read(Cursor::new(&saved[.. saved.len() - 1])).is_err());
+	saved[4] = 2;
+	assert!(lzma::Snapshot::read(Cursor::new(&saved)).is_err());
+}
+
+// a snapshot with its rep0 distance replaced
+fn tampered(reader: &lzma::Reader<Cursor<&Vec<u8>>>, rep0: u32) -> Result<lzma::Snapshot, lzma::Error> {
+	let mut saved = Vec::new();
+	reader.snapshot().write(&mut saved).unwrap();
+
+	// the distances come right before the state probabilities
+	let offset = saved.len() - 2 * (192 + 4 * 12 + 192) - 16;
+	saved[offset .. offset + 4].copy_from_slice(&rep0.to_le_bytes());
+
+	lzma::Snapshot::read(Cursor::new(&saved))
+}
+
+#[test]
+fn snapshot_distances() {
+	let buffer = encode(&control(), lzma::Properties {
+		lc: 3, lp: 0, pb: 2, dictionary: 1 << 16, uncompressed: None });
+
+	let mut reader = lzma::read(Cursor::new(&buffer)).unwrap();
+	assert!(tampered(&reader, 0).is_ok());
+
+	match tampered(&reader, 1000) {
+		Err(lzma::Error::Corrupted) => (),
+		_                           => panic!("distance beyond the history accepted"),
+	}
+
+	reader.read_exact(&mut [0u8; 200]).unwrap();
+	assert!(tampered(&reader, 100).is_ok());
+	assert!(tampered(&reader, 1000).is_err());
+	assert!(tampered(&reader, 1 << 16).is_err());
+}
+
+#[test]
+fn snapshot_finished() {
+	let mut buffer = Vec::new();
+	File::open("tests/assets/a_eos.lzma").unwrap().read_to_end(&mut buffer).unwrap();
+
+	let mut reader = lzma::read(Cursor::new(&buffer)).unwrap();
+	reader.read_to_end(&mut Vec::new()).unwrap();
+
+	let mut saved = Vec::new();
+	reader.snapshot().write(&mut saved).unwrap();
+
+	let snapshot = lzma::Snapshot::read(Cursor::new(&saved)).unwrap();
+	assert_eq!(snapshot.decoded(), control().len() as u64);
+
+	let mut stream = Cursor::new(&buffer);
+	stream.set_position(snapshot.compressed());
+
+	let mut reader = lzma::Reader::restore(stream, &snapshot).unwrap();
+	assert_eq!(reader.read(&mut [0u8; 16]).unwrap(), 0);
+}
+
+fn failure(path: &str) -> (lzma::Position, lzma::Cause) {
+	let mut buffer = Vec::new();
+	File::open(path).unwrap().read_to_end(&mut buffer).unwrap();
+
+	failure_of(&buffer)
+}
+
+fn failure_of(buffer: &[u8]) -> (lzma::Position, lzma::Cause) {
+	let mut reader = lzma::read(buffer).unwrap();
+	assert!(reader.read_to_end(&mut Vec::new()).is_err());
+
+	let failure = reader.failure().unwrap();
+	(failure.position, failure.cause)
+}
+
+fn error_of(path: &str) -> lzma::Error {
+	let error = lzma::open(path).unwrap().read_to_end(&mut Vec::new()).unwrap_err();
+
+	match error.into_inner().map(|e| e.downcast::<lzma::Error>()) {
+		Some(Ok(error)) =>
+			*error,
+
+		_ =>
+			panic!("not a decoding error")
+	}
+}
+
+#[test]
+fn error_position() {
+	for path in &["tests/assets/bad_corrupted.lzma", "tests/assets/bad_eos_incorrect_size.lzma"] {
+		let (position, _) = failure(path);
+		assert!(position.compressed > lzma::properties::HEADER_SIZE);
+	}
+
+	let (_, cause) = failure("tests/assets/bad_eos_incorrect_size.lzma");
+	assert_eq!(cause, lzma::Cause::NeedMoreData);
+
+	// the errors stay the same, the position comes from the reader
+	match error_of("tests/assets/bad_corrupted.lzma") {
+		lzma::Error::Corrupted => (),
+		error                  => panic!("unexpected error: {}", error),
+	}
+
+	match error_of("tests/assets/bad_eos_incorrect_size.lzma") {
+		lzma::Error::NeedMoreData => (),
+		error                     => panic!("unexpected error: {}", error),
+	}
+
+	let mut buffer = Vec::new();
+	File::open("tests/assets/a_eos.lzma").unwrap().read_to_end(&mut buffer).unwrap();
+
+	// truncated in the middle
+	let (position, cause) = failure_of(&buffer[.. 100]);
+	assert_eq!(cause, lzma::Cause::Truncated);
+	assert_eq!(position.compressed, 100);
+	assert!(position.uncompressed > 0);
+
+	let error = lzma::read(&buffer[.. 100]).unwrap().read_to_end(&mut Vec::new()).unwrap_err();
+	assert_eq!(error.kind(), std::io::ErrorKind::UnexpectedEof);
+
+	// the first byte of the range coded data
+	let mut flipped = buffer.clone();
+	flipped[lzma::properties::HEADER_SIZE as usize] = 1;
+
+	let (position, cause) = failure_of(&flipped);
+	assert_eq!(cause, lzma::Cause::FirstByte);
+	assert_eq!(position.compressed, lzma::properties::HEADER_SIZE);
+	assert_eq!(position.uncompressed, 0);
+	assert_eq!(position.packet, lzma::PacketKind::Unknown);
+}
+
+#[test]
+fn error_position_restored() {
+	let mut buffer = Vec::new();
+	File::open("tests/assets/a_eos.lzma").unwrap().read_to_end(&mut buffer).unwrap();
+
+	let (position, _) = failure_of(&buffer[.. 100]);
+
+	let mut reader = lzma::read(Cursor::new(&buffer[.. 100])).unwrap();
+	reader.read_exact(&mut [0u8; 10]).unwrap();
+
+	let mut saved = Vec::new();
+	reader.snapshot().write(&mut saved).unwrap();
+
+	let     snapshot = lzma::Snapshot::read(Cursor::new(&saved)).unwrap();
+	let mut stream   = Cursor::new(&buffer[.. 100]);
+	stream.set_position(snapshot.compressed());
+
+	let mut reader = lzma::Reader::restore(stream, &snapshot).unwrap();
+	assert!(reader.read_to_end(&mut Vec::new()).is_err());
+
+	assert_eq!(reader.failure().unwrap().position.compressed, position.compressed);
+}
+
+#[test]
+fn marker() {
+	// reading on after the EOS marker stays at the end
+	let mut reader = lzma::open("tests/assets/a_eos.lzma").unwrap();
+	reader.read_to_end(&mut Vec::new()).unwrap();
+	assert_eq!(reader.read(&mut [0u8; 16]).unwrap(), 0);
+
+	// the range code reaches zero in the middle of this stream, before the marker
+	let mut output = Vec::new();
+	lzma::open("tests/assets/a_zero_code.lzma").unwrap().read_to_end(&mut output).unwrap();
+	assert_eq!(output.len(), 666);
+
+	// a stream of unknown size ending without the marker
+	let mut buffer = Vec::new();
+	File::open("tests/assets/a.lzma").unwrap().read_to_end(&mut buffer).unwrap();
+
+	for byte in &mut buffer[5 .. lzma::properties::HEADER_SIZE as usize] {
+		*byte = 0xff;
+	}
+
+	let error = lzma::read(&buffer[..]).unwrap().read_to_end(&mut Vec::new()).unwrap_err();
+
+	match error.get_ref().and_then(|e| e.downcast_ref::<lzma::Error>()) {
+		Some(&lzma::Error::MissingMarker) => (),
+		_                                 => panic!("unexpected error: {}", error),
+	}
+}
+
+#[test]
+fn packets() {
+	let control = control();
+
+	for path in &["tests/assets/a.lzma", "tests/assets/a_eos.lzma"] {
+		let mut reader  = lzma::open(path).unwrap();
+		let mut packets = reader.packets().unwrap();
+		let mut output  = Vec::new();
+		let mut matches = 0;
+
+		while let Some(item) = packets.next() {
+			let (offset, packet) = item.unwrap();
+			assert_eq!(offset, output.len() as u64);
+			assert_eq!(packets.bytes().len(), packet.length());
+
+			match packet {
+				lzma::Packet::Literal(byte) =>
+					assert_eq!(packets.bytes(), &[byte]),
+
+				lzma::Packet::Match { distance, len } => {
+					let start = output.len() - distance as usize;
+					assert!(len >= 2);
+					assert_eq!(packets.bytes()[0], output[start]);
+
+					matches += 1;
+				}
+
+				lzma::Packet::EndMarker =>
+					assert_eq!(*path, "tests/assets/a_eos.lzma"),
+
+				_ =>
+					()
+			}
+
+			output.extend_from_slice(packets.bytes());
+		}
+
+		assert!(matches > 0);
+		assert_eq!(output, control.as_bytes());
+	}
+}
+
+#[test]
+fn packets_skip() {
+	let all = lzma::open("tests/assets/a.lzma").unwrap().packets().unwrap().map(Result::unwrap).collect::<Vec<_>>();
+
+	for &n in &[0, 1, 10, all.len() - 1] {
+		let mut reader  = lzma::open("tests/assets/a.lzma").unwrap();
+		let mut packets = reader.packets().unwrap();
+
+		assert_eq!(packets.nth(n).unwrap().unwrap(), all[n]);
+		assert_eq!(packets.bytes().len(), all[n].1.length());
+		assert_eq!(packets.map(Result::unwrap).collect::<Vec<_>>(), &all[n + 1 ..]);
+	}
+
+	let mut reader = lzma::open("tests/assets/a.lzma").unwrap();
+	assert!(reader.packets().unwrap().nth(all.len()).is_none());
+
+	// bytes left in the cache can't be given as packets
+	let mut reader = lzma::open("tests/assets/a.lzma").unwrap();
+
+	while reader.cached() == 0 {
+		reader.read_exact(&mut [0u8; 1]).unwrap();
+	}
+
+	assert_eq!(reader.packets().unwrap_err().to_string(), lzma::Error::InvalidState.to_string());
+
+	let mut rest = Vec::new();
+	reader.read_to_end(&mut rest).unwrap();
+	assert!(reader.packets().unwrap().next().is_none());
+}
+
+#[test]
+fn stats() {
+	let stats = lzma::stats(lzma::open("tests/assets/a_eos.lzma").unwrap()).unwrap();
+	let size  = std::fs::metadata("tests/assets/a_eos.lzma").unwrap().len() - lzma::properties::HEADER_SIZE;
+
+	assert_eq!(stats.decoded(), control().len() as u64);
+	assert_eq!(stats.marker.count, 1);
+	assert!(stats.literals.count > 0 && stats.matches.count > 0);
+
+	let matches = stats.matches.count + stats.reps.iter().map(|c| c.count).sum::<u64>();
+	assert_eq!(stats.lengths.iter().sum::<u64>(), matches);
+	assert_eq!(stats.distances.iter().sum::<u64>(), stats.matches.count);
+
+	// the range decoder flushes a few more bytes
+	assert!(stats.bits() / 8.0 <= size as f64);
+	assert!(stats.bits() / 8.0 > size as f64 - 8.0);
+
+	assert!(stats.literals.bits_per_packet() > 0.0);
+	assert!(stats.literal_bits() + stats.match_bits() < stats.bits());
+}
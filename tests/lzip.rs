@@ -0,0 +1,97 @@
+use std::io::{Read, Write};
+use std::fs::File;
+
+extern crate lzma;
+
+fn control() -> Vec<u8> {
+	let mut reader  = File::open("tests/assets/a.txt").unwrap();
+	let mut control = Vec::new();
+
+	reader.read_to_end(&mut control).unwrap();
+
+	control
+}
+
+#[test]
+fn a() {
+	let mut reader = lzma::lzip::open("tests/assets/a.lz").unwrap();
+	let mut result = Vec::new();
+
+	assert_eq!(reader.version(), 1);
+	assert_eq!(reader.properties().unwrap().dictionary, 1 << 16);
+	reader.read_to_end(&mut result).unwrap();
+
+	assert_eq!(control(), result);
+	assert!(reader.is_finished());
+}
+
+#[test]
+fn members() {
+	let mut reader = lzma::lzip::open("tests/assets/a_members.lz").unwrap();
+	let mut result = Vec::new();
+
+	reader.read_to_end(&mut result).unwrap();
+
+	assert_eq!(control(), result);
+	assert_eq!(reader.members(), 2);
+}
+
+#[test]
+fn bad_check() {
+	let mut reader = lzma::lzip::open("tests/assets/bad_check.lz").unwrap();
+	let     error  = reader.read_to_end(&mut Vec::new()).unwrap_err();
+
+	assert_eq!(error.to_string(), lzma::Error::Checksum.to_string());
+}
+
+#[test]
+fn read_after_error() {
+	let mut reader = lzma::lzip::open("tests/assets/bad_check.lz").unwrap();
+	assert!(reader.read_to_end(&mut Vec::new()).is_err());
+
+	// the error sticks instead of the reader losing its stream
+	for _ in 0 .. 2 {
+		let error = reader.read(&mut [0u8; 16]).unwrap_err();
+		assert_eq!(error.to_string(), lzma::Error::Checksum.to_string());
+	}
+
+	assert!(!reader.is_finished());
+	reader.into_inner();
+}
+
+#[test]
+fn dictionary() {
+	assert_eq!(lzma::lzip::dictionary(0x10).unwrap(), 1 << 16);
+	assert_eq!(lzma::lzip::dictionary(0xd3).unwrap(), (1 << 19) - 6 * (1 << 15));
+	assert!(lzma::lzip::dictionary(0x1e).is_err());
+}
+
+#[test]
+fn dictionary_byte() {
+	assert_eq!(lzma::lzip::dictionary_byte(1 << 16), 0x10);
+	assert_eq!(lzma::lzip::dictionary_byte((1 << 19) - 6 * (1 << 15)), 0xd3);
+	assert_eq!(lzma::lzip::dictionary_byte(1), 0x0c);
+
+	for &size in &[4097, 100000, 3 << 20, 1 << 29] {
+		let rounded = lzma::lzip::dictionary(lzma::lzip::dictionary_byte(size)).unwrap();
+		assert!(rounded >= size && rounded - size < size / 8);
+	}
+}
+
+#[test]
+fn write() {
+	let mut writer = lzma::lzip::write(Vec::new(), 100000).unwrap();
+	writer.set_search(64, 128);
+	writer.write_all(&control()).unwrap();
+
+	let     data   = writer.finish().unwrap();
+	let mut reader = lzma::lzip::read(&data[..]).unwrap();
+	let mut result = Vec::new();
+
+	assert_eq!(reader.properties().unwrap().dictionary, (1 << 17) - 6 * (1 << 12));
+	reader.read_to_end(&mut result).unwrap();
+
+	assert_eq!(control(), result);
+	assert!(reader.is_finished());
+	assert_eq!(reader.compressed(), data.len() as u64);
+}
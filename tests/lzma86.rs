@@ -0,0 +1,64 @@
+use std::io::{Read, Write, Cursor};
+use std::fs::File;
+
+extern crate lzma;
+
+// the control text with a `call` every 16 bytes
+fn control() -> Vec<u8> {
+	let mut reader  = File::open("tests/assets/a.txt").unwrap();
+	let mut control = Vec::new();
+
+	reader.read_to_end(&mut control).unwrap();
+
+	control.chunks(16).enumerate().flat_map(|(i, chunk)| {
+		let mut chunk = chunk.to_vec();
+		chunk.extend_from_slice(&[0xe8, i as u8, 0x10, 0x00, 0x00]);
+		chunk
+	}).collect()
+}
+
+#[test]
+fn read_x86() {
+	let mut reader = lzma::lzma86::read(File::open("tests/assets/a_x86.lzma86").unwrap()).unwrap();
+	let mut result = Vec::new();
+
+	assert_eq!(reader.filter(), lzma::lzma86::FILTER_X86);
+	reader.read_to_end(&mut result).unwrap();
+
+	assert_eq!(control(), result);
+}
+
+#[test]
+fn roundtrip() {
+	let data = control();
+
+	for &filter in &[lzma::lzma86::FILTER_NONE, lzma::lzma86::FILTER_X86] {
+		let mut writer = lzma::lzma86::write(Vec::new(), filter, lzma::Properties {
+			lc: 3, lp: 0, pb: 2, dictionary: 1 << 16, uncompressed: Some(data.len() as u64) }).unwrap();
+
+		writer.write_all(&data).unwrap();
+
+		let     buffer = writer.finish().unwrap();
+		let mut reader = lzma::lzma86::read(Cursor::new(buffer)).unwrap();
+		let mut result = Vec::new();
+
+		reader.read_to_end(&mut result).unwrap();
+
+		assert_eq!(data, result);
+	}
+}
+
+#[test]
+fn x86() {
+	use lzma::filter::{Filter, X86};
+
+	let     data   = control();
+	let mut buffer = data.clone();
+
+	let converted = X86::encoder().code(&mut buffer);
+	assert!(converted > data.len() - 5);
+	assert!(buffer != data);
+
+	X86::decoder().code(&mut buffer);
+	assert_eq!(data, buffer);
+}
@@ -0,0 +1,66 @@
+use std::io::{self, Read, Write, Cursor};
+use std::fs::File;
+
+extern crate lzma;
+
+fn control() -> Vec<u8> {
+	let mut reader  = File::open("tests/assets/a.txt").unwrap();
+	let mut control = Vec::new();
+
+	reader.read_to_end(&mut control).unwrap();
+
+	control
+}
+
+fn properties() -> lzma::Properties {
+	lzma::Properties { lc: 3, lp: 0, pb: 2, dictionary: 1 << 16, uncompressed: None }
+}
+
+#[test]
+fn roundtrip() {
+	let     data   = control();
+	let mut writer = lzma::microlzma::write(Vec::new(), properties(), None).unwrap();
+
+	writer.write_all(&data).unwrap();
+
+	let mut buffer = writer.finish().unwrap();
+	let     size   = buffer.len() as u64;
+
+	// trailing padding must be left alone
+	buffer.extend_from_slice(&[0; 16]);
+
+	let mut reader = lzma::microlzma::read(Cursor::new(&buffer), size, data.len() as u64, 1 << 16).unwrap();
+	let mut result = Vec::new();
+
+	assert_eq!(buffer[0], !0x5d);
+	reader.read_to_end(&mut result).unwrap();
+
+	assert_eq!(data, result);
+	assert_eq!(reader.consumed(), size);
+}
+
+#[test]
+fn limit() {
+	let mut seed = 42u32;
+	let     data = (0 .. 4096).map(|_| { seed = seed.wrapping_mul(1103515245).wrapping_add(12345); (seed >> 16) as u8 }).collect::<Vec<u8>>();
+
+	let mut writer = lzma::microlzma::write(Vec::new(), properties(), Some(512)).unwrap();
+
+	// the input past the limit is refused instead of being dropped silently
+	assert_eq!(writer.write_all(&data).unwrap_err().kind(), io::ErrorKind::WriteZero);
+	assert!(writer.is_exhausted());
+	assert_eq!(writer.write(&data).unwrap(), 0);
+
+	let encoded = writer.encoded();
+	let buffer  = writer.finish().unwrap();
+
+	assert!(buffer.len() <= 512);
+	assert!(encoded > 0 && encoded < data.len() as u64);
+
+	let mut reader = lzma::microlzma::read(Cursor::new(&buffer), buffer.len() as u64, encoded, 1 << 16).unwrap();
+	let mut result = Vec::new();
+
+	reader.read_to_end(&mut result).unwrap();
+
+	assert_eq!(&data[.. encoded as usize], &result[..]);
+}
@@ -0,0 +1,134 @@
+use std::io::{Read, Write, Cursor};
+use std::fs::File;
+
+extern crate lzma;
+
+fn control() -> Vec<u8> {
+	let mut reader  = File::open("tests/assets/a.txt").unwrap();
+	let mut control = Vec::new();
+
+	reader.read_to_end(&mut control).unwrap();
+
+	control
+}
+
+fn reversed() -> Vec<u8> {
+	control().into_iter().rev().collect()
+}
+
+fn extract<R: Read>(mut reader: lzma::nsis::Reader<R>) -> Vec<(lzma::nsis::Entry, Vec<u8>)> {
+	let mut files = Vec::new();
+
+	while let Some(entry) = reader.next_entry().unwrap() {
+		let mut content = Vec::new();
+		reader.read_to_end(&mut content).unwrap();
+
+		files.push((entry, content));
+	}
+
+	files
+}
+
+#[test]
+fn solid() {
+	let mut file   = File::open("tests/assets/a_solid.nsis").unwrap();
+	let mut header = [0u8; 5];
+
+	file.read_exact(&mut header).unwrap();
+	assert!(lzma::nsis::is_solid(&header));
+
+	let files = extract(lzma::nsis::Reader::solid(File::open("tests/assets/a_solid.nsis").unwrap()).unwrap());
+
+	assert_eq!(files.len(), 3);
+	assert_eq!(files[0].1, control());
+	assert_eq!(files[1].1, reversed());
+	assert_eq!(files[2].1, vec![]);
+	assert_eq!(files[1].0.offset, 4 + control().len() as u64);
+}
+
+#[test]
+fn non_solid() {
+	let mut file   = File::open("tests/assets/a_non_solid.nsis").unwrap();
+	let mut header = [0u8; 5];
+
+	file.read_exact(&mut header).unwrap();
+	assert!(!lzma::nsis::is_solid(&header));
+
+	let files = extract(lzma::nsis::Reader::non_solid(File::open("tests/assets/a_non_solid.nsis").unwrap()));
+
+	assert_eq!(files.len(), 2);
+	assert!(files[0].0.compressed);
+	assert_eq!(files[0].1, control());
+	assert!(!files[1].0.compressed);
+	assert_eq!(files[1].1, reversed());
+}
+
+#[test]
+fn is_solid() {
+	// dictionaries don't have to be powers of two
+	for &dictionary in &[1u32 << 12, 12 << 20, 24 << 20, 48 << 20, 1 << 30] {
+		let header = [0x5d, dictionary as u8, (dictionary >> 8) as u8, (dictionary >> 16) as u8, (dictionary >> 24) as u8];
+		assert!(lzma::nsis::is_solid(&header));
+	}
+
+	// other lc, lp and pb values
+	assert!(lzma::nsis::is_solid(&[0x00, 0x00, 0x00, 0x80, 0x00]));
+	assert!(!lzma::nsis::is_solid(&[225, 0x00, 0x00, 0x80, 0x00]));
+
+	// too small, or a compressed block length followed by its properties
+	assert!(!lzma::nsis::is_solid(&[0x5d, 0x00, 0x08, 0x00, 0x00]));
+	assert!(!lzma::nsis::is_solid(&[0x5d, 0x01, 0x00, 0x80, 0x5d]));
+	assert!(!lzma::nsis::is_solid(&[0x5d, 0x00, 0x10]));
+}
+
+#[test]
+fn list() {
+	let entries = lzma::nsis::list(File::open("tests/assets/a_solid.nsis").unwrap(), true).unwrap();
+	assert_eq!(entries.iter().map(|e| e.size).collect::<Vec<_>>(), vec![control().len() as u64, control().len() as u64, 0]);
+
+	// skipping files without reading them
+	let entries = lzma::nsis::list(File::open("tests/assets/a_non_solid.nsis").unwrap(), false).unwrap();
+	assert_eq!(entries.len(), 2);
+	assert_eq!(entries[1].size, control().len() as u64);
+}
+
+#[test]
+fn solid_written() {
+	let properties = lzma::Properties { lc: 3, lp: 0, pb: 2, dictionary: 1 << 16, uncompressed: None };
+
+	let mut buffer = Vec::new();
+	lzma::properties::write_model(&mut buffer, &properties).unwrap();
+
+	let mut writer = lzma::Writer::new(buffer, properties).unwrap();
+
+	for file in &[control(), reversed()] {
+		let length = file.len() as u32;
+
+		writer.write_all(&[length as u8, (length >> 8) as u8, (length >> 16) as u8, (length >> 24) as u8]).unwrap();
+		writer.write_all(file).unwrap();
+	}
+
+	let files = extract(lzma::nsis::Reader::solid(Cursor::new(writer.finish().unwrap())).unwrap());
+
+	assert_eq!(files.len(), 2);
+	assert_eq!(files[1].1, reversed());
+}
+
+#[test]
+fn next_after_error() {
+	let mut data = Vec::new();
+	File::open("tests/assets/a_non_solid.nsis").unwrap().read_to_end(&mut data).unwrap();
+
+	// cut in the middle of the first length
+	let mut reader = lzma::nsis::Reader::non_solid(Cursor::new(data[.. 2].to_vec()));
+	assert!(reader.next_entry().is_err());
+	assert!(reader.next_entry().is_err());
+	assert!(reader.read(&mut [0u8; 16]).is_err());
+
+	// invalid properties for the first compressed file
+	data[4] = 0xff;
+
+	let mut reader = lzma::nsis::Reader::non_solid(Cursor::new(data));
+	assert_eq!(reader.next_entry().unwrap_err().to_string(), lzma::Error::InvalidProperties.to_string());
+	assert_eq!(reader.next_entry().unwrap_err().to_string(), lzma::Error::InvalidProperties.to_string());
+}
@@ -0,0 +1,95 @@
+extern crate lzma;
+
+// deterministic data that doesn't compress on its own
+fn random(seed: u32, length: usize) -> Vec<u8> {
+	let mut state = seed;
+
+	(0 .. length).map(|_| {
+		state = state.wrapping_mul(1103515245).wrapping_add(12345);
+		(state >> 16) as u8
+	}).collect()
+}
+
+fn update(old: &[u8]) -> Vec<u8> {
+	let mut new = old.to_vec();
+
+	// change some bytes, insert a block and drop another
+	for i in (1000 .. new.len()).step_by(50000) {
+		new[i] ^= 0xff;
+	}
+
+	new.splice(70000 .. 70000, random(2, 3000));
+	new.drain(150000 .. 151000);
+
+	new
+}
+
+#[test]
+fn roundtrip() {
+	let old = random(1, 1 << 18);
+	let new = update(&old);
+
+	let patch = lzma::diff(&old, &new).unwrap();
+	assert!(patch.len() < 5000);
+
+	assert_eq!(lzma::patch(&old[..], &patch[..], Vec::new()).unwrap(), new);
+}
+
+#[test]
+fn empty() {
+	let old = random(1, 1000);
+
+	let patch = lzma::diff(&old, &[]).unwrap();
+	assert!(lzma::patch(&old[..], &patch[..], Vec::new()).unwrap().is_empty());
+
+	let patch = lzma::diff(&[], &old).unwrap();
+	assert_eq!(lzma::patch(&[][..], &patch[..], Vec::new()).unwrap(), old);
+}
+
+#[test]
+fn wrong_old() {
+	let old = random(1, 1 << 16);
+	let new = update(&random(1, 1 << 18))[.. 1 << 16].to_vec();
+
+	let patch = lzma::diff(&old, &new).unwrap();
+	let other = random(3, 1 << 16);
+
+	match lzma::patch(&other[..], &patch[..], Vec::new()) {
+		Err(lzma::Error::Checksum) =>
+			(),
+
+		_ =>
+			panic!("patch applied to the wrong file")
+	}
+
+	// a shorter or longer file
+	assert!(lzma::patch(&old[.. 1000], &patch[..], Vec::new()).is_err());
+	assert!(lzma::patch(&random(1, 1 << 17)[..], &patch[..], Vec::new()).is_err());
+}
+
+#[test]
+fn memlimit() {
+	let old = random(1, 1 << 18);
+	let new = update(&old);
+
+	match lzma::diff_with_memlimit(&old, &new, 1 << 20) {
+		Err(lzma::Error::MemoryLimit { needed, .. }) =>
+			assert!(needed > (old.len() + new.len()) as u64 * 4),
+
+		_ =>
+			panic!("memory limit not enforced")
+	}
+
+	let patch = lzma::diff_with_memlimit(&old, &new, 1 << 24).unwrap();
+
+	// the window covers both files
+	match lzma::patch_with_memlimit(&old[..], &patch[..], Vec::new(), 1 << 18) {
+		Err(lzma::Error::MemoryLimit { needed, .. }) =>
+			assert!(needed > (old.len() + new.len()) as u64),
+
+		_ =>
+			panic!("memory limit not enforced")
+	}
+
+	assert_eq!(lzma::patch_with_memlimit(&old[..], &patch[..], Vec::new(), 1 << 20).unwrap(), new);
+}
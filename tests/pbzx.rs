@@ -0,0 +1,54 @@
+use std::io::Read;
+use std::fs::File;
+
+extern crate lzma;
+
+#[test]
+fn read() {
+	let mut reader  = lzma::pbzx::open("tests/assets/a.pbzx").unwrap();
+	let mut result  = Vec::new();
+	let mut control = Vec::new();
+
+	reader.read_to_end(&mut result).unwrap();
+	File::open("tests/assets/a.txt").unwrap().read_to_end(&mut control).unwrap();
+
+	assert_eq!(control, result);
+	assert_eq!(reader.chunks(), 2);
+}
+
+#[test]
+fn truncated() {
+	let mut buffer = Vec::new();
+	File::open("tests/assets/a.pbzx").unwrap().read_to_end(&mut buffer).unwrap();
+	buffer.truncate(buffer.len() - 10);
+
+	let mut reader = lzma::pbzx::read(&buffer[..]).unwrap();
+	assert!(reader.read_to_end(&mut Vec::new()).is_err());
+}
+
+#[test]
+fn bad_magic() {
+	assert!(lzma::pbzx::open("tests/assets/a.xz").is_err());
+}
+
+#[test]
+fn read_after_error() {
+	let mut buffer = Vec::new();
+	File::open("tests/assets/a.pbzx").unwrap().read_to_end(&mut buffer).unwrap();
+	buffer.truncate(buffer.len() - 10);
+
+	let mut reader = lzma::pbzx::read(&buffer[..]).unwrap();
+	assert!(reader.read_to_end(&mut Vec::new()).is_err());
+	assert!(reader.read(&mut [0u8; 16]).is_err());
+	reader.into_inner();
+
+	// a chunk header that isn't all there
+	let mut buffer = b"pbzx".to_vec();
+	buffer.extend_from_slice(&[0, 0, 0, 0, 0x01, 0, 0, 0]);
+	buffer.extend_from_slice(&[0, 0, 0]);
+
+	let mut reader = lzma::pbzx::read(&buffer[..]).unwrap();
+	assert!(reader.read(&mut [0u8; 16]).is_err());
+	assert!(reader.read(&mut [0u8; 16]).is_err());
+	assert_eq!(reader.into_inner().len(), 0);
+}
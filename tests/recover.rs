@@ -0,0 +1,118 @@
+use std::io::{Read, Write, Cursor};
+use std::fs::File;
+
+extern crate lzma;
+
+use lzma::{Error, Format};
+
+fn text() -> Vec<u8> {
+	let mut data = Vec::new();
+	File::open("tests/assets/a.txt").unwrap().read_to_end(&mut data).unwrap();
+
+	data
+}
+
+#[test]
+fn intact() {
+	for path in &["tests/assets/a.lzma", "tests/assets/a_sha256_blocks.xz", "tests/assets/a_members.lz"] {
+		let mut output   = Vec::new();
+		let     recovery = lzma::recover(File::open(path).unwrap(), &mut output).unwrap();
+
+		assert!(recovery.is_ok());
+		assert_eq!(recovery.recovered, 327);
+		assert_eq!(output, text());
+	}
+}
+
+#[test]
+fn lzma() {
+	let mut output   = Vec::new();
+	let     recovery = lzma::recover(File::open("tests/assets/bad_corrupted.lzma").unwrap(), &mut output).unwrap();
+
+	assert_eq!(recovery.format, Format::Lzma);
+	assert_eq!(recovery.recovered, output.len() as u64);
+	assert_eq!(recovery.damaged.len(), 1);
+	assert_eq!((recovery.damaged[0].offset, recovery.damaged[0].position), (117, 267));
+
+	// the good prefix is kept, the damage shows up a few bytes before the failure
+	assert_eq!(&output[.. 200], &text()[.. 200]);
+}
+
+#[test]
+fn xz() {
+	let data = (0 .. 4000u32).map(|i| (i * 7 % 251) as u8).collect::<Vec<_>>();
+
+	let mut writer = lzma::xz::write(Vec::new(), lzma::Properties { lc: 3, lp: 0, pb: 2, dictionary: 1 << 16, uncompressed: None }).unwrap();
+	writer.set_block_size(1000).unwrap();
+	writer.write_all(&data).unwrap();
+
+	let mut stream = writer.finish().unwrap();
+	let     second = match lzma::Info::read(Cursor::new(&stream)).unwrap() {
+		lzma::Info::Xz(streams) => streams[0].blocks[1].clone(),
+		_                       => unreachable!(),
+	};
+
+	// damage the compressed data of the second block
+	let middle = (second.offset + second.header + second.compressed / 2) as usize;
+	stream[middle] ^= 0x55;
+
+	let mut output   = Vec::new();
+	let     recovery = lzma::recover(Cursor::new(&stream), &mut output).unwrap();
+
+	assert_eq!(recovery.damaged.len(), 1);
+	assert_eq!(recovery.damaged[0].offset, second.offset);
+	assert_eq!(recovery.recovered, output.len() as u64);
+	assert_eq!(recovery.recovered + recovery.damaged[0].lost.unwrap(), 4000);
+
+	// the blocks around the damaged one are still there
+	assert_eq!(&output[.. 1000], &data[.. 1000]);
+	assert_eq!(&output[output.len() - 2000 ..], &data[2000 ..]);
+}
+
+#[test]
+fn lzip() {
+	let mut stream = Vec::new();
+	File::open("tests/assets/a_members.lz").unwrap().read_to_end(&mut stream).unwrap();
+
+	// damage the first of the two members
+	stream[40] ^= 0x55;
+
+	let mut output   = Vec::new();
+	let     recovery = lzma::recover(Cursor::new(&stream), &mut output).unwrap();
+
+	assert_eq!(recovery.format, Format::Lzip);
+	assert_eq!(recovery.damaged.len(), 1);
+	assert_eq!(recovery.damaged[0].offset, 0);
+	assert_eq!(recovery.recovered + recovery.damaged[0].lost.unwrap(), 327);
+	assert_eq!(&output[output.len() - 227 ..], &text()[100 ..]);
+
+	match lzma::recover(File::open("tests/assets/bad_check.lz").unwrap(), Vec::new()).unwrap().damaged[0].error {
+		Error::Checksum => (),
+		_               => unreachable!(),
+	}
+}
+
+#[test]
+fn memlimit() {
+	for path in &["tests/assets/a_sha256_blocks.xz", "tests/assets/a_members.lz"] {
+		let recovery = lzma::recover_with_memlimit(File::open(path).unwrap(), Vec::new(), 4096).unwrap();
+
+		assert_eq!(recovery.recovered, 0);
+		assert!(!recovery.damaged.is_empty());
+
+		for damage in &recovery.damaged {
+			match damage.error {
+				Error::MemoryLimit { limit: 4096, .. } => (),
+				_                                      => panic!("unexpected error: {}", damage.error),
+			}
+		}
+	}
+
+	assert!(lzma::recover_with_memlimit(File::open("tests/assets/a.lzma").unwrap(), Vec::new(), 4096).is_err());
+}
+
+#[test]
+fn unsupported() {
+	assert!(lzma::recover(File::open("tests/assets/a.txt").unwrap(), Vec::new()).is_err());
+	assert!(lzma::recover(File::open("tests/assets/a.pbzx").unwrap(), Vec::new()).is_err());
+}
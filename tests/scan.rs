@@ -0,0 +1,144 @@
+use std::io::{Read, Write};
+use std::fs::File;
+
+extern crate lzma;
+
+use lzma::Format;
+
+fn asset(path: &str) -> Vec<u8> {
+	let mut buffer = Vec::new();
+	File::open(path).unwrap().read_to_end(&mut buffer).unwrap();
+
+	buffer
+}
+
+// deterministic filler that is neither compressed nor all zeroes
+fn junk(seed: u32, length: usize) -> Vec<u8> {
+	let mut state = seed;
+
+	(0 .. length).map(|_| {
+		state = state.wrapping_mul(1103515245).wrapping_add(12345);
+		(state >> 16) as u8
+	}).collect()
+}
+
+#[test]
+fn scan() {
+	let lzma = asset("tests/assets/a.lzma");
+	let xz   = asset("tests/assets/a.xz");
+	let lzip = asset("tests/assets/a.lz");
+
+	let mut blob = junk(1, 1000);
+	blob.extend_from_slice(&lzma);
+	blob.extend_from_slice(&junk(2, 333));
+	blob.extend_from_slice(&xz);
+	blob.extend_from_slice(&junk(3, 17));
+	blob.extend_from_slice(&lzip);
+	blob.extend_from_slice(&junk(4, 100));
+
+	let candidates = lzma::scan(&blob);
+	let found      = candidates.iter().map(|c| (c.offset, c.format, c.compressed)).collect::<Vec<_>>();
+
+	assert_eq!(found, vec![
+		(1000, Format::Lzma, lzma.len() as u64),
+		(1000 + lzma.len() + 333, Format::Xz, xz.len() as u64),
+		(1000 + lzma.len() + 333 + xz.len() + 17, Format::Lzip, lzip.len() as u64),
+	]);
+
+	for candidate in &candidates {
+		assert_eq!(candidate.decompressed, Some(327));
+	}
+
+	assert_eq!(candidates[0].properties.unwrap().dictionary, 1 << 23);
+	assert_eq!(candidates[2].properties.unwrap().dictionary, 1 << 16);
+}
+
+#[test]
+fn seven_zip() {
+	let mut header = vec![b'7', b'z', 0xbc, 0xaf, 0x27, 0x1c, 0x00, 0x04];
+	let     fields = [10, 0, 0, 0, 0, 0, 0, 0, 6, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+
+	let crc = lzma::check::crc32(&fields);
+	header.extend_from_slice(&[crc as u8, (crc >> 8) as u8, (crc >> 16) as u8, (crc >> 24) as u8]);
+	header.extend_from_slice(&fields);
+
+	let mut blob = junk(5, 50);
+	blob.extend_from_slice(&header);
+	blob.extend_from_slice(&junk(6, 16));
+
+	let candidates = lzma::scan(&blob);
+
+	assert_eq!(candidates.len(), 1);
+	assert_eq!(candidates[0].offset, 50);
+	assert_eq!(candidates[0].format, Format::SevenZip);
+	assert_eq!(candidates[0].compressed, 48);
+	assert_eq!(candidates[0].decompressed, None);
+}
+
+#[test]
+fn truncated() {
+	let lzma = asset("tests/assets/a.lzma");
+	let xz   = asset("tests/assets/a.xz");
+
+	for &(ref stream, format) in &[(lzma, Format::Lzma), (xz, Format::Xz)] {
+		let mut blob = junk(7, 200);
+		blob.extend_from_slice(&stream[.. stream.len() - 40]);
+
+		let candidates = lzma::scan(&blob);
+
+		assert_eq!(candidates.len(), 1);
+		assert_eq!(candidates[0].offset, 200);
+		assert_eq!(candidates[0].format, format);
+		assert!(candidates[0].compressed <= stream.len() as u64 - 40);
+		assert!(candidates[0].error.is_some());
+	}
+}
+
+#[test]
+fn long_stream() {
+	let mut writer = lzma::write(Vec::new(), lzma::Properties {
+		lc: 3, lp: 0, pb: 2, dictionary: 1 << 16, uncompressed: None }).unwrap();
+	writer.write_all(&vec![0u8; 3 << 20]).unwrap();
+
+	let stream = writer.finish().unwrap();
+	let xz     = asset("tests/assets/a.xz");
+
+	let mut blob = junk(8, 10);
+	blob.extend_from_slice(&stream);
+	blob.extend_from_slice(&xz);
+
+	// decoded to the end, so the scan carries on right after it
+	let candidates = lzma::scan(&blob);
+
+	assert_eq!(candidates.len(), 2);
+	assert_eq!(candidates[0].offset, 10);
+	assert_eq!(candidates[0].compressed, stream.len() as u64);
+	assert_eq!(candidates[0].decompressed, Some(3 << 20));
+	assert!(candidates[0].error.is_none());
+	assert_eq!(candidates[1].offset, 10 + stream.len());
+	assert_eq!(candidates[1].format, Format::Xz);
+}
+
+#[test]
+fn memlimit() {
+	let lzma = asset("tests/assets/a.lzma");
+	let xz   = asset("tests/assets/a.xz");
+
+	let mut blob = junk(9, 100);
+	blob.extend_from_slice(&lzma);
+	blob.extend_from_slice(&xz);
+
+	let candidates = lzma::scan_with_memlimit(&blob, 4096);
+
+	assert_eq!(candidates.len(), 1);
+	assert_eq!(candidates[0].offset, 100 + lzma.len());
+	assert_eq!(candidates[0].format, Format::Xz);
+
+	match candidates[0].error {
+		Some(lzma::Error::MemoryLimit { limit, .. }) =>
+			assert_eq!(limit, 4096),
+
+		ref error =>
+			panic!("unexpected {:?}", error)
+	}
+}
@@ -0,0 +1,149 @@
+use std::io::{Read, Write, Seek, SeekFrom, Cursor};
+
+extern crate lzma;
+
+fn data() -> Vec<u8> {
+	let mut data = Vec::new();
+
+	for i in 0 .. 40000 {
+		writeln!(data, "line {} of the log, value {}", i, i * 7919 % 10007).unwrap();
+	}
+
+	data
+}
+
+fn encode(data: &[u8], uncompressed: Option<u64>) -> Vec<u8> {
+	let mut writer = lzma::write(Vec::new(), lzma::Properties {
+		lc: 3, lp: 0, pb: 2, dictionary: 1 << 16, uncompressed: uncompressed }).unwrap();
+	writer.write_all(data).unwrap();
+
+	writer.finish().unwrap()
+}
+
+fn read_at<R: Read + Seek>(reader: &mut lzma::Seekable<R>, position: u64, length: usize) -> Vec<u8> {
+	assert_eq!(reader.seek(SeekFrom::Start(position)).unwrap(), position);
+
+	let mut buffer = vec![0u8; length];
+	reader.read_exact(&mut buffer).unwrap();
+
+	buffer
+}
+
+#[test]
+fn seek() {
+	let data   = data();
+	let mut reader = lzma::Seekable::new(Cursor::new(encode(&data, Some(data.len() as u64))), 1 << 17).unwrap();
+
+	for &position in &[500000, 3, 1000000, 999999, 250000, 0, 700001] {
+		assert_eq!(read_at(&mut reader, position, 1000), &data[position as usize .. position as usize + 1000]);
+	}
+
+	assert!(reader.checkpoints().len() > 5);
+
+	assert_eq!(reader.seek(SeekFrom::Current(-500)).unwrap(), 700501);
+	assert_eq!(reader.seek(SeekFrom::End(-10)).unwrap(), data.len() as u64 - 10);
+
+	let mut rest = Vec::new();
+	reader.read_to_end(&mut rest).unwrap();
+	assert_eq!(rest, &data[data.len() - 10 ..]);
+}
+
+#[test]
+fn seek_eos() {
+	let data   = data();
+	let mut reader = lzma::Seekable::new(Cursor::new(encode(&data, None)), 1 << 17).unwrap();
+
+	assert_eq!(reader.seek(SeekFrom::End(0)).unwrap(), data.len() as u64);
+	assert_eq!(reader.seek(SeekFrom::Start(u64::MAX)).unwrap(), data.len() as u64);
+	assert_eq!(read_at(&mut reader, 123456, 100), &data[123456 .. 123556]);
+}
+
+#[test]
+fn memlimit() {
+	let data       = data();
+	let compressed = encode(&data, Some(data.len() as u64));
+
+	match lzma::Seekable::new_with_memlimit(Cursor::new(compressed.clone()), 1 << 17, 4096) {
+		Err(lzma::Error::MemoryLimit { .. }) =>
+			(),
+
+		_ =>
+			panic!("memory limit not enforced")
+	}
+
+	let mut reader = lzma::Seekable::new_with_memlimit(Cursor::new(compressed), 1 << 17, 1 << 24).unwrap();
+	assert_eq!(read_at(&mut reader, 250000, 100), &data[250000 .. 250100]);
+}
+
+#[test]
+fn index() {
+	let data       = data();
+	let compressed = encode(&data, Some(data.len() as u64));
+
+	let mut reader = lzma::Seekable::new(Cursor::new(compressed.clone()), 1 << 17).unwrap();
+	reader.seek(SeekFrom::End(0)).unwrap();
+
+	let mut index = Vec::new();
+	reader.save_index(&mut index).unwrap();
+
+	let mut reader = lzma::Seekable::new(Cursor::new(compressed), 1 << 20).unwrap();
+	reader.load_index(Cursor::new(&index)).unwrap();
+
+	assert_eq!(reader.interval(), 1 << 17);
+	assert!(reader.checkpoints().len() > 5);
+	assert_eq!(read_at(&mut reader, 900000, 1000), &data[900000 .. 901000]);
+
+	assert!(reader.load_index(Cursor::new(&index[.. index.len() - 1])).is_err());
+	assert!(reader.load_index(Cursor::new(&index[1 ..])).is_err());
+}
+
+#[test]
+fn index_corrupted() {
+	let data       = &data()[.. 200000];
+	let compressed = encode(data, Some(data.len() as u64));
+
+	let mut reader = lzma::Seekable::new(Cursor::new(compressed.clone()), 1 << 15).unwrap();
+	reader.seek(SeekFrom::End(0)).unwrap();
+
+	let mut index = Vec::new();
+	reader.save_index(&mut index).unwrap();
+
+	// rep0 of the last checkpoint, right before the state probabilities
+	let rep0 = index.len() - 2 * (192 + 4 * 12 + 192) - 16;
+
+	for &distance in &[1u32 << 16, 1 << 20, u32::MAX] {
+		let mut corrupted = index.clone();
+		corrupted[rep0 .. rep0 + 4].copy_from_slice(&distance.to_le_bytes());
+
+		assert!(reader.load_index(Cursor::new(&corrupted)).is_err());
+	}
+
+	// the checkpoints take the end of the index
+	let first = reader.checkpoints().iter().fold(index.len(), |offset, checkpoint| {
+		let mut saved = Vec::new();
+		checkpoint.write(&mut saved).unwrap();
+
+		offset - saved.len()
+	});
+
+	// whatever gets through must fail cleanly instead of panicking, the first
+	// checkpoint starts with its offsets, range and window, the last ends with
+	// its state and distances before the state probabilities
+	let header = first - 1 .. first + 120;
+	let tail   = rep0 - 8 .. rep0 + 16;
+
+	for offset in header.chain(tail) {
+		let mut corrupted = index.clone();
+		corrupted[offset] ^= 0xa5;
+
+		let mut reader = lzma::Seekable::new(Cursor::new(compressed.clone()), 1 << 15).unwrap();
+
+		if reader.load_index(Cursor::new(&corrupted)).is_ok() {
+			for &position in &[0, 100000, 199000] {
+				if reader.seek(SeekFrom::Start(position)).is_ok() {
+					let _ = reader.read(&mut [0u8; 1000]);
+				}
+			}
+		}
+	}
+}
@@ -0,0 +1,55 @@
+use std::io::{Read, Write, Cursor};
+use std::fs::File;
+
+extern crate lzma;
+
+fn control() -> Vec<u8> {
+	let mut reader  = File::open("tests/assets/a.txt").unwrap();
+	let mut control = Vec::new();
+
+	reader.read_to_end(&mut control).unwrap();
+
+	let mut file = b"FWS\x0a".to_vec();
+	let     size = control.len() as u32 + 8;
+
+	file.extend_from_slice(&[size as u8, (size >> 8) as u8, (size >> 16) as u8, (size >> 24) as u8]);
+	file.extend_from_slice(&control);
+
+	file
+}
+
+#[test]
+fn read() {
+	let mut reader = lzma::swf::read(File::open("tests/assets/a.swf").unwrap()).unwrap();
+	let mut result = Vec::new();
+
+	assert_eq!(reader.version(), 10);
+	reader.read_to_end(&mut result).unwrap();
+
+	assert_eq!(control(), result);
+}
+
+#[test]
+fn roundtrip() {
+	let     file   = control();
+	let mut writer = lzma::swf::write(Vec::new(), lzma::Properties {
+		lc: 3, lp: 0, pb: 2, dictionary: 1 << 16, uncompressed: None }).unwrap();
+
+	writer.write_all(&file).unwrap();
+
+	let     buffer = writer.finish().unwrap();
+	let mut reader = lzma::swf::read(Cursor::new(&buffer)).unwrap();
+	let mut result = Vec::new();
+
+	assert_eq!(&buffer[.. 3], b"ZWS");
+	assert_eq!(reader.compressed() as usize, buffer.len() - 17);
+	reader.read_to_end(&mut result).unwrap();
+
+	assert_eq!(file, result);
+}
+
+#[test]
+#[should_panic]
+fn bad_magic() {
+	lzma::swf::read(File::open("tests/assets/a.lzma").unwrap()).unwrap();
+}
@@ -0,0 +1,81 @@
+use std::io::{BufReader, Cursor};
+use std::fs::File;
+
+extern crate lzma;
+
+use lzma::{Error, Format, Report};
+
+fn verify(path: &str) -> Report {
+	lzma::verify(BufReader::new(File::open(path).unwrap())).unwrap()
+}
+
+#[test]
+fn lzma() {
+	let report = verify("tests/assets/a.lzma");
+	assert!(report.is_ok());
+	assert_eq!(report.format, Format::Lzma);
+	assert_eq!((report.compressed, report.uncompressed, report.declared, report.marker), (117, 327, Some(327), Some(false)));
+
+	let report = verify("tests/assets/a_eos.lzma");
+	assert!(report.is_ok());
+	assert_eq!((report.uncompressed, report.declared, report.marker), (327, None, Some(true)));
+
+	let report = verify("tests/assets/a_eos_and_size.lzma");
+	assert!(report.is_ok());
+	assert_eq!((report.uncompressed, report.marker), (327, Some(true)));
+
+	// a buffer shorter than the bytes the detection needs
+	let report = lzma::verify(BufReader::with_capacity(1, File::open("tests/assets/a.lzma").unwrap())).unwrap();
+	assert!(report.is_ok());
+	assert_eq!((report.compressed, report.uncompressed), (117, 327));
+}
+
+#[test]
+fn xz() {
+	let report = verify("tests/assets/a_sha256_blocks.xz");
+	assert!(report.is_ok());
+	assert_eq!((report.compressed, report.uncompressed, report.checksums), (432, 327, 4));
+
+	let report = verify("tests/assets/a_concatenated.xz");
+	assert!(report.is_ok());
+	assert_eq!((report.uncompressed, report.checksums), (327, 1));
+}
+
+#[test]
+fn lzip() {
+	let report = verify("tests/assets/a_members.lz");
+	assert!(report.is_ok());
+	assert_eq!((report.compressed, report.uncompressed, report.declared, report.checksums), (200, 327, Some(327), 2));
+}
+
+#[test]
+fn corrupted() {
+	let report = verify("tests/assets/bad_corrupted.lzma");
+	assert_eq!(report.uncompressed, 267);
+
+	match report.error {
+		Some(Error::Corrupted) => (),
+		_                      => unreachable!(),
+	}
+
+	assert_eq!(report.failure.unwrap().position.uncompressed, 267);
+
+	assert!(verify("tests/assets/bad_incorrect_size.lzma").error.is_some());
+	assert!(verify("tests/assets/bad_eos_incorrect_size.lzma").error.is_some());
+
+	match verify("tests/assets/bad_check.xz").error {
+		Some(Error::Checksum) => (),
+		_                     => unreachable!(),
+	}
+
+	match verify("tests/assets/bad_check.lz").error {
+		Some(Error::Checksum) => (),
+		_                     => unreachable!(),
+	}
+}
+
+#[test]
+fn unsupported() {
+	assert!(lzma::verify(Cursor::new(&b"hello"[..])).is_err());
+	assert!(lzma::verify(BufReader::new(File::open("tests/assets/a.pbzx").unwrap())).is_err());
+}
@@ -0,0 +1,518 @@
+use std::io::{Read, Write, Cursor};
+use std::fs::File;
+
+extern crate lzma;
+
+fn control() -> Vec<u8> {
+	let mut reader  = File::open("tests/assets/a.txt").unwrap();
+	let mut control = Vec::new();
+
+	reader.read_to_end(&mut control).unwrap();
+
+	control
+}
+
+fn decode(path: &str) -> Vec<u8> {
+	let mut reader = lzma::xz::open(path).unwrap();
+	let mut result = Vec::new();
+
+	reader.read_to_end(&mut result).unwrap();
+	assert!(reader.is_finished());
+
+	result
+}
+
+#[test]
+fn a() {
+	assert_eq!(control(), decode("tests/assets/a.xz"));
+}
+
+#[test]
+fn sha256_blocks() {
+	assert_eq!(control(), decode("tests/assets/a_sha256_blocks.xz"));
+}
+
+#[test]
+fn delta() {
+	assert_eq!(control(), decode("tests/assets/a_delta.xz"));
+}
+
+#[test]
+fn concatenated() {
+	let mut reader = lzma::xz::open("tests/assets/a_concatenated.xz").unwrap();
+	let mut result = Vec::new();
+
+	assert_eq!(reader.flags().check, lzma::xz::Check::Crc32);
+	reader.read_to_end(&mut result).unwrap();
+
+	assert_eq!(control(), result);
+	assert_eq!(reader.streams(), 2);
+	assert_eq!(reader.flags().check, lzma::xz::Check::None);
+}
+
+#[test]
+fn bad_check() {
+	let mut reader = lzma::xz::open("tests/assets/bad_check.xz").unwrap();
+	let     error  = reader.read_to_end(&mut Vec::new()).unwrap_err();
+
+	assert_eq!(error.to_string(), lzma::Error::Checksum.to_string());
+}
+
+#[test]
+fn bad_magic() {
+	assert!(lzma::xz::open("tests/assets/a.lzma").is_err());
+}
+
+#[test]
+fn memlimit() {
+	let mut reader = lzma::xz::Reader::with_memlimit(File::open("tests/assets/a.xz").unwrap(), 4096).unwrap();
+	let     error  = reader.read_to_end(&mut Vec::new()).unwrap_err();
+
+	match error.into_inner().unwrap().downcast::<lzma::Error>().map(|e| *e) {
+		Ok(lzma::Error::MemoryLimit { limit: 4096, .. }) =>
+			(),
+
+		_ =>
+			panic!("memory limit not enforced")
+	}
+}
+
+#[test]
+fn read_after_error() {
+	let mut reader = lzma::xz::Reader::with_memlimit(File::open("tests/assets/a.xz").unwrap(), 4096).unwrap();
+	assert!(reader.read_to_end(&mut Vec::new()).is_err());
+
+	// the error sticks and the stream is still there
+	let error = reader.read(&mut [0u8; 16]).unwrap_err();
+	assert!(error.to_string().contains("memory"));
+	assert_eq!(reader.compressed(), 32);
+	reader.into_inner();
+
+	let mut reader = lzma::xz::open("tests/assets/bad_check.xz").unwrap();
+	assert!(reader.read_to_end(&mut Vec::new()).is_err());
+	assert_eq!(reader.read(&mut [0u8; 16]).unwrap_err().to_string(), lzma::Error::Checksum.to_string());
+}
+
+#[test]
+fn lzma2_preset() {
+	// an uncompressed chunk that doesn't reset the dictionary
+	let stream = [0x02, 0x00, 0x02, b'a', b'b', b'c', 0x00];
+
+	let mut reader = lzma::lzma2::Reader::with_preset(&stream[..], 1 << 16, b"preset").unwrap();
+	let mut result = Vec::new();
+
+	reader.read_to_end(&mut result).unwrap();
+	assert_eq!(&result[..], b"abc");
+
+	let mut reader = lzma::lzma2::read(&stream[..], 1 << 16).unwrap();
+	assert!(reader.read_to_end(&mut Vec::new()).is_err());
+}
+
+fn parallel(path: &str, threads: usize) -> Vec<u8> {
+	let mut reader = lzma::xz::Parallel::new(File::open(path).unwrap(), threads).unwrap();
+	let mut result = Vec::new();
+
+	reader.read_to_end(&mut result).unwrap();
+
+	result
+}
+
+fn indexed(path: &str, threads: usize) -> Vec<u8> {
+	let mut reader = lzma::xz::Parallel::indexed(File::open(path).unwrap(), threads).unwrap();
+	let mut result = Vec::new();
+
+	reader.read_to_end(&mut result).unwrap();
+
+	result
+}
+
+#[test]
+fn parallel_blocks() {
+	for &threads in &[1, 2, 3, 8] {
+		assert_eq!(control(), parallel("tests/assets/a_sha256_blocks.xz", threads));
+		assert_eq!(control(), parallel("tests/assets/a_crc32_blocks.xz", threads));
+		assert_eq!(control(), parallel("tests/assets/a_concatenated.xz", threads));
+
+		assert_eq!(control(), indexed("tests/assets/a_crc32_blocks.xz", threads));
+		assert_eq!(control(), indexed("tests/assets/a_concatenated.xz", threads));
+	}
+}
+
+#[test]
+fn parallel_bad() {
+	let mut reader = lzma::xz::Parallel::new(File::open("tests/assets/bad_check.xz").unwrap(), 2).unwrap();
+	let     error  = reader.read_to_end(&mut Vec::new()).unwrap_err();
+
+	assert_eq!(error.to_string(), lzma::Error::Checksum.to_string());
+
+	// a bit flipped in a block decoded by a worker
+	let mut buffer = Vec::new();
+	File::open("tests/assets/a_sha256_blocks.xz").unwrap().read_to_end(&mut buffer).unwrap();
+	buffer[150] ^= 0x10;
+
+	let mut reader = lzma::xz::Parallel::new(Cursor::new(&buffer), 2).unwrap();
+	assert!(reader.read_to_end(&mut Vec::new()).is_err());
+
+	// a truncated index
+	let length = buffer.len();
+	assert!(lzma::xz::Parallel::indexed(Cursor::new(&buffer[.. length - 4]), 2).is_err());
+}
+
+#[test]
+fn parallel_read_after_error() {
+	let mut reader = lzma::xz::Parallel::with_memlimit(File::open("tests/assets/a.xz").unwrap(), 2, 4096).unwrap();
+	assert!(reader.read_to_end(&mut Vec::new()).is_err());
+
+	let error = reader.read(&mut [0u8; 16]).unwrap_err();
+	assert!(error.to_string().contains("memory"));
+
+	let mut reader = lzma::xz::Parallel::new(File::open("tests/assets/bad_check.xz").unwrap(), 2).unwrap();
+	assert!(reader.read_to_end(&mut Vec::new()).is_err());
+	assert_eq!(reader.read(&mut [0u8; 16]).unwrap_err().to_string(), lzma::Error::Checksum.to_string());
+}
+
+fn encode(data: &[u8], threads: usize, block: usize) -> Vec<u8> {
+	let mut writer = lzma::xz::Writer::new(Vec::new(), lzma::Properties {
+		lc: 3, lp: 0, pb: 2, dictionary: 1 << 16, uncompressed: None }, threads).unwrap();
+	writer.set_block_size(block).unwrap();
+	writer.write_all(data).unwrap();
+
+	writer.finish().unwrap()
+}
+
+#[test]
+fn parallel_encode() {
+	let control    = control();
+	let compressed = encode(&control, 1, 40000);
+
+	for &threads in &[2, 3, 8] {
+		assert_eq!(compressed, encode(&control, threads, 40000));
+	}
+
+	let mut result = Vec::new();
+	lzma::xz::read(Cursor::new(&compressed)).unwrap().read_to_end(&mut result).unwrap();
+	assert_eq!(control, result);
+
+	assert_eq!(control, indexed_cursor(&compressed, 3));
+
+	// a single block bigger than a LZMA2 chunk
+	let mut large = Vec::new();
+
+	for i in 0 .. 200000 {
+		writeln!(large, "{} {}", i, i * 7919 % 10007).unwrap();
+	}
+
+	let mut result = Vec::new();
+	lzma::xz::read(Cursor::new(encode(&large, 2, 1 << 22))).unwrap().read_to_end(&mut result).unwrap();
+	assert_eq!(large, result);
+
+	let mut result = Vec::new();
+	lzma::xz::read(Cursor::new(encode(&[], 2, 1000))).unwrap().read_to_end(&mut result).unwrap();
+	assert!(result.is_empty());
+}
+
+fn indexed_cursor(data: &[u8], threads: usize) -> Vec<u8> {
+	let mut reader = lzma::xz::Parallel::indexed(Cursor::new(data), threads).unwrap();
+	let mut result = Vec::new();
+
+	reader.read_to_end(&mut result).unwrap();
+
+	result
+}
+
+// a LZMA2 stream resetting the dictionary every few kilobytes
+fn lzma2_segments(data: &[u8]) -> Vec<u8> {
+	let mut stream = Vec::new();
+
+	for piece in data.chunks(20000) {
+		let mut writer = lzma::lzma2::write(Vec::new(), lzma::Properties {
+			lc: 3, lp: 0, pb: 2, dictionary: 1 << 16, uncompressed: None }).unwrap();
+		writer.write_all(piece).unwrap();
+
+		let encoded = writer.finish().unwrap();
+		stream.extend_from_slice(&encoded[.. encoded.len() - 1]);
+	}
+
+	stream.push(0x00);
+	stream
+}
+
+#[test]
+fn lzma2_parallel() {
+	let control = control();
+	let stream  = lzma2_segments(&control);
+
+	let mut result = Vec::new();
+	lzma::lzma2::read(&stream[..], 1 << 16).unwrap().read_to_end(&mut result).unwrap();
+	assert_eq!(control, result);
+
+	for &threads in &[1, 2, 3] {
+		let mut trailing = stream.clone();
+		trailing.extend_from_slice(b"trailing");
+
+		let mut reader = lzma::lzma2::Parallel::new(&trailing[..], 1 << 16, threads).unwrap();
+		let mut result = Vec::new();
+
+		reader.read_to_end(&mut result).unwrap();
+		assert_eq!(control, result);
+		assert!(reader.is_finished());
+		assert_eq!(reader.into_inner(), b"trailing");
+	}
+
+	// the first chunk has to reset the dictionary
+	let mut reader = lzma::lzma2::Parallel::new(&stream[1 ..], 1 << 16, 2).unwrap();
+	assert!(reader.read_to_end(&mut Vec::new()).is_err());
+
+	// a segment too big to be held in memory between small ones is decoded in
+	// place
+	let mut large = Vec::new();
+
+	while (large.len() as u64) <= lzma::lzma2::SEGMENT_MAXIMUM {
+		large.extend_from_slice(&control);
+		large.extend_from_slice(&noise(1000));
+	}
+
+	let mut writer = lzma::lzma2::write(Vec::new(), lzma::Properties {
+		lc: 3, lp: 0, pb: 2, dictionary: 1 << 16, uncompressed: None }).unwrap();
+	writer.write_all(&large).unwrap();
+
+	let mut mixed = stream[.. stream.len() - 1].to_vec();
+	mixed.extend_from_slice(&writer.finish().unwrap());
+	mixed.pop();
+	mixed.extend_from_slice(&stream);
+	mixed.extend_from_slice(b"trailing");
+
+	let mut expected = control.clone();
+	expected.extend_from_slice(&large);
+	expected.extend_from_slice(&control);
+
+	for &threads in &[1, 3] {
+		let mut reader = lzma::lzma2::Parallel::new(&mixed[..], 1 << 16, threads).unwrap();
+		let mut result = Vec::new();
+
+		reader.read_to_end(&mut result).unwrap();
+		assert!(result == expected);
+		assert!(reader.is_finished());
+		assert_eq!(reader.into_inner(), b"trailing");
+	}
+
+	// the stream ends in the middle of the large segment
+	let mut reader = lzma::lzma2::Parallel::new(&mixed[.. mixed.len() / 2], 1 << 16, 3).unwrap();
+	assert!(reader.read_to_end(&mut Vec::new()).is_err());
+
+	// a single block without sizes, as written by other tools
+	let     check = lzma::xz::Check::Crc32;
+	let mut xz    = Vec::new();
+	lzma::xz::Flags { check: check }.write_header(&mut xz).unwrap();
+
+	let mut block = lzma::xz::Block { size: 0, compressed: None, uncompressed: None,
+		filters: vec![lzma::xz::Filter::Lzma2(1 << 16)] };
+	xz.extend_from_slice(&block.encode().unwrap());
+	xz.extend_from_slice(&stream);
+
+	while xz.len() % 4 != 0 {
+		xz.push(0);
+	}
+
+	let mut hasher = lzma::xz::Hasher::new(check);
+	hasher.update(&control);
+	xz.extend_from_slice(&hasher.value().unwrap());
+
+	let record = lzma::xz::Record {
+		unpadded:     block.size + stream.len() as u64 + check.size() as u64,
+		uncompressed: control.len() as u64 };
+	let size = lzma::xz::Index::write(&mut xz, &[record]).unwrap();
+	lzma::xz::Flags { check: check }.write_footer(&mut xz, size).unwrap();
+
+	let mut reader = lzma::xz::Parallel::new(Cursor::new(&xz), 3).unwrap();
+	let mut result = Vec::new();
+
+	reader.read_to_end(&mut result).unwrap();
+	assert_eq!(control, result);
+}
+
+#[test]
+fn lzma2_parallel_read_after_error() {
+	let mut data = Vec::new();
+
+	while data.len() < 60000 {
+		data.extend_from_slice(&control());
+	}
+
+	// invalid properties in the first chunk of the second segment
+	let mut stream = lzma2_segments(&data);
+	let     second = lzma2_segments(&data[.. 20000]).len() - 1;
+	assert!(stream[second] >= 0xe0);
+	stream[second + 5] = 0xff;
+
+	for &threads in &[1, 2, 3] {
+		let mut reader = lzma::lzma2::Parallel::new(&stream[..], 1 << 16, threads).unwrap();
+		let mut result = Vec::new();
+
+		let error = reader.read_to_end(&mut result).unwrap_err().to_string();
+		assert!(result.len() <= 20000);
+
+		for _ in 0 .. 3 {
+			assert_eq!(reader.read(&mut [0u8; 16]).unwrap_err().to_string(), error);
+		}
+	}
+}
+
+// bytes that don't compress
+fn noise(length: usize) -> Vec<u8> {
+	let mut state = 0x2545f491u32;
+
+	(0 .. length).map(|_| {
+		state ^= state << 13;
+		state ^= state >> 17;
+		state ^= state << 5;
+
+		(state >> 24) as u8
+	}).collect()
+}
+
+#[test]
+fn lzma2_uncompressed() {
+	let properties = lzma::Properties { lc: 3, lp: 0, pb: 2, dictionary: 1 << 16, uncompressed: None };
+
+	let noise = noise(200000);
+	let mut writer = lzma::lzma2::write(Vec::new(), properties).unwrap();
+	writer.write_all(&noise).unwrap();
+
+	// stored with a three bytes header per chunk, the first one resetting the
+	// dictionary
+	let stream = writer.finish().unwrap();
+	assert!(stream.len() < noise.len() + 64);
+	assert_eq!(stream[0], 0x01);
+
+	let mut result = Vec::new();
+	lzma::lzma2::read(&stream[..], 1 << 16).unwrap().read_to_end(&mut result).unwrap();
+	assert_eq!(noise, result);
+
+	// compressed chunks after stored ones reset the state
+	let mut mixed = control();
+	mixed.extend_from_slice(&noise[.. 100000]);
+	mixed.extend_from_slice(&control());
+	mixed.extend_from_slice(&noise[.. 1000]);
+	mixed.extend_from_slice(&control());
+
+	let compressed = encode(&mixed, 1, 1 << 20);
+
+	let mut result = Vec::new();
+	lzma::xz::read(Cursor::new(&compressed)).unwrap().read_to_end(&mut result).unwrap();
+	assert_eq!(mixed, result);
+}
+
+#[test]
+fn lzma2_uncompressed_reset() {
+	let properties = lzma::Properties { lc: 0, lp: 0, pb: 0, dictionary: 1 << 16, uncompressed: None };
+
+	let mut writer = lzma::lzma2::write(Vec::new(), properties).unwrap();
+	writer.write_all(b"abcabcabcabcabcabcabcabcabcabcabcabcabcabc").unwrap();
+
+	let chunk = writer.finish().unwrap();
+	let chunk = &chunk[.. chunk.len() - 1];
+	assert_eq!(chunk[0] & 0xe0, 0xe0);
+
+	// the same chunk resetting only the state, which is fine right after it as
+	// literals and matches don't depend on the position with these properties
+	let mut stream = chunk.to_vec();
+	stream.push(0xa0 | (chunk[0] & 0x1f));
+	stream.extend_from_slice(&chunk[1 .. 5]);
+	stream.extend_from_slice(&chunk[6 ..]);
+	stream.push(0x00);
+
+	let mut result = Vec::new();
+	lzma::lzma2::read(&stream[..], 1 << 16).unwrap().read_to_end(&mut result).unwrap();
+	assert_eq!(result.len(), 84);
+
+	// but a dictionary reset in a stored chunk asks for new properties
+	let mut stream = chunk.to_vec();
+	stream.extend_from_slice(&[0x01, 0x00, 0x00, b'a']);
+	stream.push(0xa0 | (chunk[0] & 0x1f));
+	stream.extend_from_slice(&chunk[1 .. 5]);
+	stream.extend_from_slice(&chunk[6 ..]);
+	stream.push(0x00);
+
+	let error = lzma::lzma2::read(&stream[..], 1 << 16).unwrap().read_to_end(&mut Vec::new()).unwrap_err();
+	assert_eq!(error.to_string(), lzma::Error::Corrupted.to_string());
+
+	let mut reader = lzma::lzma2::Parallel::new(&stream[..], 1 << 16, 2).unwrap();
+	assert!(reader.read_to_end(&mut Vec::new()).is_err());
+}
+
+#[test]
+fn settings_after_write() {
+	let mut writer = lzma::xz::Writer::new(Vec::new(), lzma::Properties {
+		lc: 3, lp: 0, pb: 2, dictionary: 1 << 16, uncompressed: None }, 1).unwrap();
+	writer.write_all(b"data").unwrap();
+
+	match writer.set_check(lzma::xz::Check::Crc32) {
+		Err(lzma::Error::InvalidState) => (),
+		_                              => unreachable!(),
+	}
+
+	match writer.set_block_size(1000) {
+		Err(lzma::Error::InvalidState) => (),
+		_                              => unreachable!(),
+	}
+}
+
+#[test]
+fn parallel_large_blocks() {
+	// blocks decoded by the workers, sent back in pieces
+	let mut large = Vec::new();
+
+	while large.len() < 3 << 20 {
+		large.extend_from_slice(&control());
+	}
+
+	let compressed = encode(&large, 2, 2 << 20);
+
+	for &threads in &[2, 3] {
+		let mut result = Vec::new();
+		lzma::xz::Parallel::new(Cursor::new(&compressed), threads).unwrap().read_to_end(&mut result).unwrap();
+		assert!(result == large);
+	}
+
+	// a block too big to be buffered for the workers is decoded as it's read,
+	// its sizes are still verified
+	let noise = noise(9 << 20);
+	let mut stream = Vec::new();
+
+	for (i, piece) in noise.chunks(1 << 16).enumerate() {
+		stream.push(if i == 0 { 0x01 } else { 0x02 });
+		stream.push(((piece.len() - 1) >> 8) as u8);
+		stream.push((piece.len() - 1) as u8);
+		stream.extend_from_slice(piece);
+	}
+
+	stream.push(0x00);
+
+	let     check = lzma::xz::Check::Crc32;
+	let mut xz    = Vec::new();
+	lzma::xz::Flags { check: check }.write_header(&mut xz).unwrap();
+
+	let mut block = lzma::xz::Block { size: 0, compressed: Some(stream.len() as u64),
+		uncompressed: Some(noise.len() as u64), filters: vec![lzma::xz::Filter::Lzma2(1 << 16)] };
+	xz.extend_from_slice(&block.encode().unwrap());
+	xz.extend_from_slice(&stream);
+
+	while xz.len() % 4 != 0 {
+		xz.push(0);
+	}
+
+	let mut hasher = lzma::xz::Hasher::new(check);
+	hasher.update(&noise);
+	xz.extend_from_slice(&hasher.value().unwrap());
+
+	let record = lzma::xz::Record {
+		unpadded:     block.size + stream.len() as u64 + check.size() as u64,
+		uncompressed: noise.len() as u64 };
+	let size = lzma::xz::Index::write(&mut xz, &[record]).unwrap();
+	lzma::xz::Flags { check: check }.write_footer(&mut xz, size).unwrap();
+
+	let mut result = Vec::new();
+	lzma::xz::Parallel::new(Cursor::new(&xz), 3).unwrap().read_to_end(&mut result).unwrap();
+	assert!(result == noise);
+}
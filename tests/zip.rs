@@ -0,0 +1,71 @@
+use std::io::{Read, Write, Cursor};
+use std::fs::File;
+
+extern crate lzma;
+
+fn control() -> String {
+	let mut reader  = File::open("tests/assets/a.txt").unwrap();
+	let mut control = String::new();
+
+	reader.read_to_string(&mut control).unwrap();
+
+	control
+}
+
+#[test]
+fn read() {
+	let     string = control();
+	let mut reader = lzma::zip::read(File::open("tests/assets/a_zip.lzma").unwrap(), string.len() as u64, 0).unwrap();
+	let mut result = String::new();
+
+	reader.read_to_string(&mut result).unwrap();
+
+	assert_eq!(string, result);
+}
+
+#[test]
+fn roundtrip() {
+	let string = control();
+
+	for &uncompressed in &[None, Some(string.len() as u64)] {
+		let mut writer = lzma::zip::write(Vec::new(), lzma::Properties {
+			lc: 3, lp: 0, pb: 2, dictionary: 1 << 16, uncompressed: uncompressed }).unwrap();
+
+		writer.write_all(string.as_bytes()).unwrap();
+
+		let     buffer = writer.finish().unwrap();
+		let     flags  = if uncompressed.is_none() { lzma::zip::MARKER_FLAG } else { 0 };
+		let mut reader = lzma::zip::read(Cursor::new(buffer), string.len() as u64, flags).unwrap();
+		let mut result = String::new();
+
+		reader.read_to_string(&mut result).unwrap();
+
+		assert_eq!(string, result);
+	}
+}
+
+#[test]
+fn marker() {
+	let string = control();
+
+	for &uncompressed in &[None, Some(string.len() as u64)] {
+		let mut writer = lzma::zip::write(Vec::new(), lzma::Properties {
+			lc: 3, lp: 0, pb: 2, dictionary: 1 << 16, uncompressed: uncompressed }).unwrap();
+
+		writer.write_all(string.as_bytes()).unwrap();
+
+		// the marker flag needs the marker right after the size
+		let buffer = writer.finish().unwrap();
+
+		for &size in &[10, string.len() as u64, string.len() as u64 + 10] {
+			let result = lzma::zip::read(Cursor::new(&buffer), size, lzma::zip::MARKER_FLAG).unwrap().read_to_end(&mut Vec::new());
+
+			if uncompressed.is_none() && size == string.len() as u64 {
+				assert_eq!(result.unwrap(), string.len());
+			}
+			else {
+				assert!(result.is_err());
+			}
+		}
+	}
+}